@@ -0,0 +1,166 @@
+// 長時間，乱数で操作を続け，BTreeMapとの一致とcheck_invariantsを定期的に確かめる．
+// 終わりに，確保したまま解放されていないbyteがないかも確かめる．
+//
+//   cargo run --release --example soak -- --secs 3600 --threads 8 --mix 50:30:20 --skew 2
+//
+// --mixは挿入:削除:探索の比．--skewは1で一様，大きいほど小さいkeyに偏る．
+// このcrateに並行なmapはないので，threadごとに別のlistを回す．
+use skip_list_rs::{SkipList, XorShift};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::BTreeMap,
+    env,
+    sync::atomic::{AtomicIsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+struct Counting;
+
+static LIVE: AtomicIsize = AtomicIsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size() as isize, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[derive(Clone)]
+struct Config {
+    secs: u64,
+    threads: usize,
+    keys: u64,
+    mix: [u64; 3],
+    skew: u32,
+    check_every: u64,
+    seed: u64,
+}
+
+fn parse() -> Config {
+    let mut config = Config {
+        secs: 60,
+        threads: 1,
+        keys: 10_000,
+        mix: [50, 30, 20],
+        skew: 1,
+        check_every: 100_000,
+        seed: 1,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(name) = args.next() {
+        let value = args
+            .next()
+            .unwrap_or_else(|| panic!("{name} needs a value"));
+        let int = || {
+            value
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("bad {name}: {value}"))
+        };
+        match name.as_str() {
+            "--secs" => config.secs = int(),
+            "--threads" => config.threads = int() as usize,
+            "--keys" => config.keys = int().max(1),
+            "--skew" => config.skew = int().max(1) as u32,
+            "--check-every" => config.check_every = int().max(1),
+            "--seed" => config.seed = int(),
+            "--mix" => {
+                let parts: Vec<u64> = value.split(':').map(|e| e.parse().unwrap()).collect();
+                config.mix = parts.try_into().expect("--mix is insert:remove:search");
+                assert!(
+                    config.mix.iter().sum::<u64>() > 0,
+                    "--mix must not be all zero"
+                );
+            }
+            _ => panic!("unknown option {name}"),
+        }
+    }
+    config
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // [0, 1)の一様な値をskew乗すると，0の近くに偏る．
+    fn key(&mut self, keys: u64, skew: u32) -> u64 {
+        let unit = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        (unit.powi(skew as i32) * keys as f64) as u64
+    }
+}
+
+fn check(list: &SkipList<u64, u64, XorShift>, model: &BTreeMap<u64, u64>) {
+    list.check_invariants().unwrap();
+    assert_eq!(list.count(), model.len());
+    assert!(list.iter().eq(model.iter()));
+}
+
+// 1回りごとにlistを作り直し，deadlineまで続ける．行った操作の数を返す．
+fn run(config: Config, id: u64) -> u64 {
+    let deadline = Instant::now() + Duration::from_secs(config.secs);
+    let mut rng = Rng(config
+        .seed
+        .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        .wrapping_add(id)
+        | 1);
+    let [insert, remove, _] = config.mix;
+    let total: u64 = config.mix.iter().sum();
+    let mut ops = 0;
+
+    while Instant::now() < deadline {
+        let mut list = SkipList::new(XorShift::new(rng.next()));
+        let mut model = BTreeMap::new();
+        for _ in 0..config.check_every {
+            let key = rng.key(config.keys, config.skew);
+            let op = rng.next() % total;
+            if op < insert {
+                let value = rng.next();
+                assert_eq!(list.insert(key, value).is_ok(), !model.contains_key(&key));
+                model.entry(key).or_insert(value);
+            } else if op < insert + remove {
+                assert_eq!(list.remove(&key).ok(), model.remove_entry(&key));
+            } else {
+                assert_eq!(list.search(&key), model.get(&key));
+            }
+        }
+        ops += config.check_every;
+        check(&list, &model);
+    }
+    ops
+}
+
+fn main() {
+    let config = parse();
+    let baseline = LIVE.load(Ordering::Relaxed);
+    let started = Instant::now();
+
+    let handles: Vec<_> = (0..config.threads as u64)
+        .map(|id| {
+            let config = config.clone();
+            thread::spawn(move || run(config, id))
+        })
+        .collect();
+    let ops: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+    let leaked = LIVE.load(Ordering::Relaxed) - baseline;
+    let elapsed = started.elapsed().as_secs_f64();
+    println!(
+        "{ops} operations in {elapsed:.1}s ({:.0} ops/s), {leaked} bytes still allocated",
+        ops as f64 / elapsed
+    );
+    assert_eq!(leaked, 0, "nodes were leaked");
+}
@@ -0,0 +1,80 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use skip_list_rs::{InsertError, SkipList};
+use std::collections::BTreeMap;
+
+// 入力の先頭8byteをGeneratorのseedにし，残りを2byteずつ(操作, key)として読む．
+fuzz_target!(|data: &[u8]| {
+    let Some((seed, ops)) = data.split_first_chunk::<8>() else {
+        return;
+    };
+    let mut state = u64::from_le_bytes(*seed) | 1;
+    let gen = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state & 1 == 1
+    };
+
+    let mut list = SkipList::new(gen);
+    let mut model = BTreeMap::new();
+
+    for op in ops.chunks_exact(2) {
+        let (op, key) = (op[0], op[1]);
+        let value = u16::from(op) << 8 | u16::from(key);
+        match op % 10 {
+            0 | 1 => {
+                let expected = match model.contains_key(&key) {
+                    true => Err(InsertError::Occupied(key, value)),
+                    false => Ok(()),
+                };
+                assert_eq!(list.insert(key, value), expected);
+                model.entry(key).or_insert(value);
+            }
+            2 => {
+                let expected = match model.last_key_value() {
+                    Some((last, _)) if *last >= key => model.contains_key(&key),
+                    _ => false,
+                };
+                assert_eq!(list.append(key, value).is_err(), expected);
+                model.entry(key).or_insert(value);
+            }
+            3 => assert_eq!(list.remove(&key).ok(), model.remove_entry(&key)),
+            4 => assert_eq!(list.search(&key), model.get(&key)),
+            5 => {
+                if let Some(value) = list.search_mut(&key) {
+                    *value = value.wrapping_add(1);
+                }
+                if let Some(value) = model.get_mut(&key) {
+                    *value = value.wrapping_add(1);
+                }
+            }
+            6 => {
+                assert_eq!(list.first(), model.first_key_value());
+                assert_eq!(list.last(), model.last_key_value());
+            }
+            7 => match key % 2 {
+                0 => assert_eq!(list.pop_first(), model.pop_first()),
+                _ => assert_eq!(list.pop_last(), model.pop_last()),
+            },
+            8 => {
+                let end = key.saturating_add(op / 10);
+                assert!(list.range(key..end).eq(model.range(key..end)));
+            }
+            _ => {
+                if key % 16 == 0 {
+                    let removed = model.range(..key).count();
+                    assert_eq!(list.truncate_before(&key), removed);
+                    model = model.split_off(&key);
+                } else {
+                    assert!(list.iter().eq(model.iter()));
+                }
+            }
+        }
+        assert_eq!(list.count(), model.len());
+        assert_eq!(list.check_invariants(), Ok(()));
+    }
+
+    assert!(list.into_iter().eq(model.into_iter()));
+});
@@ -0,0 +1,302 @@
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+use crate::{
+    iter::IntoIter,
+    node::{Link, Node},
+    Generator, Operation, SkipList,
+};
+
+// 1段上げるのに続けて要るtrueの数の上限．昇格の確率は1/2から1/4まで下げる．
+// 比較の回数はどちらもおよそ2 log2(n)で，1/4ならtowerのlinkが半分で済む．
+const MAX_STRIDE: usize = 2;
+// これより少ないentryでは比較の回数が定数項に埋もれるので，strideを変えない．
+const MIN_COUNT: usize = 64;
+
+// 昇格の確率は1/2^stride，limitは今のentryの数で新しいtowerが届く高さの上限．
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tuning {
+    pub stride: usize,
+    pub limit: usize,
+}
+
+// searchとinsertとremoveの比較の回数をwindow回ごとに平均し，entryの数のbit長との比を見る．
+// 確率pでの比較の回数はおよそlog(n) / (p log(1 / p))なので，その比は2^stride / strideに近いはずで，
+// 大きく上回ればtowerが足りないのでstrideを下げ，下回るほど速ければ疎なtowerでよいのでstrideを上げる．
+// &selfの探索から呼ぶので，すべてatomicで持つ．
+pub(crate) struct Tuner {
+    window: usize,
+    stride: AtomicUsize,
+    samples: AtomicUsize,
+    comparisons: AtomicUsize,
+}
+
+fn expected(stride: usize) -> f64 {
+    (1 << stride) as f64 / stride as f64
+}
+
+impl Tuner {
+    pub(crate) fn observe(&self, op: Operation, comparisons: usize, count: usize) {
+        // filterやhash indexで辿らずに済んだ探索は，形の良し悪しを表さない．
+        let walked = matches!(
+            op,
+            Operation::Search | Operation::Insert | Operation::Remove
+        );
+        if !walked || comparisons == 0 {
+            return;
+        }
+        self.comparisons.fetch_add(comparisons, Relaxed);
+        if self.samples.fetch_add(1, Relaxed) + 1 < self.window {
+            return;
+        }
+        self.samples.store(0, Relaxed);
+        let total = self.comparisons.swap(0, Relaxed);
+        if count < MIN_COUNT {
+            return;
+        }
+
+        let bits = (usize::BITS - count.leading_zeros()) as f64;
+        let ratio = total as f64 / self.window as f64 / bits;
+        let stride = self.stride.load(Relaxed);
+        if stride > 1 && ratio > expected(stride) * 1.5 {
+            self.stride.store(stride - 1, Relaxed);
+        } else if stride < MAX_STRIDE && ratio < expected(stride) {
+            self.stride.store(stride + 1, Relaxed);
+        }
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 有効にすると，search_mutで見つけたnodeを，Generatorが高さの数だけ続けてtrueを返したときに1段高くする．
+    // よく引くkeyほど高くなり，splay木のように偏った探索を短くする．低くするのはcompactで行う．
+    pub fn set_self_adjusting(&mut self, enabled: bool) {
+        self.self_adjusting = enabled;
+    }
+
+    // 各towerの高さを，Generatorで選び直した高さと今の高さより1低い高さの大きいほうにして，先頭から順につなぎ直す．
+    // 引かれなくなったkeyは呼ぶたびに1段ずつ下がり，選び直した高さに落ち着く．高さは今のentryの数の上限に丸める．
+    pub fn compact(&mut self) {
+        let (stride, limit) = self.promotion();
+        let limit = limit.max(1);
+        let mut rest = IntoIter {
+            node: self.nodes[0].next,
+        };
+        self.count = 0;
+        self.nodes = vec![Link::null()];
+        self.tails = vec![Link::null()];
+
+        while let Some(node) = rest.node.take() {
+            let mut fresh = 1;
+            while fresh < limit && (0..stride).all(|_| self.gen.gen()) {
+                fresh += 1;
+            }
+            let level = fresh.max(node.level() - 1).min(limit);
+            rest.node = node.nexts()[0].next;
+
+            let node = if level == node.level() {
+                node.nexts_mut().fill(Link::null());
+                node
+            } else {
+                let (key, value) = node.dispose();
+                let node = Node::new(key, value, level);
+                self.allocated(node);
+                node
+            };
+            self.push_back(node);
+        }
+        self.rebuild_hash_index();
+        self.debug_check(true);
+    }
+
+    // 以後，window回の探索ごとに昇格の確率を選び直し，新しいnodeとcompactに使う．
+    // 既にあるtowerは変えないので，大きく育ったり縮んだりした後はcompactで組み直す．
+    pub fn enable_tuning(&mut self, window: usize) {
+        assert!(window > 0, "window must be positive");
+        self.tuner = Some(Tuner {
+            window,
+            stride: AtomicUsize::new(1),
+            samples: AtomicUsize::new(0),
+            comparisons: AtomicUsize::new(0),
+        });
+    }
+
+    pub fn disable_tuning(&mut self) {
+        self.tuner = None;
+    }
+
+    pub fn tuning(&self) -> Option<Tuning> {
+        self.tuner.as_ref()?;
+        let (stride, limit) = self.promotion();
+        Some(Tuning { stride, limit })
+    }
+
+    // strideを上げたぶん，上限も1段に数bitをまとめて低くする．
+    pub(crate) fn promotion(&self) -> (usize, usize) {
+        let limit = self.level_limit();
+        match &self.tuner {
+            Some(tuner) => {
+                let stride = tuner.stride.load(Relaxed);
+                (stride, limit.min(limit.div_ceil(stride) + 1))
+            }
+            None => (1, limit),
+        }
+    }
+
+    // 上限に届いていなければ，確率で1段高いnodeに作り直してつなぎ直す．中身は変わらないのでdigestもfilterもそのままで，hash indexだけ引き直す．
+    pub(crate) fn promote(&mut self, node: Node<K, V>) -> Node<K, V> {
+        let level = node.level();
+        if level >= self.level_limit() || !(0..level).all(|_| self.gen.gen()) {
+            return node;
+        }
+        let key = node.key();
+        let Ok(removed) = self.remove_by(|next, _| next.cmp(key)) else {
+            unreachable!("the promoted node is not in the list")
+        };
+        self.index_remove(removed);
+        let (key, value) = removed.dispose();
+        let node = Node::new(key, value, level + 1);
+        self.allocated(node);
+        match self.link_by(|next, _, key| next.cmp(key), node) {
+            Ok((node, _)) => {
+                self.index_add(node);
+                node
+            }
+            Err(_) => unreachable!("the promoted key is still in the list"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::test_util::{from_levels, render};
+    use crate::{Generator, SkipList, Tuning};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::sync::atomic::Ordering::Relaxed;
+
+    fn level_of<G: Generator<bool>>(list: &SkipList<i32, i32, G>, key: i32) -> usize {
+        (0..list.height())
+            .take_while(|level| list.level_iter(*level).any(|(k, _)| *k == key))
+            .count()
+    }
+
+    #[mockalloc::test]
+    fn self_adjusting() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..1000 {
+            list.insert(key, key).unwrap();
+        }
+
+        // 無効なら形を変えない．
+        let before = render(&list);
+        for _ in 0..100 {
+            *list.search_mut(&500).unwrap() += 1;
+        }
+        assert_eq!(render(&list), before);
+
+        list.set_self_adjusting(true);
+        for _ in 0..2000 {
+            *list.search_mut(&500).unwrap() += 1;
+        }
+        let hot = level_of(&list, 500);
+        assert!(hot >= 6, "{hot}");
+        assert_eq!(list.search(&500), Some(&2600));
+        assert_eq!(list.search_mut(&1000), None);
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+
+        // 引かれないkeyは1回に1段までしか下がらない．
+        list.compact();
+        assert!(level_of(&list, 500) >= hot - 1);
+        for _ in 0..20 {
+            list.compact();
+        }
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.iter().map(|(k, _)| *k).eq(0..1000));
+    }
+
+    #[mockalloc::test]
+    fn compact_shrinks() {
+        let mut list = SkipList::new(|| true);
+        for key in 0..1000 {
+            list.insert(key, key).unwrap();
+        }
+        for key in 8..1000 {
+            list.remove(&key).unwrap();
+        }
+        list.compact();
+        assert_eq!(list.height(), 4);
+        check_spans(&list);
+
+        let mut empty = SkipList::<i32, i32, _>::new(|| true);
+        empty.compact();
+        assert_eq!(empty.height(), 1);
+    }
+
+    #[mockalloc::test]
+    fn tuning() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = from_levels(
+            Gen::standard(SmallRng::from_entropy()),
+            (0..4096).map(|key| (key, key, 1)),
+        );
+        assert_eq!(list.tuning(), None);
+        list.enable_tuning(100);
+        assert_eq!(
+            list.tuning(),
+            Some(Tuning {
+                stride: 1,
+                limit: 13
+            })
+        );
+
+        // towerのないlistでは比較が多すぎるので，strideを下げる．
+        list.tuner.as_ref().unwrap().stride.store(2, Relaxed);
+        assert_eq!(
+            list.tuning(),
+            Some(Tuning {
+                stride: 2,
+                limit: 8
+            })
+        );
+        for _ in 0..100 {
+            list.search(&rng.gen_range(0..4096));
+        }
+        assert_eq!(list.tuning().unwrap().stride, 1);
+
+        // 組み直せば十分速いので，疎なtowerに上げる．
+        for _ in 0..20 {
+            list.compact();
+        }
+        for _ in 0..100 {
+            list.search(&rng.gen_range(0..4096));
+        }
+        assert_eq!(
+            list.tuning(),
+            Some(Tuning {
+                stride: 2,
+                limit: 8
+            })
+        );
+        for _ in 0..20 {
+            list.compact();
+        }
+        assert!(list.height() <= 8);
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+
+        // 縮めば上限も下がる．残った高いtowerで比較が増えると，strideも下がりうる．
+        for key in 64..4096 {
+            list.remove(&key).unwrap();
+        }
+        let Tuning { stride, limit } = list.tuning().unwrap();
+        assert_eq!(limit, if stride == 2 { 5 } else { 7 });
+        for _ in 0..1000 {
+            list.insert(rng.gen_range(64..128), 0).ok();
+        }
+        assert!(list.iter().all(|(k, v)| *k < 64 || *v == 0));
+        list.disable_tuning();
+        assert_eq!(list.tuning(), None);
+    }
+}
@@ -0,0 +1,318 @@
+use std::ops::{Add, Bound, RangeBounds};
+
+use crate::{
+    node::{Link, MaybeNode},
+    Generator, InsertError, RemoveError, SkipList,
+};
+
+// combineは結合的で，emptyはその単位元であること．
+pub trait Monoid<V> {
+    fn empty() -> Self;
+    fn of(value: &V) -> Self;
+    fn combine(&self, other: &Self) -> Self;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sum<T>(pub T);
+
+impl<T: Add<Output = T> + Default + Clone> Monoid<T> for Sum<T> {
+    fn empty() -> Self {
+        Sum(T::default())
+    }
+
+    fn of(value: &T) -> Self {
+        Sum(value.clone())
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Sum(self.0.clone() + other.0.clone())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Min<T>(pub Option<T>);
+
+impl<T: Ord + Clone> Monoid<T> for Min<T> {
+    fn empty() -> Self {
+        Min(None)
+    }
+
+    fn of(value: &T) -> Self {
+        Min(Some(value.clone()))
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Min(Some(a.min(b).clone())),
+            (a, b) => Min(a.clone().or(b.clone())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Max<T>(pub Option<T>);
+
+impl<T: Ord + Clone> Monoid<T> for Max<T> {
+    fn empty() -> Self {
+        Max(None)
+    }
+
+    fn of(value: &T) -> Self {
+        Max(Some(value.clone()))
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Max(Some(a.max(b).clone())),
+            (a, b) => Max(a.clone().or(b.clone())),
+        }
+    }
+}
+
+type Entry<V, M> = (V, Vec<M>);
+
+// 各linkに，その飛び越す範囲(始点を除き終点を含む)のvalueの集約を持たせる．
+// headの集約はheadsに，nodeの集約はvalueと並べて持つ．
+// 挿入や削除のあとは，影響を受けたlinkを下のlevelから順に一つ下のlevelのlinkを辿って計算し直す．
+pub struct AggregateSkipList<K: Ord, V, M: Monoid<V>, G: Generator<bool>> {
+    pub(crate) list: SkipList<K, Entry<V, M>, G>,
+    heads: Vec<M>,
+}
+
+impl<K: Ord, V, M: Monoid<V>, G: Generator<bool>> AggregateSkipList<K, V, M, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            heads: vec![M::empty()],
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        let inserted = self
+            .list
+            .insert_by(|next, _, key| next.cmp(key), key, (value, Vec::new()));
+        let (node, _) = inserted.map_err(|(key, (value, _))| InsertError::Occupied(key, value))?;
+
+        node.value_mut().1 = (0..node.level()).map(|_| M::empty()).collect();
+        let len = self.list.nodes.len();
+        self.heads.resize_with(len, M::empty);
+
+        let preds = self.preds(node.key());
+        for (level, pred) in preds.into_iter().enumerate() {
+            self.recompute(pred, level);
+            if level < node.level() {
+                self.recompute(node.into(), level);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        let (value, _) = self.list.search(key)?;
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.list.contains_key(key)
+    }
+
+    // valueを書き換え，それを含む集約を計算し直す．
+    pub fn update<R>(&mut self, key: &K, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        let (value, _) = self.list.search_mut(key)?;
+        let result = f(value);
+        self.recompute_all(self.preds(key));
+        Some(result)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let preds = self.preds(key);
+        let (key, (value, _)) = self.list.remove(key)?;
+        self.recompute_all(preds);
+        Ok((key, value))
+    }
+
+    pub fn aggregate(&self) -> M {
+        self.range_aggregate(..)
+    }
+
+    // 範囲の直前のnodeから，範囲を超えない最も高いlinkを辿って集約を合わせる．
+    pub fn range_aggregate<R: RangeBounds<K>>(&self, range: R) -> M {
+        let before_start = |key: &K| match range.start_bound() {
+            Bound::Included(b) => key < b,
+            Bound::Excluded(b) => key <= b,
+            Bound::Unbounded => false,
+        };
+        let before_end = |key: &K| match range.end_bound() {
+            Bound::Included(b) => key <= b,
+            Bound::Excluded(b) => key < b,
+            Bound::Unbounded => true,
+        };
+
+        let mut acc = M::empty();
+        let mut from: MaybeNode<K, Entry<V, M>> = match self.list.find_last(before_start) {
+            Some(node) => node.into(),
+            None => MaybeNode::null(),
+        };
+
+        loop {
+            let links = self.links(from);
+            let step = (0..links.len())
+                .rev()
+                .find(|&level| match links[level].next.take() {
+                    Some(next) => before_end(next.key()),
+                    None => false,
+                });
+            let Some(level) = step else {
+                break;
+            };
+
+            acc = acc.combine(&self.aggs(from)[level]);
+            from = links[level].next;
+        }
+
+        acc
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.list.iter().map(|(key, (value, _))| (key, value))
+    }
+
+    // 各levelでkeyより前にある最後のnode(nullはhead)．
+    fn preds(&self, key: &K) -> Vec<MaybeNode<K, Entry<V, M>>> {
+        let mut forwards = self.list.nodes.as_slice();
+        let mut from = MaybeNode::null();
+        let mut preds = vec![MaybeNode::null(); forwards.len()];
+
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                if next.key() >= key {
+                    break;
+                }
+                from = next.into();
+                forwards = next.nexts();
+            }
+            preds[level] = from;
+        }
+
+        preds
+    }
+
+    fn recompute_all(&mut self, preds: Vec<MaybeNode<K, Entry<V, M>>>) {
+        for (level, pred) in preds.into_iter().enumerate() {
+            self.recompute(pred, level);
+        }
+    }
+
+    // fromのlevelのlinkの集約を，level - 1のlinkから計算し直す．
+    fn recompute(&mut self, from: MaybeNode<K, Entry<V, M>>, level: usize) {
+        let target = self.links(from)[level].next;
+        let agg = match target.take() {
+            None => M::empty(),
+            Some(node) if level == 0 => M::of(&node.value().0),
+            Some(_) => {
+                let mut acc = M::empty();
+                let mut cur = from;
+                while cur != target {
+                    acc = acc.combine(&self.aggs(cur)[level - 1]);
+                    cur = self.links(cur)[level - 1].next;
+                }
+                acc
+            }
+        };
+
+        match from.take() {
+            Some(node) => node.value_mut().1[level] = agg,
+            None => self.heads[level] = agg,
+        }
+    }
+
+    fn links(&self, from: MaybeNode<K, Entry<V, M>>) -> &[Link<K, Entry<V, M>>] {
+        match from.take() {
+            Some(node) => node.nexts(),
+            None => &self.list.nodes,
+        }
+    }
+
+    fn aggs(&self, from: MaybeNode<K, Entry<V, M>>) -> &[M] {
+        match from.take() {
+            Some(node) => &node.value().1,
+            None => &self.heads,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{AggregateSkipList, Max, Min, Sum};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[mockalloc::test]
+    fn sum_min_max() {
+        let mut sum =
+            AggregateSkipList::<_, _, Sum<i32>, _>::new(Gen::standard(SmallRng::from_entropy()));
+        let mut min =
+            AggregateSkipList::<_, _, Min<i32>, _>::new(Gen::standard(SmallRng::from_entropy()));
+        let mut max =
+            AggregateSkipList::<_, _, Max<i32>, _>::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..10 {
+            sum.insert(key, key * 10).unwrap();
+            min.insert(key, 5 - key).unwrap();
+            max.insert(key, key % 4).unwrap();
+        }
+
+        assert_eq!(sum.aggregate(), Sum(450));
+        assert_eq!(sum.range_aggregate(2..5), Sum(90));
+        assert_eq!(sum.range_aggregate(5..5), Sum(0));
+        assert_eq!(min.range_aggregate(..=3), Min(Some(2)));
+        assert_eq!(min.range_aggregate(20..), Min(None));
+        assert_eq!(max.range_aggregate(4..7), Max(Some(2)));
+        assert_eq!(max.range_aggregate(4..8), Max(Some(3)));
+
+        assert_eq!(sum.update(&3, |v| std::mem::replace(v, 0)), Some(30));
+        assert_eq!(sum.range_aggregate(2..5), Sum(60));
+        assert_eq!(sum.remove(&4), Ok((4, 40)));
+        assert_eq!(sum.range_aggregate(2..5), Sum(20));
+        assert_eq!(sum.update(&4, |_| ()), None);
+    }
+
+    #[mockalloc::test]
+    fn random_against_btree() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list =
+            AggregateSkipList::<_, _, Sum<u64>, _>::new(Gen::standard(SmallRng::from_entropy()));
+        let mut model = BTreeMap::new();
+
+        for _ in 0..500 {
+            let key = rng.gen_range(0..100);
+            match rng.gen_range(0..3) {
+                0 => {
+                    let value = rng.gen_range(0..1000);
+                    if list.insert(key, value).is_ok() {
+                        model.insert(key, value);
+                    }
+                }
+                1 => assert_eq!(list.remove(&key).ok(), model.remove_entry(&key)),
+                _ => {
+                    let value = rng.gen_range(0..1000);
+                    list.update(&key, |v| *v = value);
+                    if let Some(v) = model.get_mut(&key) {
+                        *v = value;
+                    }
+                }
+            }
+
+            let a = rng.gen_range(0..100);
+            let b = rng.gen_range(a..=100);
+            let expected: u64 = model.range(a..b).map(|(_, v)| v).sum();
+            assert_eq!(list.range_aggregate(a..b), Sum(expected));
+        }
+        assert_eq!(list.aggregate(), Sum(model.values().sum()));
+    }
+}
@@ -0,0 +1,175 @@
+use std::cmp::Ordering;
+
+use crate::{iter::Iter, node::Node, Generator, InsertError, RemoveError, SkipList};
+
+// keyの側のnodeを指し，そのvalueで順序づける．
+struct ByValue<K: Ord, V: Ord>(Node<K, V>);
+
+impl<K: Ord, V: Ord> PartialEq for ByValue<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.value() == other.0.value()
+    }
+}
+
+impl<K: Ord, V: Ord> Eq for ByValue<K, V> {}
+
+impl<K: Ord, V: Ord> PartialOrd for ByValue<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V: Ord> Ord for ByValue<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.value().cmp(other.0.value())
+    }
+}
+
+// keyとvalueはkeysの側だけが持ち，valuesはkeysのnodeを指す．
+pub struct SkipBiMap<K: Ord, V: Ord, G: Generator<bool>> {
+    keys: SkipList<K, V, G>,
+    values: SkipList<ByValue<K, V>, (), G>,
+}
+
+impl<K: Ord, V: Ord, G: Generator<bool>> SkipBiMap<K, V, G> {
+    pub fn new(mut make_gen: impl FnMut() -> G) -> Self {
+        Self {
+            keys: SkipList::new(make_gen()),
+            values: SkipList::new(make_gen()),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.keys.count()
+    }
+
+    // keyかvalueのどちらかが既にあれば失敗する．
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        if self.contains_value(&value) {
+            return Err(InsertError::Occupied(key, value));
+        }
+        let (node, _) = self
+            .keys
+            .insert_by(|next, _, key| next.cmp(key), key, value)
+            .map_err(|(key, value)| InsertError::Occupied(key, value))?;
+
+        let inserted = self.values.insert_by(
+            |next, _, key| next.0.value().cmp(key.0.value()),
+            ByValue(node),
+            (),
+        );
+        debug_assert!(inserted.is_ok());
+        Ok(())
+    }
+
+    pub fn get_by_key(&self, key: &K) -> Option<&V> {
+        self.keys.search(key)
+    }
+
+    pub fn get_by_value(&self, value: &V) -> Option<&K> {
+        let node = self.find_value(value)?;
+        Some(node.key())
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.keys.contains_key(key)
+    }
+
+    pub fn contains_value(&self, value: &V) -> bool {
+        self.find_value(value).is_some()
+    }
+
+    pub fn remove_by_key(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let node = self
+            .keys
+            .remove_by(|next, _| next.cmp(key))
+            .map_err(|()| RemoveError::NotFound)?;
+        let value = node.value();
+        let removed = self.values.remove_by(|next, _| next.0.value().cmp(value));
+        let Ok(removed) = removed else {
+            unreachable!("every key has its value");
+        };
+        removed.dispose();
+        Ok(node.dispose())
+    }
+
+    pub fn remove_by_value(&mut self, value: &V) -> Result<(K, V), RemoveError> {
+        let removed = self
+            .values
+            .remove_by(|next, _| next.0.value().cmp(value))
+            .map_err(|()| RemoveError::NotFound)?;
+        let (ByValue(node), _) = removed.dispose();
+        let key = node.key();
+        let node = self.keys.remove_by(|next, _| next.cmp(key));
+        let Ok(node) = node else {
+            unreachable!("every value has its key");
+        };
+        Ok(node.dispose())
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.keys.iter()
+    }
+
+    // valueの順に(value, key)を返す．
+    pub fn iter_by_value(&self) -> impl Iterator<Item = (&V, &K)> {
+        self.values
+            .iter()
+            .map(|(ByValue(node), _)| (node.value(), node.key()))
+    }
+
+    fn find_value(&self, value: &V) -> Option<Node<K, V>> {
+        let ByValue(node) = self
+            .values
+            .find_first(|next| next.0.value() < value)
+            .take()?
+            .key();
+        (node.value() == value).then_some(*node)
+    }
+}
+
+// valuesはkeysが所有するnodeだけを指すので，SkipListと同じくSend/Syncにできる．
+unsafe impl<K: Ord + Send, V: Ord + Send, G: Generator<bool> + Send> Send for SkipBiMap<K, V, G> {}
+unsafe impl<K: Ord + Sync, V: Ord + Sync, G: Generator<bool> + Sync> Sync for SkipBiMap<K, V, G> {}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{InsertError, RemoveError, SkipBiMap};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn both_sides() {
+        let mut map = SkipBiMap::new(|| Gen::standard(SmallRng::from_entropy()));
+
+        map.insert(3, "c").unwrap();
+        map.insert(1, "b").unwrap();
+        map.insert(2, "a").unwrap();
+        assert_eq!(map.insert(1, "z"), Err(InsertError::Occupied(1, "z")));
+        assert_eq!(map.insert(9, "a"), Err(InsertError::Occupied(9, "a")));
+        assert_eq!(map.count(), 3);
+
+        assert_eq!(map.get_by_key(&1), Some(&"b"));
+        assert_eq!(map.get_by_value(&"a"), Some(&2));
+        assert_eq!(map.get_by_value(&"d"), None);
+        assert!(map.iter().map(|(k, _)| *k).eq([1, 2, 3]));
+        assert!(map.iter_by_value().map(|(_, k)| *k).eq([2, 1, 3]));
+
+        assert_eq!(map.remove_by_key(&2), Ok((2, "a")));
+        assert_eq!(map.remove_by_value(&"a"), Err(RemoveError::NotFound));
+        assert_eq!(map.remove_by_value(&"c"), Ok((3, "c")));
+        assert_eq!(map.remove_by_key(&3), Err(RemoveError::NotFound));
+
+        assert!(!map.contains_value(&"c"));
+        assert!(map.contains_key(&1));
+        map.insert(2, "c").unwrap();
+        assert!(map.iter_by_value().eq([(&"b", &1), (&"c", &2)]));
+    }
+
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<SkipBiMap<i32, String, fn() -> bool>>();
+    }
+}
@@ -0,0 +1,146 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::{Generator, SkipList};
+
+// keyあたり10 bit，hash 7個で，偽陽性はおよそ1%．
+const BITS_PER_KEY: usize = 10;
+const HASHES: u64 = 7;
+
+pub(crate) struct Bloom<K> {
+    hash: fn(&K) -> u64,
+    bits: Vec<u64>,
+    // 足したkeyの数．取り除いたkeyの分も減らさない．
+    added: usize,
+    capacity: usize,
+}
+
+fn key_hash<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<K> Bloom<K> {
+    fn new(hash: fn(&K) -> u64, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            hash,
+            bits: vec![0; (capacity * BITS_PER_KEY).div_ceil(64)],
+            added: 0,
+            capacity,
+        }
+    }
+
+    // 1つのhashを2つに割り，h1 + i * h2で各位置を決める．
+    fn positions(&self, key: &K) -> impl Iterator<Item = usize> {
+        let hash = (self.hash)(key);
+        let (h1, h2) = (hash, hash.rotate_left(32) | 1);
+        let len = self.bits.len() as u64 * 64;
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    fn add(&mut self, key: &K) {
+        for pos in self.positions(key) {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+        self.added += 1;
+    }
+
+    fn may_contain(&self, key: &K) -> bool {
+        self.positions(key)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+}
+
+impl<K: Ord + Hash, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 以後，searchとcontains_keyはfilterにないkeyを辿らずに返す．capacityを超えて足されると作り直して倍にする．
+    pub fn enable_bloom_filter(&mut self, capacity: usize) {
+        self.bloom = Some(Bloom::new(key_hash::<K>, capacity));
+        self.rebuild_bloom_filter();
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn disable_bloom_filter(&mut self) {
+        self.bloom = None;
+    }
+
+    // 取り除いたkeyのbitは立ったままなので，削除が続いて偽陽性が増えたら今のkeyだけで作り直す．
+    pub fn rebuild_bloom_filter(&mut self) {
+        let Some(old) = self.bloom.take() else {
+            return;
+        };
+        let mut bloom = Bloom::new(old.hash, old.capacity.max(self.count));
+        for (key, _) in self.iter() {
+            bloom.add(key);
+        }
+        self.bloom = Some(bloom);
+    }
+
+    pub(crate) fn bloom_add(&mut self, key: &K) {
+        let Some(bloom) = &mut self.bloom else {
+            return;
+        };
+        if bloom.added < bloom.capacity {
+            bloom.add(key);
+            return;
+        }
+        // keyは既にlistにつながっているので，作り直せば入る．
+        bloom.capacity = bloom.capacity.saturating_mul(2).max(self.count);
+        self.rebuild_bloom_filter();
+    }
+
+    // filterがなければ常にtrue．
+    pub(crate) fn may_contain(&self, key: &K) -> bool {
+        self.bloom
+            .as_ref()
+            .is_none_or(|bloom| bloom.may_contain(key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[mockalloc::test]
+    fn bloom_filter() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..100 {
+            list.insert(rng.gen_range(0..100_000), ()).ok();
+        }
+        list.enable_bloom_filter(10);
+        // capacityを超えても作り直すので，偽陰性は出ない．
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..100_000);
+            if list.insert(key, ()).is_ok() {
+                assert!(list.contains_key(&key));
+            }
+        }
+        let keys: Vec<i32> = list.iter().map(|(k, _)| *k).collect();
+        assert!(keys.iter().all(|key| list.contains_key(key)));
+
+        let bloom = list.bloom.as_ref().unwrap();
+        assert!(bloom.capacity >= list.count());
+        let misses = (100_000..110_000).filter(|k| list.may_contain(k)).count();
+        assert!(misses < 500, "{misses} false positives");
+
+        // 取り除いたkeyはfilterを通るが，辿った先で見つからない．
+        for key in &keys[..keys.len() / 2] {
+            list.remove(key).unwrap();
+        }
+        for key in &keys[..keys.len() / 2] {
+            assert!(!list.contains_key(key));
+        }
+        list.rebuild_bloom_filter();
+        assert!(keys[keys.len() / 2..]
+            .iter()
+            .all(|key| list.contains_key(key)));
+
+        list.disable_bloom_filter();
+        assert!(list.may_contain(&-1));
+    }
+}
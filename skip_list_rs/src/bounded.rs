@@ -0,0 +1,230 @@
+use crate::{
+    node::Node, Generator, InsertError, QueueHandle, RemoveError, SkipList, SkipPriorityQueue,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    Smallest,
+    Largest,
+    Oldest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionReason {
+    Capacity,
+    Expired,
+}
+
+pub(crate) type Evictor<K, V> = dyn FnMut(K, V, EvictionReason) + Send + Sync;
+
+type EntryNode<K, V> = Node<K, (V, Option<QueueHandle>)>;
+
+// 挿入してから溢れた分を追い出すので，追い出されるのが挿入したentry自身のこともある．
+// Oldestのときだけ挿入順のqueueを持つ．
+pub struct BoundedSkipList<K: Ord, V, G: Generator<bool>> {
+    entries: SkipList<K, (V, Option<QueueHandle>), G>,
+    order: Option<SkipPriorityQueue<(), EntryNode<K, V>, G>>,
+    policy: EvictionPolicy,
+    max_len: usize,
+    evictor: Option<Box<Evictor<K, V>>>,
+}
+
+impl<K: Ord, V, G: Generator<bool>> BoundedSkipList<K, V, G> {
+    pub fn with_max_len(
+        max_len: usize,
+        policy: EvictionPolicy,
+        mut make_gen: impl FnMut() -> G,
+    ) -> Self {
+        let entries = SkipList::new(make_gen());
+        let order = match policy {
+            EvictionPolicy::Oldest => Some(SkipPriorityQueue::new(make_gen())),
+            EvictionPolicy::Smallest | EvictionPolicy::Largest => None,
+        };
+
+        Self {
+            entries,
+            order,
+            policy,
+            max_len,
+            evictor: None,
+        }
+    }
+
+    // 以後，追い出したentryはinsertから返さずにfへ渡す．
+    pub fn on_evict(&mut self, f: impl FnMut(K, V, EvictionReason) + Send + Sync + 'static) {
+        self.evictor = Some(Box::new(f));
+    }
+
+    pub fn count(&self) -> usize {
+        self.entries.count()
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<(K, V)>, InsertError<K, V>> {
+        let inserted = self
+            .entries
+            .insert_by(|next, _, key| next.cmp(key), key, (value, None));
+        let (node, _) = inserted.map_err(|(key, (value, _))| InsertError::Occupied(key, value))?;
+
+        if let Some(order) = &mut self.order {
+            node.value_mut().1 = Some(order.push((), node));
+        }
+
+        if self.count() <= self.max_len {
+            return Ok(None);
+        }
+        let evicted = self.evict();
+        match (&mut self.evictor, evicted) {
+            (Some(evictor), Some((key, value))) => {
+                evictor(key, value, EvictionReason::Capacity);
+                Ok(None)
+            }
+            (_, evicted) => Ok(evicted),
+        }
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        let (value, _) = self.entries.search(key)?;
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let (key, (value, handle)) = self.entries.remove(key)?;
+        if let (Some(order), Some(handle)) = (&mut self.order, handle) {
+            order.remove(handle);
+        }
+        Ok((key, value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, (value, _))| (key, value))
+    }
+
+    pub(crate) fn evict(&mut self) -> Option<(K, V)> {
+        let (key, (value, _)) = match self.policy {
+            EvictionPolicy::Smallest => self.entries.pop_first()?,
+            EvictionPolicy::Largest => self.entries.pop_last()?,
+            EvictionPolicy::Oldest => {
+                let (_, node) = self.order.as_mut()?.pop()?;
+                let key = node.key();
+                let removed = self.entries.remove_by(|next, _| next.cmp(key)).ok()?;
+                removed.dispose()
+            }
+        };
+        Some((key, value))
+    }
+}
+
+// orderはentriesが所有するnodeだけを指すので，SkipListと同じくSend/Syncにできる．
+unsafe impl<K: Ord + Send, V: Send, G: Generator<bool> + Send> Send for BoundedSkipList<K, V, G> {}
+unsafe impl<K: Ord + Sync, V: Sync, G: Generator<bool> + Sync> Sync for BoundedSkipList<K, V, G> {}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{BoundedSkipList, EvictionPolicy, EvictionReason, InsertError};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::sync::{Arc, Mutex};
+
+    fn bounded(
+        max_len: usize,
+        policy: EvictionPolicy,
+    ) -> BoundedSkipList<i32, i32, Gen<bool, SmallRng, rand::distributions::Standard>> {
+        BoundedSkipList::with_max_len(max_len, policy, || Gen::standard(SmallRng::from_entropy()))
+    }
+
+    #[mockalloc::test]
+    fn evict_smallest() {
+        let mut list = bounded(3, EvictionPolicy::Smallest);
+
+        assert_eq!(list.insert(5, 50), Ok(None));
+        assert_eq!(list.insert(3, 30), Ok(None));
+        assert_eq!(list.insert(8, 80), Ok(None));
+        assert_eq!(list.insert(8, 0), Err(InsertError::Occupied(8, 0)));
+        assert_eq!(list.insert(6, 60), Ok(Some((3, 30))));
+        assert_eq!(list.insert(1, 10), Ok(Some((1, 10))));
+
+        assert!(list.iter().map(|(k, _)| *k).eq([5, 6, 8]));
+    }
+
+    #[mockalloc::test]
+    fn evict_largest() {
+        let mut list = bounded(2, EvictionPolicy::Largest);
+
+        list.insert(5, 50).unwrap();
+        list.insert(3, 30).unwrap();
+        assert_eq!(list.insert(4, 40), Ok(Some((5, 50))));
+        assert!(list.iter().map(|(k, _)| *k).eq([3, 4]));
+    }
+
+    #[mockalloc::test]
+    fn evict_oldest() {
+        let mut list = bounded(3, EvictionPolicy::Oldest);
+
+        list.insert(5, 50).unwrap();
+        list.insert(3, 30).unwrap();
+        list.insert(8, 80).unwrap();
+        assert_eq!(list.remove(&5), Ok((5, 50)));
+        assert_eq!(list.insert(1, 10), Ok(None));
+        assert_eq!(list.insert(9, 90), Ok(Some((3, 30))));
+        assert_eq!(list.insert(2, 20), Ok(Some((8, 80))));
+
+        assert!(list.iter().map(|(k, _)| *k).eq([1, 2, 9]));
+        assert_eq!(list.search(&9), Some(&90));
+        assert_eq!(list.count(), 3);
+    }
+
+    #[mockalloc::test]
+    fn on_evict() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut list = bounded(2, EvictionPolicy::Smallest);
+        let sink = evicted.clone();
+        list.on_evict(move |key, value, reason| sink.lock().unwrap().push((key, value, reason)));
+
+        list.insert(5, 50).unwrap();
+        list.insert(3, 30).unwrap();
+        assert_eq!(list.insert(4, 40), Ok(None));
+        assert_eq!(list.insert(1, 10), Ok(None));
+        assert_eq!(list.remove(&5), Ok((5, 50)));
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            [
+                (3, 30, EvictionReason::Capacity),
+                (1, 10, EvictionReason::Capacity)
+            ]
+        );
+    }
+
+    // evictorはSend + Syncなので，listごと別のthreadへ渡せる．
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<BoundedSkipList<i32, String, fn() -> bool>>();
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut list = bounded(2, EvictionPolicy::Oldest);
+        let sink = evicted.clone();
+        list.on_evict(move |key, _, _| sink.lock().unwrap().push(key));
+        let list = Mutex::new(list);
+        std::thread::scope(|scope| {
+            for key in 0..4 {
+                let list = &list;
+                scope.spawn(move || list.lock().unwrap().insert(key, key).unwrap());
+            }
+        });
+        assert_eq!(list.lock().unwrap().count(), 2);
+        assert_eq!(evicted.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{node::Node, CapacityError, Generator, RemoveError, SkipList};
+
+// nodeの確保に使った大きさの和をmax_bytes以下に保つ．headの配列やKとVの中の確保は数えない．
+// towerの高さを先に決めてから大きさを量るので，収まらないときは何も確保しない．keyがあればOccupiedを優先する．
+pub struct BudgetedSkipList<K: Ord, V, G: Generator<bool>> {
+    list: SkipList<K, V, G>,
+    used: usize,
+    max_bytes: usize,
+}
+
+impl<K: Ord, V, G: Generator<bool>> BudgetedSkipList<K, V, G> {
+    pub fn with_max_bytes(max_bytes: usize, gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            used: 0,
+            max_bytes,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), CapacityError<K, V>> {
+        let level = self.list.gen_level();
+        let size = Node::<K, V>::size_of_level(level);
+        if size > self.max_bytes - self.used {
+            if self.list.contains_key(&key) {
+                return Err(CapacityError::Occupied(key, value));
+            }
+            return Err(CapacityError::OverBudget(key, value));
+        }
+        let node = Node::new(key, value, level);
+        self.list.allocated(node);
+        self.list
+            .link_by(|next, _, key| next.cmp(key), node)
+            .map_err(|(key, value)| CapacityError::Occupied(key, value))?;
+        self.used += size;
+        Ok(())
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        self.list.search(key)
+    }
+
+    pub fn search_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.list.search_mut(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.list.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let node = self
+            .list
+            .remove_by(|next, _| next.cmp(key))
+            .map_err(|_| RemoveError::NotFound)?;
+        self.used -= node.allocated_size();
+        Ok(node.dispose())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.list.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::node::Node;
+    use crate::test::Gen;
+    use crate::{BudgetedSkipList, CapacityError, RemoveError};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[mockalloc::test]
+    fn over_budget() {
+        let size = Node::<u32, u32>::size_of_level(1);
+        let mut list = BudgetedSkipList::with_max_bytes(size * 3, || false);
+        for key in 0..3 {
+            list.insert(key, key).unwrap();
+        }
+        assert_eq!(list.used_bytes(), size * 3);
+        assert_eq!(list.insert(1, 0), Err(CapacityError::Occupied(1, 0)));
+        assert_eq!(list.insert(9, 9), Err(CapacityError::OverBudget(9, 9)));
+
+        assert_eq!(list.remove(&0), Ok((0, 0)));
+        assert_eq!(list.remove(&0), Err(RemoveError::NotFound));
+        assert_eq!(list.used_bytes(), size * 2);
+        assert_eq!(list.insert(9, 9), Ok(()));
+        assert!(list.iter().map(|(k, _)| *k).eq([1, 2, 9]));
+    }
+
+    #[mockalloc::test]
+    fn budget_model() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = BudgetedSkipList::with_max_bytes(2000, Gen::standard(rng.clone()));
+        let mut model = BTreeMap::new();
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..100);
+            if rng.gen_ratio(1, 3) {
+                assert_eq!(list.remove(&key).ok(), model.remove_entry(&key));
+                continue;
+            }
+            match list.insert(key, key) {
+                Ok(()) => assert!(model.insert(key, key).is_none()),
+                Err(CapacityError::Occupied(..)) => assert!(model.contains_key(&key)),
+                Err(CapacityError::OverBudget(..)) => assert!(list.used_bytes() > 1000),
+            }
+            assert!(list.used_bytes() <= list.max_bytes());
+        }
+        assert!(list.iter().eq(model.iter()));
+        let keys: Vec<_> = model.keys().copied().collect();
+        for key in keys {
+            list.remove(&key).unwrap();
+        }
+        assert_eq!(list.used_bytes(), 0);
+    }
+}
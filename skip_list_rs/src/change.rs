@@ -0,0 +1,242 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::{check_range, Generator, SkipList, Subscriber};
+
+// subscribeした関数に渡される変更．keyは呼び出しの間だけ借りられ，Removedのときもまだ解放されていない．
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change<'a, K> {
+    Inserted(&'a K),
+    Removed(&'a K),
+    Updated(&'a K),
+}
+
+// deriveではK: Copyを求めてしまうので，参照だけを写す．
+impl<K> Clone for Change<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Change<'_, K> {}
+
+impl<'a, K> Change<'a, K> {
+    pub fn key(&self) -> &'a K {
+        match self {
+            Change::Inserted(key) | Change::Removed(key) | Change::Updated(key) => key,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+struct Watcher<K> {
+    id: u64,
+    start: Bound<K>,
+    end: Bound<K>,
+    f: Box<Subscriber<K>>,
+}
+
+// rangeはstartの順に並べる．keyをstartの内に含むrangeは先頭から続くので，そこだけendを見る．
+pub(crate) struct Watchers<K> {
+    next: u64,
+    ranges: Vec<Watcher<K>>,
+}
+
+// Unboundedを先に，同じkeyならIncludedを先に並べる．
+fn start_order<K>(bound: &Bound<K>) -> (Option<&K>, bool) {
+    match bound {
+        Bound::Unbounded => (None, false),
+        Bound::Included(key) => (Some(key), false),
+        Bound::Excluded(key) => (Some(key), true),
+    }
+}
+
+impl<K: Ord> Watchers<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            next: 0,
+            ranges: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub(crate) fn notify(&mut self, change: Change<'_, K>) {
+        let key = change.key();
+        let started = self.ranges.partition_point(|w| match &w.start {
+            Bound::Included(start) => start <= key,
+            Bound::Excluded(start) => start < key,
+            Bound::Unbounded => true,
+        });
+        for watcher in &mut self.ranges[..started] {
+            let within = match &watcher.end {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+            if within {
+                (watcher.f)(change);
+            }
+        }
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // rangeの中のkeyの変更だけをfに知らせる．subscribeとは別に，いくつでも登録できる．
+    pub fn subscribe_range(
+        &mut self,
+        range: impl RangeBounds<K>,
+        f: impl FnMut(Change<'_, K>) + Send + Sync + 'static,
+    ) -> SubscriptionId
+    where
+        K: Clone,
+    {
+        check_range(&range);
+        let watchers = &mut self.watchers;
+        let watcher = Watcher {
+            id: watchers.next,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            f: Box::new(f),
+        };
+        watchers.next += 1;
+        let pos = watchers
+            .ranges
+            .partition_point(|w| start_order(&w.start) <= start_order(&watcher.start));
+        watchers.ranges.insert(pos, watcher);
+        SubscriptionId(watchers.next - 1)
+    }
+
+    pub fn unsubscribe_range(&mut self, id: SubscriptionId) -> bool {
+        let ranges = &mut self.watchers.ranges;
+        let Some(pos) = ranges.iter().position(|w| w.id == id.0) else {
+            return false;
+        };
+        ranges.remove(pos);
+        true
+    }
+
+    // 取り除いたnodeを1つずつ知らせる必要があるか．
+    pub(crate) fn notifies(&self) -> bool {
+        self.subscriber.is_some() || !self.watchers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{Change, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::ops::Bound;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Event {
+        Inserted(i32),
+        Removed(i32),
+        Updated(i32),
+    }
+
+    #[mockalloc::test]
+    fn feed() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let sink = events.clone();
+        list.subscribe(move |change| {
+            let event = match change {
+                Change::Inserted(key) => Event::Inserted(*key),
+                Change::Removed(key) => Event::Removed(*key),
+                Change::Updated(key) => Event::Updated(*key),
+            };
+            sink.lock().unwrap().push(event);
+        });
+
+        for key in [3, 1, 2] {
+            list.insert(key, key).unwrap();
+        }
+        assert!(list.insert(1, 0).is_err());
+        list.append(5, 5).unwrap();
+        assert_eq!(list.update(&2, |v| *v = 20), Some(()));
+        assert_eq!(list.update(&4, |v| *v = 40), None);
+        list.remove(&3).unwrap();
+        assert!(list.remove(&3).is_err());
+        list.pop_last();
+        list.pop_first();
+        list.insert(6, 6).unwrap();
+        assert_eq!(list.truncate_before(&10), 2);
+
+        list.unsubscribe();
+        list.insert(7, 7).unwrap();
+
+        use Event::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                Inserted(3),
+                Inserted(1),
+                Inserted(2),
+                Inserted(5),
+                Updated(2),
+                Removed(3),
+                Removed(5),
+                Removed(1),
+                Inserted(6),
+                Removed(2),
+                Removed(6),
+            ]
+        );
+    }
+
+    #[mockalloc::test]
+    fn range_subscriptions() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let watch = |list: &mut SkipList<i32, i32, _>, name, range: (Bound<i32>, Bound<i32>)| {
+            let sink = events.clone();
+            list.subscribe_range(range, move |change: Change<'_, i32>| {
+                sink.lock().unwrap().push((name, *change.key()))
+            })
+        };
+        let window = watch(
+            &mut list,
+            "window",
+            (Bound::Included(10), Bound::Excluded(20)),
+        );
+        watch(&mut list, "tail", (Bound::Excluded(15), Bound::Unbounded));
+        watch(&mut list, "head", (Bound::Unbounded, Bound::Included(5)));
+
+        for key in [3, 10, 15, 16, 20, 25] {
+            list.insert(key, key).unwrap();
+        }
+        list.update(&16, |v| *v += 1);
+        list.remove(&10).unwrap();
+        assert_eq!(list.truncate_before(&16), 2);
+        assert!(list.unsubscribe_range(window));
+        assert!(!list.unsubscribe_range(window));
+        list.remove(&16).unwrap();
+
+        // 同じ変更を受けるrangeには，startの順に知らせる．
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ("head", 3),
+                ("window", 10),
+                ("window", 15),
+                ("window", 16),
+                ("tail", 16),
+                ("tail", 20),
+                ("tail", 25),
+                ("window", 16),
+                ("tail", 16),
+                ("window", 10),
+                ("head", 3),
+                ("window", 15),
+                ("tail", 16),
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Write},
+    ops::RangeBounds,
+};
+
+use crate::{
+    iter::{Iter, Range},
+    Generator, InsertError, RemoveError, SkipList,
+};
+
+// すべての操作をBTreeMapにも行い，呼び出しのたびに結果と中身が一致するか確かめる．
+// 一致しなければ，それまでの操作の履歴を添えてpanicする．
+pub struct CheckedSkipList<K: Ord + Clone + Debug, V: Clone + PartialEq + Debug, G: Generator<bool>>
+{
+    list: SkipList<K, V, G>,
+    model: BTreeMap<K, V>,
+    history: String,
+}
+
+impl<K: Ord + Clone + Debug, V: Clone + PartialEq + Debug, G: Generator<bool>>
+    CheckedSkipList<K, V, G>
+{
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            model: BTreeMap::new(),
+            history: String::new(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        let count = self.list.count();
+        self.agree(count == self.model.len(), "count");
+        count
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        self.record(format_args!("insert({key:?}, {value:?})"));
+        let occupied = self.model.contains_key(&key);
+        if !occupied {
+            self.model.insert(key.clone(), value.clone());
+        }
+        let result = self.list.insert(key, value);
+        self.agree(result.is_err() == occupied, "result");
+        self.check();
+        result
+    }
+
+    pub fn append(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        self.record(format_args!("append({key:?}, {value:?})"));
+        let occupied = self.model.contains_key(&key);
+        if !occupied {
+            self.model.insert(key.clone(), value.clone());
+        }
+        let result = self.list.append(key, value);
+        self.agree(result.is_err() == occupied, "result");
+        self.check();
+        result
+    }
+
+    pub fn truncate_before(&mut self, key: &K) -> usize {
+        self.record(format_args!("truncate_before({key:?})"));
+        let kept = self.model.split_off(key);
+        let expected = std::mem::replace(&mut self.model, kept).len();
+        let removed = self.list.truncate_before(key);
+        self.agree(removed == expected, "result");
+        self.check();
+        removed
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        let found = self.list.search(key);
+        self.agree(found == self.model.get(key), "search");
+        found
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.search(key).is_some()
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        let first = self.list.first();
+        self.agree(first == self.model.first_key_value(), "first");
+        first
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        let last = self.list.last();
+        self.agree(last == self.model.last_key_value(), "last");
+        last
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        self.record(format_args!("pop_first()"));
+        let expected = self.model.pop_first();
+        let popped = self.list.pop_first();
+        self.agree(popped == expected, "result");
+        self.check();
+        popped
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        self.record(format_args!("pop_last()"));
+        let expected = self.model.pop_last();
+        let popped = self.list.pop_last();
+        self.agree(popped == expected, "result");
+        self.check();
+        popped
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        self.record(format_args!("remove({key:?})"));
+        let expected = self.model.remove_entry(key);
+        let removed = self.list.remove(key);
+        self.agree(removed.as_ref().ok() == expected.as_ref(), "result");
+        self.check();
+        removed
+    }
+
+    pub fn range<R: RangeBounds<K> + Clone>(&self, range: R) -> Range<'_, K, V> {
+        let expected = self.model.range(range.clone());
+        self.agree(self.list.range(range.clone()).eq(expected), "range");
+        self.list.range(range)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.list.iter()
+    }
+
+    pub fn into_inner(self) -> SkipList<K, V, G> {
+        self.list
+    }
+
+    fn record(&mut self, op: std::fmt::Arguments<'_>) {
+        writeln!(self.history, "{op}").unwrap();
+    }
+
+    fn check(&self) {
+        if let Err(violation) = self.list.check_invariants() {
+            self.diverged(&format!("invariant ({violation})"));
+        }
+        self.agree(self.list.count() == self.model.len(), "count");
+        self.agree(self.list.iter().eq(self.model.iter()), "entries");
+    }
+
+    fn agree(&self, agreed: bool, what: &str) {
+        if !agreed {
+            self.diverged(what);
+        }
+    }
+
+    fn diverged(&self, what: &str) -> ! {
+        panic!(
+            "skip list diverged from the model in {what} after:\n{}",
+            self.history
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::CheckedSkipList;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[mockalloc::test]
+    fn random_ops() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = CheckedSkipList::new(Gen::standard(SmallRng::from_entropy()));
+
+        for _ in 0..500 {
+            let key = rng.gen_range(0..100);
+            match rng.gen_range(0..8) {
+                0 | 1 => {
+                    let _ = list.insert(key, key * 10);
+                }
+                2 => {
+                    let _ = list.append(key, key * 10);
+                }
+                3 => {
+                    let _ = list.remove(&key);
+                }
+                4 => {
+                    list.pop_first();
+                }
+                5 => {
+                    list.pop_last();
+                }
+                6 if rng.gen_ratio(1, 10) => {
+                    list.truncate_before(&key);
+                }
+                _ => {
+                    list.search(&key);
+                    list.first();
+                    list.last();
+                    list.range(key..).count();
+                }
+            }
+        }
+        assert_eq!(list.count(), list.into_inner().iter().count());
+    }
+
+    #[test]
+    #[should_panic(expected = "diverged from the model in entries after:\ninsert(1, 10)\n")]
+    fn reports_history() {
+        let mut list = CheckedSkipList::new(|| false);
+        list.insert(1, 10).unwrap();
+        *list.list.search_mut(&1).unwrap() = 20;
+        let _ = list.insert(2, 20);
+    }
+}
@@ -0,0 +1,68 @@
+use std::{cmp::Ordering, fmt};
+
+// 大文字と小文字を区別せずに並べるkey．"Z"は"a"より後ろになり，"A"と"a"は同じkeyになる．
+// 各文字をchar::to_lowercaseで畳んでから比べる．localeに依る照合はしない．
+#[derive(Clone, Copy, Default)]
+pub struct CaseInsensitive<S>(pub S);
+
+impl<S: AsRef<str>> CaseInsensitive<S> {
+    fn folded(&self) -> impl Iterator<Item = char> + '_ {
+        self.0.as_ref().chars().flat_map(char::to_lowercase)
+    }
+}
+
+impl<S: AsRef<str>> PartialEq for CaseInsensitive<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S: AsRef<str>> Eq for CaseInsensitive<S> {}
+
+impl<S: AsRef<str>> PartialOrd for CaseInsensitive<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: AsRef<str>> Ord for CaseInsensitive<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.folded().cmp(other.folded())
+    }
+}
+
+impl<S: AsRef<str>> fmt::Debug for CaseInsensitive<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0.as_ref(), f)
+    }
+}
+
+impl<S: AsRef<str>> fmt::Display for CaseInsensitive<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CaseInsensitive;
+    use crate::SkipList;
+
+    #[mockalloc::test]
+    fn case_insensitive() {
+        let mut list = SkipList::new(|| false);
+        for name in ["Zoe", "adam", "Émile", "bob", "Alice", "éric"] {
+            list.insert(CaseInsensitive(name), ()).unwrap();
+        }
+        let names: Vec<_> = list.iter().map(|(k, _)| k.0).collect();
+        assert_eq!(names, ["adam", "Alice", "bob", "Zoe", "Émile", "éric"]);
+
+        assert!(list.insert(CaseInsensitive("ZOE"), ()).is_err());
+        assert!(list.contains_key(&CaseInsensitive("BOB")));
+        assert_eq!(
+            CaseInsensitive("Straße".to_string()),
+            CaseInsensitive("STRAßE".to_string())
+        );
+        assert_eq!(format!("{:?}", CaseInsensitive("A")), "\"A\"");
+    }
+}
@@ -0,0 +1,340 @@
+use std::{cmp::Ordering, marker::PhantomData, mem};
+
+use crate::{
+    merge::seek,
+    node::MaybeNode,
+    patch::{links, step, Preds},
+    Change, Generator, InsertError, Operation, SkipList,
+};
+
+// level 0のnodeを指す．末尾を過ぎるとcurrentはNoneになる．
+pub struct Cursor<'a, K: Ord, V> {
+    node: MaybeNode<K, V>,
+    head: MaybeNode<K, V>,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Cursor<'a, K, V> {
+    pub fn current(&self) -> Option<(&'a K, &'a V)> {
+        let node = self.node.take()?;
+        Some((node.key(), node.value()))
+    }
+
+    pub fn move_next(&mut self) {
+        if let Some(node) = self.node.take() {
+            self.node = node.nexts()[0].next;
+        }
+    }
+
+    // key以上の最初のentryへ移る．前方なら今の位置から，そうでなければ先頭から探す．
+    pub fn seek(&mut self, key: &K) {
+        let from = match self.node.take() {
+            Some(node) if node.key() < key => self.node,
+            _ => self.head,
+        };
+        self.node = seek(from, |k| k < key);
+    }
+}
+
+// valueだけを書き換えられる．split_beforeとsplit_afterで，cursorの前後を別のlistへ切り離せる．
+pub struct CursorMut<'a, K: Ord, V, G: Generator<bool>> {
+    node: MaybeNode<K, V>,
+    list: &'a mut SkipList<K, V, G>,
+    // 各levelで今のnode以前の最後のnode．末尾を過ぎていれば各levelの最後のnode．
+    preds: Preds<K, V>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a, G: Generator<bool>> CursorMut<'a, K, V, G> {
+    pub fn current(&mut self) -> Option<(&K, &mut V)> {
+        let node = self.node.take()?;
+        Some((node.key(), node.value_mut()))
+    }
+
+    pub fn move_next(&mut self) {
+        if let Some(node) = self.node.take() {
+            self.node = node.nexts()[0].next;
+            if let Some(next) = self.node.take() {
+                step(&mut self.preds, next);
+            }
+        }
+    }
+
+    pub fn seek(&mut self, key: &K) {
+        if self.node.take().is_none_or(|node| node.key() >= key) {
+            self.preds = self.list.head_preds();
+        }
+        self.list.advance(&mut self.preds, key, None);
+        self.land();
+    }
+
+    // cursorの位置から探して挿入し，cursorを挿入したentry(重複していれば既にあったentry)へ移す．
+    // cursorより後ろのkeyは，cursorとの間の距離に応じた比較だけで済むので，ほぼ昇順に並んだkeyを続けて入れるなら償却O(1)になる．
+    // cursorのentryより前のkeyは，hintが外れているのでheadから探す．
+    pub fn insert_hint(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        let list = &mut *self.list;
+        let last = self.preds[0].0;
+        let mut comparisons = 0;
+        if let Some(last) = last.take() {
+            comparisons += 1;
+            match last.key().cmp(&key) {
+                Ordering::Less => {}
+                Ordering::Equal => {
+                    list.report(Operation::Insert, comparisons);
+                    self.node = last.into();
+                    return Err(InsertError::Occupied(key, value));
+                }
+                Ordering::Greater => self.preds = list.head_preds(),
+            }
+        }
+        let found = list.advance(&mut self.preds, &key, Some(&mut comparisons));
+        list.report(Operation::Insert, comparisons);
+        if found.is_some() {
+            self.land();
+            return Err(InsertError::Occupied(key, value));
+        }
+
+        let node = list.alloc(key, value);
+        list.splice(&mut self.preds, node);
+        list.debug_check(true);
+        list.hash_in(node);
+        list.notify(Change::Inserted(node.key()));
+        self.node = node.into();
+        Ok(())
+    }
+
+    // cursorより前のentryをgenを使う新しいlistとして返す．末尾を過ぎていればすべてを返す．
+    // 各levelを切り口でつなぎ替えるだけで，nodeは作り直さない．
+    pub fn split_before(&mut self, gen: G) -> SkipList<K, V, G> {
+        let n = self.pos() - 1;
+        let mut rest = self.list.cut_at_rank(n, gen);
+        // 前半が残っているので，入れ替えて後半を残す．
+        mem::swap(&mut self.list.nodes, &mut rest.nodes);
+        mem::swap(&mut self.list.tails, &mut rest.tails);
+        mem::swap(&mut self.list.count, &mut rest.count);
+        self.list.forget(rest.nodes[0].next);
+        self.preds = self.list.head_preds();
+        self.land();
+        rest
+    }
+
+    // cursorより後ろのentryを返す．末尾を過ぎていれば空のlistを返す．
+    // cursor以前のnodeは残るので，predsはそのまま使える．
+    pub fn split_after(&mut self, gen: G) -> SkipList<K, V, G> {
+        let n = self.pos();
+        let rest = self.list.cut_at_rank(n, gen);
+        self.list.forget(rest.nodes[0].next);
+        rest
+    }
+
+    // 今のnodeの位置(1始まり)．末尾を過ぎていればcount + 1．
+    fn pos(&self) -> usize {
+        match self.node.take() {
+            Some(_) => self.preds[0].1,
+            None => self.list.count + 1,
+        }
+    }
+
+    // predsの直後のnodeへ移る．
+    fn land(&mut self) {
+        self.node = links(&self.list.nodes, self.preds[0].0)[0].next;
+        if let Some(node) = self.node.take() {
+            step(&mut self.preds, node);
+        }
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn cursor(&self) -> Cursor<'_, K, V> {
+        Cursor {
+            node: self.nodes[0].next,
+            head: self.nodes[0].next,
+            marker: PhantomData,
+        }
+    }
+
+    // iter_mutと同じく，content_hashは次に計算し直す．
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, K, V, G> {
+        self.mark_stale();
+        let mut cursor = CursorMut {
+            node: MaybeNode::null(),
+            preds: self.head_preds(),
+            list: self,
+        };
+        cursor.land();
+        cursor
+    }
+
+    fn head_preds(&self) -> Preds<K, V> {
+        vec![(MaybeNode::null(), 0); self.nodes.len()]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::{Change, Metrics, Operation, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[mockalloc::test]
+    fn seek() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..500 {
+            list.insert(key * 3, key).unwrap();
+        }
+
+        let mut cursor = list.cursor();
+        assert_eq!(cursor.current(), Some((&0, &0)));
+        for _ in 0..200 {
+            let key = rng.gen_range(0..1600);
+            cursor.seek(&key);
+            assert_eq!(cursor.current(), list.range(key..).next());
+            cursor.move_next();
+            assert_eq!(cursor.current(), list.range(key..).nth(1));
+        }
+    }
+
+    #[mockalloc::test]
+    fn cursor_mut() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..10 {
+            list.insert(key * 10, key).unwrap();
+        }
+
+        let mut cursor = list.cursor_mut();
+        cursor.seek(&35);
+        *cursor.current().unwrap().1 = 400;
+        cursor.seek(&10);
+        cursor.move_next();
+        *cursor.current().unwrap().1 = 200;
+        cursor.seek(&1000);
+        assert!(cursor.current().is_none());
+
+        assert_eq!(list.search(&40), Some(&400));
+        assert_eq!(list.search(&20), Some(&200));
+    }
+
+    #[mockalloc::test]
+    fn split() {
+        let gen = || Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen());
+        for key in 0..100 {
+            list.insert(key, key).unwrap();
+        }
+        let (tx, rx) = std::sync::mpsc::channel();
+        list.subscribe(move |change| {
+            if let Change::Removed(key) = change {
+                tx.send(*key).unwrap();
+            }
+        });
+
+        let mut cursor = list.cursor_mut();
+        cursor.seek(&30);
+        let front = cursor.split_before(gen());
+        assert_eq!(cursor.current(), Some((&30, &mut 30)));
+        cursor.seek(&69);
+        let back = cursor.split_after(gen());
+        assert_eq!(cursor.current(), Some((&69, &mut 69)));
+        cursor.move_next();
+        assert!(cursor.current().is_none());
+        assert!(cursor.split_after(gen()).iter().next().is_none());
+
+        assert!(front.iter().map(|(k, _)| *k).eq(0..30));
+        assert!(back.iter().map(|(k, _)| *k).eq(70..100));
+        assert!(list.iter().map(|(k, _)| *k).eq(30..70));
+        for list in [&front, &back, &list] {
+            check_spans(list);
+            assert_eq!(list.check_invariants(), Ok(()));
+        }
+        let mut removed: Vec<_> = rx.try_iter().collect();
+        removed.sort();
+        assert!(removed.into_iter().eq((0..30).chain(70..100)));
+
+        // 末尾を過ぎたcursorより前は，すべてのentry．
+        let mut cursor = list.cursor_mut();
+        cursor.seek(&100);
+        let all = cursor.split_before(gen());
+        assert_eq!(list.count(), 0);
+        assert!(list.cursor_mut().current().is_none());
+        assert!(all.iter().map(|(k, _)| *k).eq(30..70));
+        check_spans(&list);
+        check_spans(&all);
+    }
+
+    struct Comparisons(Arc<AtomicUsize>);
+
+    impl Metrics for Comparisons {
+        fn operation(&self, op: Operation, comparisons: usize) {
+            if op == Operation::Insert {
+                self.0.fetch_add(comparisons, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[mockalloc::test]
+    fn insert_hint() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let comparisons = Arc::new(AtomicUsize::new(0));
+        list.set_metrics(Comparisons(comparisons.clone()));
+
+        // 少しずつ前後するkeyの列．
+        let keys: Vec<u32> = (0..4000).map(|i| i * 4 + rng.gen_range(0..12)).collect();
+        let mut model = BTreeMap::new();
+        let mut cursor = list.cursor_mut();
+        for &key in &keys {
+            let inserted = cursor.insert_hint(key, key);
+            assert_eq!(inserted.is_ok(), model.insert(key, key).is_none());
+            assert_eq!(cursor.current().map(|(k, _)| *k), Some(key));
+        }
+        let per_insert = comparisons.load(Ordering::Relaxed) as f64 / keys.len() as f64;
+        assert!(per_insert < 8.0, "{per_insert}");
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.iter().eq(model.iter()));
+
+        // 前回より前のkeyはheadから，cursorを動かした後はその位置から探して入る．
+        let mut cursor = list.cursor_mut();
+        for _ in 0..500 {
+            let key = rng.gen_range(0..17000);
+            if rng.gen_ratio(1, 10) {
+                cursor.seek(&rng.gen_range(0..17000));
+            }
+            match cursor.insert_hint(key, 0) {
+                Ok(()) => assert!(model.insert(key, 0).is_none()),
+                Err(e) => assert!(model.contains_key(&e.into_inner().0)),
+            }
+        }
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.iter().eq(model.iter()));
+
+        // seekした位置の直後へは，headから探すより少ない比較で入る．
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in (0..20000u32).map(|i| i * 2) {
+            list.insert(key, key).unwrap();
+        }
+        let comparisons = Arc::new(AtomicUsize::new(0));
+        list.set_metrics(Comparisons(comparisons.clone()));
+        let mut cursor = list.cursor_mut();
+        let n = 1000;
+        for _ in 0..n {
+            let key = rng.gen_range(0..19999) * 2;
+            cursor.seek(&key);
+            assert_eq!(cursor.current().map(|(k, _)| *k), Some(key));
+            cursor.insert_hint(key + 1, key + 1).unwrap_or(());
+            assert_eq!(cursor.current().map(|(k, _)| *k), Some(key + 1));
+            cursor.move_next();
+            assert_eq!(cursor.current().map(|(k, _)| *k), Some(key + 2));
+            assert!(cursor.insert_hint(key + 2, 0).is_err());
+        }
+        let per_insert = comparisons.load(Ordering::Relaxed) as f64 / (2 * n) as f64;
+        assert!(per_insert < 8.0, "{per_insert}");
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::Instant;
+
+use crate::{Generator, QueueHandle, SkipPriorityQueue};
+
+// deadlineをpriorityとするSkipPriorityQueue．同じdeadlineは挿入順に取り出される．
+pub struct SkipDelayQueue<V, G: Generator<bool>> {
+    queue: SkipPriorityQueue<Instant, V, G>,
+}
+
+impl<V, G: Generator<bool>> SkipDelayQueue<V, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            queue: SkipPriorityQueue::new(gen),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.queue.count()
+    }
+
+    pub fn insert(&mut self, deadline: Instant, value: V) -> QueueHandle {
+        self.queue.push(deadline, value)
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let (deadline, _) = self.queue.peek()?;
+        Some(*deadline)
+    }
+
+    // deadlineがnow以前のものを1つ，deadlineの早い順に取り出す．
+    pub fn poll_expired(&mut self, now: Instant) -> Option<(Instant, V)> {
+        if self.next_deadline()? > now {
+            return None;
+        }
+        self.queue.pop()
+    }
+
+    pub fn deadline(&self, handle: QueueHandle) -> Option<Instant> {
+        self.queue.priority(handle).copied()
+    }
+
+    pub fn reset(&mut self, handle: QueueHandle, deadline: Instant) -> Option<Instant> {
+        self.queue.change_priority(handle, deadline)
+    }
+
+    pub fn remove(&mut self, handle: QueueHandle) -> Option<(Instant, V)> {
+        self.queue.remove(handle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipDelayQueue;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::time::{Duration, Instant};
+
+    #[mockalloc::test]
+    fn poll_in_deadline_order() {
+        let mut queue = SkipDelayQueue::new(Gen::standard(SmallRng::from_entropy()));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let a = queue.insert(at(30), "a");
+        queue.insert(at(10), "b");
+        let c = queue.insert(at(20), "c");
+        queue.insert(at(10), "d");
+
+        assert_eq!(queue.next_deadline(), Some(at(10)));
+        assert_eq!(queue.poll_expired(at(5)), None);
+        assert_eq!(queue.poll_expired(at(10)), Some((at(10), "b")));
+        assert_eq!(queue.poll_expired(at(10)), Some((at(10), "d")));
+        assert_eq!(queue.poll_expired(at(10)), None);
+
+        assert_eq!(queue.reset(a, at(15)), Some(at(30)));
+        assert_eq!(queue.deadline(a), Some(at(15)));
+        assert_eq!(queue.remove(c), Some((at(20), "c")));
+        assert_eq!(queue.remove(c), None);
+
+        assert_eq!(queue.poll_expired(at(100)), Some((at(15), "a")));
+        assert_eq!(queue.poll_expired(at(100)), None);
+        assert_eq!(queue.next_deadline(), None);
+        assert_eq!(queue.count(), 0);
+    }
+
+    // 期限の来たものをtimerのthreadで取り出す．
+    #[test]
+    fn timer_thread() {
+        let mut queue = SkipDelayQueue::new(Gen::standard(SmallRng::from_entropy()));
+        let start = Instant::now();
+        for ms in [3, 1, 2] {
+            queue.insert(start + Duration::from_millis(ms), ms);
+        }
+        let timer = std::thread::spawn(move || {
+            let mut fired = Vec::new();
+            while let Some(deadline) = queue.next_deadline() {
+                std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                while let Some((_, ms)) = queue.poll_expired(Instant::now()) {
+                    fired.push(ms);
+                }
+            }
+            fired
+        });
+        assert_eq!(timer.join().unwrap(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::{cmp::Ordering, iter::Peekable};
+
+use crate::{iter::Iter, Generator, SkipList};
+
+// selfからotherへの差分．Modifiedは(key, selfのvalue, otherのvalue)．
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diff<'a, K, V> {
+    Added(&'a K, &'a V),
+    Removed(&'a K, &'a V),
+    Modified(&'a K, &'a V, &'a V),
+}
+
+pub struct DiffIter<'a, K: Ord, V> {
+    left: Peekable<Iter<'a, K, V>>,
+    right: Peekable<Iter<'a, K, V>>,
+}
+
+impl<'a, K: Ord, V: PartialEq> Iterator for DiffIter<'a, K, V> {
+    type Item = Diff<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.left.peek(), self.right.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((l, _)), Some((r, _))) => l.cmp(r),
+            };
+            match order {
+                Ordering::Less => {
+                    let (key, value) = self.left.next()?;
+                    return Some(Diff::Removed(key, value));
+                }
+                Ordering::Greater => {
+                    let (key, value) = self.right.next()?;
+                    return Some(Diff::Added(key, value));
+                }
+                Ordering::Equal => {
+                    let (key, old) = self.left.next()?;
+                    let (_, new) = self.right.next()?;
+                    if old != new {
+                        return Some(Diff::Modified(key, old, new));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord, V: PartialEq, G: Generator<bool>> SkipList<K, V, G> {
+    // 両方をkeyの順に並べて辿り，O(n + m)で差分を返す．
+    pub fn diff<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipList<K, V, H>,
+    ) -> DiffIter<'a, K, V> {
+        DiffIter {
+            left: self.iter().peekable(),
+            right: other.iter().peekable(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{Diff, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn diff() {
+        let mut old = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut new = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in [1, 2, 3, 5] {
+            old.insert(key, key).unwrap();
+        }
+        for (key, value) in [(0, 0), (2, 2), (3, 30), (4, 4)] {
+            new.insert(key, value).unwrap();
+        }
+
+        assert!(old.diff(&new).eq([
+            Diff::Added(&0, &0),
+            Diff::Removed(&1, &1),
+            Diff::Modified(&3, &3, &30),
+            Diff::Added(&4, &4),
+            Diff::Removed(&5, &5),
+        ]));
+        assert!(new.diff(&new).next().is_none());
+    }
+}
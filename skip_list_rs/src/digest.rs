@@ -0,0 +1,177 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::{node::Node, Generator, SkipList};
+
+pub(crate) struct Digest<K, V> {
+    hash: fn(&K, &V) -> u64,
+    sum: u64,
+    // search_mutやiter_mutでvalueが書き換えられたかもしれない．
+    stale: bool,
+}
+
+impl<K, V> Clone for Digest<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            hash: self.hash,
+            sum: self.sum,
+            stale: self.stale,
+        }
+    }
+}
+
+// DefaultHasher::newの鍵は固定なので，同じbuildのreplica同士なら同じ値になる．
+fn entry_hash<K: Hash, V: Hash>(key: &K, value: &V) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (key, value).hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<K: Ord + Hash, V: Hash, G: Generator<bool>> SkipList<K, V, G> {
+    // 以後，挿入，削除，updateのたびにentryのhashを足し引きして保つ．
+    // 並びはkeyから決まるので，和をとっても順序の違いは見逃さない．
+    pub fn enable_content_hash(&mut self) {
+        let hash = entry_hash::<K, V>;
+        self.digest = Some(Digest {
+            hash,
+            sum: self.sum_with(hash),
+            stale: false,
+        });
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn disable_content_hash(&mut self) {
+        self.digest = None;
+    }
+
+    // 有効でなければNone．search_mutやiter_mutを使った後は，全体から計算し直す．
+    pub fn content_hash(&mut self) -> Option<u64> {
+        let hash = self.digest.as_ref()?.hash;
+        if self.digest.as_ref()?.stale {
+            let sum = self.sum_with(hash);
+            self.digest = Some(Digest {
+                hash,
+                sum,
+                stale: false,
+            });
+        }
+        Some(self.digest.as_ref()?.sum)
+    }
+
+    fn sum_with(&self, hash: fn(&K, &V) -> u64) -> u64 {
+        self.iter()
+            .fold(0, |sum, (key, value)| sum.wrapping_add(hash(key, value)))
+    }
+
+    // 挿入したnodeをcontent hashとbloom filterとhash indexに加える．
+    pub(crate) fn hash_in(&mut self, node: Node<K, V>) {
+        self.bloom_add(node.key());
+        self.index_add(node);
+        if let Some(digest) = &mut self.digest {
+            digest.sum = digest
+                .sum
+                .wrapping_add((digest.hash)(node.key(), node.value()));
+        }
+    }
+
+    pub(crate) fn hash_out(&mut self, node: Node<K, V>) {
+        self.index_remove(node);
+        if let Some(digest) = &mut self.digest {
+            digest.sum = digest
+                .sum
+                .wrapping_sub((digest.hash)(node.key(), node.value()));
+        }
+    }
+
+    pub(crate) fn mark_stale(&mut self) {
+        if let Some(digest) = &mut self.digest {
+            digest.stale = true;
+        }
+    }
+
+    // fが途中でpanicしてもstaleのまま残り，次のcontent_hashで計算し直される．
+    pub(crate) fn rehash<R>(&mut self, node: Node<K, V>, f: impl FnOnce(&mut V) -> R) -> R {
+        let Some(digest) = &mut self.digest else {
+            return f(node.value_mut());
+        };
+        let stale = std::mem::replace(&mut digest.stale, true);
+        digest.sum = digest
+            .sum
+            .wrapping_sub((digest.hash)(node.key(), node.value()));
+        let result = f(node.value_mut());
+        digest.sum = digest
+            .sum
+            .wrapping_add((digest.hash)(node.key(), node.value()));
+        digest.stale = stale;
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{Patch, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[mockalloc::test]
+    fn replicas() {
+        let mut rng = SmallRng::from_entropy();
+        let mut left = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut right = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        assert_eq!(left.content_hash(), None);
+        left.enable_content_hash();
+        right.enable_content_hash();
+        assert_eq!(left.content_hash(), right.content_hash());
+
+        for _ in 0..500 {
+            let key = rng.gen_range(0..100);
+            match rng.gen_range(0..4) {
+                0 => {
+                    left.insert(key, key).ok();
+                }
+                1 => {
+                    left.append(key, key).ok();
+                }
+                2 => {
+                    left.remove(&key).ok();
+                }
+                _ => {
+                    left.update(&key, |v| *v += 1);
+                }
+            }
+        }
+        left.truncate_before(&10);
+        left.pop_first();
+        left.pop_last();
+        assert_ne!(left.content_hash(), right.content_hash());
+
+        let patch: Vec<_> = right.diff(&left).map(Patch::from).collect();
+        right.apply_patch(patch);
+        assert_eq!(left.content_hash(), right.content_hash());
+
+        let expected = left.content_hash();
+        left.disable_content_hash();
+        left.enable_content_hash();
+        assert_eq!(left.content_hash(), expected);
+    }
+
+    #[mockalloc::test]
+    fn stale() {
+        let mut list = SkipList::new(|| false);
+        list.enable_content_hash();
+        for key in 0..4 {
+            list.insert(key, key).unwrap();
+        }
+        let before = list.content_hash();
+
+        *list.search_mut(&2).unwrap() = 20;
+        assert_ne!(list.content_hash(), before);
+        for (_, value) in list.iter_mut() {
+            if *value == 20 {
+                *value = 2;
+            }
+        }
+        assert_eq!(list.content_hash(), before);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{borrow::Borrow, cmp::Ordering};
+
+use crate::{node::Node, Change, Generator, Operation, SkipList};
+
+// keyを借用のまま探し，空いていたときだけToOwnedで所有するkeyを作る．
+// &strでString keyのlistを引いても，見つかればallocateしない．
+pub enum EntryRef<'a, 'q, K: Ord + Borrow<Q>, Q: ?Sized + ToOwned<Owned = K>, V, G: Generator<bool>>
+{
+    Occupied(OccupiedEntryRef<'a, K, V, G>),
+    Vacant(VacantEntryRef<'a, 'q, K, Q, V, G>),
+}
+
+// posはheadを0とした1始まりの位置．
+pub struct OccupiedEntryRef<'a, K: Ord, V, G: Generator<bool>> {
+    list: &'a mut SkipList<K, V, G>,
+    node: Node<K, V>,
+    pos: usize,
+}
+
+// posはkeyが入る位置．
+pub struct VacantEntryRef<'a, 'q, K: Ord, Q: ?Sized, V, G: Generator<bool>> {
+    list: &'a mut SkipList<K, V, G>,
+    key: &'q Q,
+    pos: usize,
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn entry_ref<'a, 'q, Q: ?Sized + Ord + ToOwned<Owned = K>>(
+        &'a mut self,
+        key: &'q Q,
+    ) -> EntryRef<'a, 'q, K, Q, V, G>
+    where
+        K: Borrow<Q>,
+    {
+        let mut forwards = self.nodes.as_slice();
+        let mut rank = 0;
+        let mut comparisons = 0;
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                comparisons += 1;
+                if next.key().borrow() >= key {
+                    break;
+                }
+                rank += forwards[level].span;
+                forwards = next.nexts();
+            }
+        }
+        self.report(Operation::Search, comparisons);
+
+        let pos = rank + 1;
+        match forwards[0].next.take() {
+            Some(node) if node.key().borrow() == key => EntryRef::Occupied(OccupiedEntryRef {
+                list: self,
+                node,
+                pos,
+            }),
+            _ => EntryRef::Vacant(VacantEntryRef {
+                list: self,
+                key,
+                pos,
+            }),
+        }
+    }
+}
+
+impl<'a, 'q, K: Ord + Borrow<Q>, Q: ?Sized + ToOwned<Owned = K>, V, G: Generator<bool>>
+    EntryRef<'a, 'q, K, Q, V, G>
+{
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let EntryRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Ord, V, G: Generator<bool>> OccupiedEntryRef<'a, K, V, G> {
+    pub fn key(&self) -> &K {
+        self.node.key()
+    }
+
+    pub fn get(&self) -> &V {
+        self.node.value()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.list.mark_stale();
+        self.node.value_mut()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.list.mark_stale();
+        self.node.value_mut()
+    }
+
+    pub fn remove(self) -> (K, V) {
+        let pos = self.pos;
+        let Ok(removed) = self.list.remove_by(|_, next| next.cmp(&pos)) else {
+            unreachable!("the occupied node is not in the list")
+        };
+        self.list.hash_out(removed);
+        self.list.notify(Change::Removed(removed.key()));
+        removed.dispose()
+    }
+}
+
+impl<'a, 'q, K: Ord + Borrow<Q>, Q: ?Sized + ToOwned<Owned = K>, V, G: Generator<bool>>
+    VacantEntryRef<'a, 'q, K, Q, V, G>
+{
+    pub fn key(&self) -> &'q Q {
+        self.key
+    }
+
+    // 探したときの位置につなぐので，keyを比べ直さない．
+    pub fn insert(self, value: V) -> &'a mut V {
+        let pos = self.pos;
+        let locate = |_: &K, next: usize, _: &K| {
+            if next < pos {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        };
+        let Ok((node, pos)) = self.list.insert_by(locate, self.key.to_owned(), value) else {
+            unreachable!("position based locate never reports a duplicate")
+        };
+        self.list.check_neighbours(node, pos);
+        self.list.debug_check(true);
+        self.list.hash_in(node);
+        self.list.notify(Change::Inserted(node.key()));
+        node.value_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EntryRef;
+    use crate::test::{check_spans, Gen};
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn entry_ref() {
+        let mut list: SkipList<String, usize, _> =
+            SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let text = "the quick brown fox jumps over the lazy dog the end";
+        for word in text.split(' ') {
+            *list.entry_ref(word).or_default() += 1;
+        }
+        assert_eq!(list.search(&"the".to_string()), Some(&3));
+        assert_eq!(list.count(), 9);
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+
+        match list.entry_ref("cat") {
+            EntryRef::Vacant(entry) => {
+                assert_eq!(entry.key(), "cat");
+                *entry.insert(5) += 1;
+            }
+            EntryRef::Occupied(_) => unreachable!(),
+        }
+        match list.entry_ref("quick") {
+            EntryRef::Occupied(entry) => {
+                assert_eq!(entry.get(), &1);
+                assert_eq!(entry.remove(), ("quick".to_string(), 1));
+            }
+            EntryRef::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(list.search(&"cat".to_string()), Some(&6));
+        assert!(!list.contains_key(&"quick".to_string()));
+        assert_eq!(list.entry_ref("zzz").key(), "zzz");
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+    }
+
+    // record_allocsは入れ子にできないので，mockalloc::testを使わない．
+    #[test]
+    fn entry_ref_hit() {
+        let mut list: SkipList<String, usize, _> = SkipList::new(|| false);
+        list.insert("fox".to_string(), 1).unwrap();
+        let info = mockalloc::record_allocs(|| {
+            *list.entry_ref("fox").and_modify(|n| *n += 10).or_insert(0) += 1;
+        });
+        assert_eq!(info.num_allocs(), 0);
+        assert_eq!(list.search(&"fox".to_string()), Some(&12));
+    }
+}
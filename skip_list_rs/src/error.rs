@@ -0,0 +1,191 @@
+use std::{error::Error, fmt};
+
+// 挿入できなかったentryはそのまま返す．
+#[derive(Debug, PartialEq, Eq)]
+pub enum InsertError<K, V> {
+    Occupied(K, V),
+}
+
+impl<K, V> InsertError<K, V> {
+    pub fn into_inner(self) -> (K, V) {
+        match self {
+            InsertError::Occupied(key, value) => (key, value),
+        }
+    }
+}
+
+impl<K, V> fmt::Display for InsertError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertError::Occupied(..) => f.write_str("the key is already present"),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for InsertError<K, V> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveError {
+    NotFound,
+}
+
+impl fmt::Display for RemoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoveError::NotFound => f.write_str("the key is not present"),
+        }
+    }
+}
+
+impl Error for RemoveError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TryInsertError<K, V> {
+    Occupied(K, V),
+    AllocError(K, V),
+}
+
+impl<K, V> TryInsertError<K, V> {
+    pub fn into_inner(self) -> (K, V) {
+        match self {
+            TryInsertError::Occupied(key, value) | TryInsertError::AllocError(key, value) => {
+                (key, value)
+            }
+        }
+    }
+}
+
+impl<K, V> fmt::Display for TryInsertError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryInsertError::Occupied(..) => f.write_str("the key is already present"),
+            TryInsertError::AllocError(..) => f.write_str("memory allocation failed"),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for TryInsertError<K, V> {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CapacityError<K, V> {
+    Occupied(K, V),
+    OverBudget(K, V),
+}
+
+impl<K, V> CapacityError<K, V> {
+    pub fn into_inner(self) -> (K, V) {
+        match self {
+            CapacityError::Occupied(key, value) | CapacityError::OverBudget(key, value) => {
+                (key, value)
+            }
+        }
+    }
+}
+
+impl<K, V> fmt::Display for CapacityError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CapacityError::Occupied(..) => f.write_str("the key is already present"),
+            CapacityError::OverBudget(..) => f.write_str("the node would exceed the byte budget"),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for CapacityError<K, V> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+impl Error for AllocError {}
+
+// posはheadを0とした1始まりの位置．
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    // level 0でkeyが狭義単調増加になっていない．
+    Unordered { pos: usize },
+    // 上のlevelの鎖がlevel 0の鎖の部分列になっていない．
+    NotSubsequence { level: usize },
+    // levelの鎖にそれより低いtowerのnodeがつながっている．
+    Tower { pos: usize, level: usize },
+    // posからのlevelのlinkのspanが実際の距離と違う．
+    Span { pos: usize, level: usize },
+    // levelの末尾の情報が実際と違う．
+    Tail { level: usize },
+    Count { expected: usize, actual: usize },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::Unordered { pos } => {
+                write!(
+                    f,
+                    "the key at position {pos} is not greater than its predecessor"
+                )
+            }
+            InvariantViolation::NotSubsequence { level } => {
+                write!(f, "level {level} is not a subsequence of level 0")
+            }
+            InvariantViolation::Tower { pos, level } => {
+                write!(
+                    f,
+                    "the node at position {pos} is linked at level {level} above its tower"
+                )
+            }
+            InvariantViolation::Span { pos, level } => {
+                write!(
+                    f,
+                    "the span of the level {level} link from position {pos} is wrong"
+                )
+            }
+            InvariantViolation::Tail { level } => {
+                write!(f, "the tail of level {level} is wrong")
+            }
+            InvariantViolation::Count { expected, actual } => {
+                write!(f, "the count is {expected} but {actual} nodes are linked")
+            }
+        }
+    }
+}
+
+impl Error for InvariantViolation {}
+
+// 比較に失敗したときも，挿入しなかったentryを返す．
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompareInsertError<K, V, E> {
+    Occupied(K, V),
+    Compare(E, K, V),
+}
+
+impl<K, V, E> CompareInsertError<K, V, E> {
+    pub fn into_inner(self) -> (K, V) {
+        match self {
+            CompareInsertError::Occupied(key, value)
+            | CompareInsertError::Compare(_, key, value) => (key, value),
+        }
+    }
+}
+
+impl<K, V, E: fmt::Display> fmt::Display for CompareInsertError<K, V, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompareInsertError::Occupied(..) => f.write_str("the key is already present"),
+            CompareInsertError::Compare(e, ..) => write!(f, "comparing keys failed: {e}"),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, E: Error + 'static> Error for CompareInsertError<K, V, E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompareInsertError::Occupied(..) => None,
+            CompareInsertError::Compare(e, ..) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    bounded::Evictor, node::Node, EvictionReason, Generator, InsertError, QueueHandle, RemoveError,
+    SkipList, SkipPriorityQueue,
+};
+
+type EntryNode<K, V> = Node<K, (V, QueueHandle)>;
+
+// entryはdeadline indexのhandleを，deadline indexはentryのnodeを持ち，互いを直接引く．
+// 期限切れのentryはexpireされるまで残るが，getなどからは見えない．
+pub struct ExpiringSkipList<K: Ord, V, D: Ord, G: Generator<bool>> {
+    entries: SkipList<K, (V, QueueHandle), G>,
+    deadlines: SkipPriorityQueue<D, EntryNode<K, V>, G>,
+    evictor: Option<Box<Evictor<K, V>>>,
+}
+
+impl<K: Ord, V, D: Ord, G: Generator<bool>> ExpiringSkipList<K, V, D, G> {
+    pub fn new(mut make_gen: impl FnMut() -> G) -> Self {
+        Self {
+            entries: SkipList::new(make_gen()),
+            deadlines: SkipPriorityQueue::new(make_gen()),
+            evictor: None,
+        }
+    }
+
+    // evict_expiredで取り除いたentryをfへ渡す．expireの返すentryは呼び出し側が受け取るので渡さない．
+    pub fn on_evict(&mut self, f: impl FnMut(K, V, EvictionReason) + Send + Sync + 'static) {
+        self.evictor = Some(Box::new(f));
+    }
+
+    // 期限切れでまだexpireされていないentryも含む．
+    pub fn count(&self) -> usize {
+        self.entries.count()
+    }
+
+    pub fn insert(&mut self, key: K, value: V, deadline: D) -> Result<(), InsertError<K, (V, D)>> {
+        let placeholder = QueueHandle(u64::MAX);
+        let inserted =
+            self.entries
+                .insert_by(|next, _, key| next.cmp(key), key, (value, placeholder));
+        let (node, _) = match inserted {
+            Ok(inserted) => inserted,
+            Err((key, (value, _))) => return Err(InsertError::Occupied(key, (value, deadline))),
+        };
+
+        node.value_mut().1 = self.deadlines.push(deadline, node);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &K, now: &D) -> Option<&V> {
+        let (value, handle) = self.entries.search(key)?;
+        if self.deadlines.priority(*handle)? <= now {
+            return None;
+        }
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &K, now: &D) -> bool {
+        self.get(key, now).is_some()
+    }
+
+    pub fn deadline(&self, key: &K) -> Option<&D> {
+        let (_, handle) = self.entries.search(key)?;
+        self.deadlines.priority(*handle)
+    }
+
+    pub fn next_deadline(&self) -> Option<&D> {
+        let (deadline, _) = self.deadlines.peek()?;
+        Some(deadline)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V, D), RemoveError> {
+        let (key, (value, handle)) = self.entries.remove(key)?;
+        let Some((deadline, _)) = self.deadlines.remove(handle) else {
+            unreachable!("every entry has a deadline");
+        };
+        Ok((key, value, deadline))
+    }
+
+    // deadlineがnow以前のentryをすべて取り除き，deadline順にon_evictのfへ渡す．fがなければdropする．取り除いた数を返す．
+    pub fn evict_expired(&mut self, now: &D) -> usize {
+        let mut evictor = self.evictor.take();
+        let mut evicted = 0;
+        for (key, value) in self.expire(now) {
+            if let Some(evictor) = &mut evictor {
+                evictor(key, value, EvictionReason::Expired);
+            }
+            evicted += 1;
+        }
+        self.evictor = evictor;
+        evicted
+    }
+
+    // deadlineがnow以前のentryをdeadline順に取り除く．途中でdropすれば残りはそのまま残る．
+    pub fn expire<'a>(&'a mut self, now: &'a D) -> Expired<'a, K, V, D, G> {
+        Expired { list: self, now }
+    }
+}
+
+pub struct Expired<'a, K: Ord, V, D: Ord, G: Generator<bool>> {
+    list: &'a mut ExpiringSkipList<K, V, D, G>,
+    now: &'a D,
+}
+
+impl<K: Ord, V, D: Ord, G: Generator<bool>> Iterator for Expired<'_, K, V, D, G> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (deadline, _) = self.list.deadlines.peek()?;
+        if deadline > self.now {
+            return None;
+        }
+
+        let (_, node) = self.list.deadlines.pop()?;
+        let key = node.key();
+        let removed = self.list.entries.remove_by(|next, _| next.cmp(key)).ok()?;
+        let (key, (value, _)) = removed.dispose();
+        Some((key, value))
+    }
+}
+
+// deadlinesはentriesが所有するnodeだけを指すので，SkipListと同じくSend/Syncにできる．
+unsafe impl<K: Ord + Send, V: Send, D: Ord + Send, G: Generator<bool> + Send> Send
+    for ExpiringSkipList<K, V, D, G>
+{
+}
+unsafe impl<K: Ord + Sync, V: Sync, D: Ord + Sync, G: Generator<bool> + Sync> Sync
+    for ExpiringSkipList<K, V, D, G>
+{
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{EvictionReason, ExpiringSkipList, InsertError, RemoveError};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::sync::{Arc, Mutex};
+
+    #[mockalloc::test]
+    fn expire_in_deadline_order() {
+        let mut list = ExpiringSkipList::new(|| Gen::standard(SmallRng::from_entropy()));
+
+        list.insert("a", 1, 30).unwrap();
+        list.insert("b", 2, 10).unwrap();
+        list.insert("c", 3, 20).unwrap();
+        assert_eq!(
+            list.insert("a", 4, 40),
+            Err(InsertError::Occupied("a", (4, 40)))
+        );
+
+        assert_eq!(list.get(&"b", &5), Some(&2));
+        assert_eq!(list.get(&"b", &10), None);
+        assert!(!list.contains_key(&"c", &25));
+        assert_eq!(list.deadline(&"c"), Some(&20));
+        assert_eq!(list.next_deadline(), Some(&10));
+        assert_eq!(list.count(), 3);
+
+        assert!(list.expire(&25).eq([("b", 2), ("c", 3)]));
+        assert_eq!(list.count(), 1);
+        assert_eq!(list.next_deadline(), Some(&30));
+        assert!(list.expire(&25).next().is_none());
+
+        assert_eq!(list.remove(&"a"), Ok(("a", 1, 30)));
+        assert_eq!(list.remove(&"a"), Err(RemoveError::NotFound));
+        assert_eq!(list.next_deadline(), None);
+    }
+
+    #[mockalloc::test]
+    fn partial_expire() {
+        let mut list = ExpiringSkipList::new(|| Gen::standard(SmallRng::from_entropy()));
+        for key in 0..10 {
+            list.insert(key, key, 100 - key).unwrap();
+        }
+
+        {
+            let mut expired = list.expire(&95);
+            assert_eq!(expired.next(), Some((9, 9)));
+        }
+
+        assert_eq!(list.count(), 9);
+        assert!(list.expire(&95).map(|(k, _)| k).eq([8, 7, 6, 5]));
+        assert_eq!(list.get(&4, &95), Some(&4));
+    }
+
+    #[mockalloc::test]
+    fn evict_expired() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut list = ExpiringSkipList::new(|| Gen::standard(SmallRng::from_entropy()));
+        for key in 0..6 {
+            list.insert(key, key * 10, 10 - key).unwrap();
+        }
+        assert_eq!(list.evict_expired(&7), 3);
+        assert_eq!(list.count(), 3);
+
+        let sink = evicted.clone();
+        list.on_evict(move |key, value, reason| sink.lock().unwrap().push((key, value, reason)));
+        assert_eq!(list.evict_expired(&9), 2);
+        assert_eq!(list.evict_expired(&9), 0);
+        assert!(list.expire(&10).eq([(0, 0)]));
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            [
+                (2, 20, EvictionReason::Expired),
+                (1, 10, EvictionReason::Expired)
+            ]
+        );
+    }
+
+    // 別のthreadから定期的にevict_expiredを呼ぶ．
+    #[test]
+    fn background_sweep() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut list = ExpiringSkipList::new(|| Gen::standard(SmallRng::from_entropy()));
+        let sink = evicted.clone();
+        list.on_evict(move |key, _, _| sink.lock().unwrap().push(key));
+        for key in 0..10u32 {
+            list.insert(key, key.to_string(), key * 10).unwrap();
+        }
+
+        let list = Arc::new(Mutex::new(list));
+        let sweeper = {
+            let list = list.clone();
+            std::thread::spawn(move || {
+                for now in [15, 55] {
+                    list.lock().unwrap().evict_expired(&now);
+                }
+            })
+        };
+        sweeper.join().unwrap();
+        assert_eq!(*evicted.lock().unwrap(), [0, 1, 2, 3, 4, 5]);
+        let list = list.lock().unwrap();
+        assert_eq!(list.count(), 4);
+        assert_eq!(list.get(&6, &55), Some(&"6".to_string()));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Generator, SkipList};
+
+// searchがlevelでkeyと比べたnode．positionはheadを0とした1始まりの位置．
+// advancedならそのnodeへ進み，そうでなければそこで止まって1つ下のlevelへ下りた．
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step<'a, K> {
+    pub level: usize,
+    pub key: &'a K,
+    pub position: usize,
+    pub advanced: bool,
+}
+
+// stepsは比べた順に並ぶので，その数がsearchの比較の回数になる．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchPath<'a, K> {
+    pub steps: Vec<Step<'a, K>>,
+    pub found: bool,
+}
+
+impl<K> SearchPath<'_, K> {
+    pub fn comparisons(&self) -> usize {
+        self.steps.len()
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // searchと同じようにheadから下りる．hash indexやBloom filterがあっても使わず，listの形だけを辿る．
+    pub fn explain(&self, key: &K) -> SearchPath<'_, K> {
+        let mut forwards = self.nodes.as_slice();
+        let mut rank = 0;
+        let mut steps = Vec::new();
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                let position = rank + forwards[level].span;
+                let advanced = next.key() < key;
+                steps.push(Step {
+                    level,
+                    key: next.key(),
+                    position,
+                    advanced,
+                });
+                if !advanced {
+                    break;
+                }
+                rank = position;
+                forwards = next.nexts();
+            }
+        }
+        let found = forwards[0].next.take().is_some_and(|n| n.key() == key);
+        SearchPath { steps, found }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SearchPath, Step};
+    use crate::test::Gen;
+    use crate::test_util::from_levels;
+    use crate::{Metrics, Operation, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn step(level: usize, key: &i32, position: usize, advanced: bool) -> Step<'_, i32> {
+        Step {
+            level,
+            key,
+            position,
+            advanced,
+        }
+    }
+
+    #[mockalloc::test]
+    fn explain() {
+        let list = from_levels(|| false, [(1, (), 1), (3, (), 3), (5, (), 1), (7, (), 2)]);
+        assert_eq!(
+            list.explain(&5),
+            SearchPath {
+                steps: vec![
+                    step(2, &3, 2, true),
+                    step(1, &7, 4, false),
+                    step(0, &5, 3, false),
+                ],
+                found: true,
+            }
+        );
+        // 末尾より大きいkeyは，最後のnodeから下りるだけで比べない．
+        assert_eq!(
+            list.explain(&8),
+            SearchPath {
+                steps: vec![step(2, &3, 2, true), step(1, &7, 4, true)],
+                found: false,
+            }
+        );
+        assert_eq!(
+            list.explain(&0),
+            SearchPath {
+                steps: vec![
+                    step(2, &3, 2, false),
+                    step(1, &3, 2, false),
+                    step(0, &1, 1, false),
+                ],
+                found: false,
+            }
+        );
+    }
+
+    struct Comparisons(Arc<AtomicUsize>);
+
+    impl Metrics for Comparisons {
+        fn operation(&self, op: Operation, comparisons: usize) {
+            if op == Operation::Search {
+                self.0.store(comparisons, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[mockalloc::test]
+    fn matches_search() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..300 {
+            let key = rng.gen_range(0..1000);
+            list.insert(key, key).ok();
+        }
+        let comparisons = Arc::new(AtomicUsize::new(0));
+        list.set_metrics(Comparisons(comparisons.clone()));
+        for key in 0..1000 {
+            let path = list.explain(&key);
+            assert_eq!(path.found, list.search(&key).is_some());
+            assert_eq!(path.comparisons(), comparisons.load(Ordering::Relaxed));
+            for step in &path.steps {
+                assert_eq!(list.node_at(step.position).map(|n| n.key()), Some(step.key));
+                assert_eq!(step.advanced, step.key < &key);
+            }
+        }
+    }
+}
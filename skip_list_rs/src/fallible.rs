@@ -0,0 +1,142 @@
+use std::cmp::Ordering;
+
+use crate::{node::Node, Change, CompareInsertError, Generator, Operation, SkipList};
+
+// 比較が失敗しうるkeyのための版．fは，listのkeyを探すkeyと比べた順序を返す．
+// 先に比較だけで位置を決めてから，位置を頼りにつなぎ直すので，比較が失敗してもlistは変わらない．
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn try_search_by<E>(
+        &self,
+        f: impl FnMut(&K) -> Result<Ordering, E>,
+    ) -> Result<Option<(&K, &V)>, E> {
+        let (_, found) = self.try_locate(Operation::Search, f)?;
+        Ok(found.map(|node| (node.key(), node.value())))
+    }
+
+    // cmp(listのkey, 挿入するkey)．
+    pub fn try_insert_by<E>(
+        &mut self,
+        key: K,
+        value: V,
+        mut cmp: impl FnMut(&K, &K) -> Result<Ordering, E>,
+    ) -> Result<(), CompareInsertError<K, V, E>> {
+        let pos = match self.try_locate(Operation::Insert, |k| cmp(k, &key)) {
+            Err(e) => return Err(CompareInsertError::Compare(e, key, value)),
+            Ok((_, Some(_))) => return Err(CompareInsertError::Occupied(key, value)),
+            Ok((pos, None)) => pos,
+        };
+        let locate = |_: &K, next: usize, _: &K| {
+            if next < pos {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        };
+        let Ok((node, pos)) = self.insert_by(locate, key, value) else {
+            unreachable!("position based locate never reports a duplicate")
+        };
+        self.check_neighbours(node, pos);
+        self.debug_check(true);
+        self.hash_in(node);
+        self.notify(Change::Inserted(node.key()));
+        Ok(())
+    }
+
+    pub fn try_remove_by<E>(
+        &mut self,
+        f: impl FnMut(&K) -> Result<Ordering, E>,
+    ) -> Result<Option<(K, V)>, E> {
+        let (pos, found) = self.try_locate(Operation::Remove, f)?;
+        if found.is_none() {
+            return Ok(None);
+        }
+        let Ok(removed) = self.remove_by(|_, next| next.cmp(&pos)) else {
+            unreachable!("the located node is not in the list")
+        };
+        self.hash_out(removed);
+        self.notify(Change::Removed(removed.key()));
+        Ok(Some(removed.dispose()))
+    }
+
+    // fがEqualを返したnodeとその位置を返す．なければ，そのkeyが入るはずの位置を返す．位置は1始まり．
+    fn try_locate<E>(
+        &self,
+        op: Operation,
+        mut f: impl FnMut(&K) -> Result<Ordering, E>,
+    ) -> Result<(usize, Option<Node<K, V>>), E> {
+        let mut forwards = self.nodes.as_slice();
+        let mut rank = 0;
+        let mut comparisons = 0;
+
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                comparisons += 1;
+                let ord = f(next.key()).inspect_err(|_| self.report(op, comparisons))?;
+                match ord {
+                    Ordering::Less => {}
+                    Ordering::Equal => {
+                        self.report(op, comparisons);
+                        return Ok((rank + forwards[level].span, Some(next)));
+                    }
+                    Ordering::Greater => break,
+                }
+                rank += forwards[level].span;
+                forwards = next.nexts();
+            }
+        }
+        self.report(op, comparisons);
+        Ok((rank + 1, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::{CompareInsertError, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::cmp::Ordering;
+
+    // 13との比較だけ失敗する．13はlistに入らないので，13を探すと必ず失敗する．
+    fn cmp(a: &i32, b: &i32) -> Result<Ordering, &'static str> {
+        if *a == 13 || *b == 13 {
+            return Err("unlucky");
+        }
+        Ok(a.cmp(b))
+    }
+
+    #[mockalloc::test]
+    fn fallible() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in (1..100).filter(|k| k % 3 != 0 && *k != 13) {
+            assert_eq!(list.try_insert_by(key, key * 10, cmp), Ok(()));
+        }
+        check_spans(&list);
+        assert_eq!(list.try_search_by(|k| cmp(k, &50)), Ok(Some((&50, &500))));
+        assert_eq!(list.try_search_by(|k| cmp(k, &51)), Ok(None));
+        assert_eq!(list.try_search_by(|k| cmp(k, &13)), Err("unlucky"));
+
+        assert_eq!(
+            list.try_insert_by(50, 0, cmp),
+            Err(CompareInsertError::Occupied(50, 0))
+        );
+        assert_eq!(
+            list.try_insert_by(13, 0, cmp),
+            Err(CompareInsertError::Compare("unlucky", 13, 0))
+        );
+        assert_eq!(list.try_insert_by(51, 510, cmp), Ok(()));
+        assert_eq!(list.try_insert_by(0, 0, cmp), Ok(()));
+        assert_eq!(list.count(), 67);
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+
+        assert_eq!(list.try_remove_by(|k| cmp(k, &50)), Ok(Some((50, 500))));
+        assert_eq!(list.try_remove_by(|k| cmp(k, &50)), Ok(None));
+        assert_eq!(list.try_remove_by(|k| cmp(k, &13)), Err("unlucky"));
+        assert_eq!(list.try_remove_by(|k| cmp(k, &0)), Ok(Some((0, 0))));
+        assert_eq!(list.count(), 65);
+        check_spans(&list);
+        let keys = (1..100).filter(|k| (k % 3 != 0 && *k != 13 && *k != 50) || *k == 51);
+        assert!(list.iter().map(|(k, _)| *k).eq(keys));
+    }
+}
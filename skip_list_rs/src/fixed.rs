@@ -0,0 +1,317 @@
+use std::{error::Error, fmt, marker::PhantomData, mem::MaybeUninit};
+
+use crate::{Generator, RemoveError};
+
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FixedInsertError<K, V> {
+    Occupied(K, V),
+    Full(K, V),
+}
+
+impl<K, V> FixedInsertError<K, V> {
+    pub fn into_inner(self) -> (K, V) {
+        match self {
+            FixedInsertError::Occupied(key, value) | FixedInsertError::Full(key, value) => {
+                (key, value)
+            }
+        }
+    }
+}
+
+impl<K, V> fmt::Display for FixedInsertError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixedInsertError::Occupied(..) => f.write_str("the key is already present"),
+            FixedInsertError::Full(..) => f.write_str("the list is full"),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for FixedInsertError<K, V> {}
+
+struct Slot<K, V, const L: usize> {
+    entry: MaybeUninit<(K, V)>,
+    level: usize,
+    nexts: [usize; L],
+}
+
+// 容量Nのslotと高さLのtowerを配列で持ち，一切allocateしない．
+// linkはslotのindexで，空きslotはnexts[0]でつないだfree listにする．
+pub struct FixedSkipList<K: Ord, V, G: Generator<bool>, const N: usize, const L: usize> {
+    gen: G,
+    count: usize,
+    head: [usize; L],
+    free: usize,
+    slots: [Slot<K, V, L>; N],
+}
+
+impl<K: Ord, V, G: Generator<bool>, const N: usize, const L: usize> FixedSkipList<K, V, G, N, L> {
+    pub fn new(gen: G) -> Self {
+        assert!(L > 0, "tower height should be > 0");
+        Self {
+            gen,
+            count: 0,
+            head: [NIL; L],
+            free: if N == 0 { NIL } else { 0 },
+            slots: std::array::from_fn(|idx| {
+                let mut nexts = [NIL; L];
+                nexts[0] = if idx + 1 < N { idx + 1 } else { NIL };
+                Slot {
+                    entry: MaybeUninit::uninit(),
+                    level: 0,
+                    nexts,
+                }
+            }),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.free == NIL
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), FixedInsertError<K, V>> {
+        let forwards = self.find_forwards(&key);
+        if let Some(found) = self.next_of(forwards[0], 0) {
+            if self.key(found) == &key {
+                return Err(FixedInsertError::Occupied(key, value));
+            }
+        }
+
+        let idx = self.free;
+        if idx == NIL {
+            return Err(FixedInsertError::Full(key, value));
+        }
+        self.free = self.slots[idx].nexts[0];
+
+        let level = {
+            let mut size = 1;
+            while size < L && self.gen.gen() {
+                size += 1;
+            }
+            size
+        };
+
+        let slot = &mut self.slots[idx];
+        slot.entry.write((key, value));
+        slot.level = level;
+        for (level, &from) in forwards.iter().enumerate().take(level) {
+            let next = self.nexts(from)[level];
+            self.slots[idx].nexts[level] = next;
+            self.nexts_mut(from)[level] = idx;
+        }
+        self.count += 1;
+
+        Ok(())
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        let idx = self.find(key)?;
+        let (_, value) = unsafe { self.slots[idx].entry.assume_init_ref() };
+        Some(value)
+    }
+
+    pub fn search_mut(&mut self, key: &K) -> Option<&mut V> {
+        let idx = self.find(key)?;
+        let (_, value) = unsafe { self.slots[idx].entry.assume_init_mut() };
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        let idx = self.next_of(N, 0)?;
+        let (key, value) = unsafe { self.slots[idx].entry.assume_init_ref() };
+        Some((key, value))
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let forwards = self.find_forwards(key);
+        let Some(idx) = self.next_of(forwards[0], 0) else {
+            return Err(RemoveError::NotFound);
+        };
+        if self.key(idx) != key {
+            return Err(RemoveError::NotFound);
+        }
+
+        for (level, &from) in forwards.iter().enumerate().take(self.slots[idx].level) {
+            let next = self.slots[idx].nexts[level];
+            self.nexts_mut(from)[level] = next;
+        }
+
+        let slot = &mut self.slots[idx];
+        slot.nexts = [NIL; L];
+        slot.nexts[0] = self.free;
+        slot.level = 0;
+        self.free = idx;
+        self.count -= 1;
+
+        Ok(unsafe { slot.entry.assume_init_read() })
+    }
+
+    pub fn iter(&self) -> FixedIter<'_, K, V, L> {
+        FixedIter {
+            slots: &self.slots,
+            idx: self.head[0],
+            marker: PhantomData,
+        }
+    }
+
+    // 各levelでkeyより前にある最後のslot(Nはhead)を返す．
+    fn find_forwards(&self, key: &K) -> [usize; L] {
+        let mut forwards = [N; L];
+        let mut from = N;
+
+        for level in (0..L).rev() {
+            while let Some(next) = self.next_of(from, level) {
+                if self.key(next) >= key {
+                    break;
+                }
+                from = next;
+            }
+            forwards[level] = from;
+        }
+
+        forwards
+    }
+
+    fn find(&self, key: &K) -> Option<usize> {
+        let forwards = self.find_forwards(key);
+        let idx = self.next_of(forwards[0], 0)?;
+        (self.key(idx) == key).then_some(idx)
+    }
+
+    fn key(&self, idx: usize) -> &K {
+        let (key, _) = unsafe { self.slots[idx].entry.assume_init_ref() };
+        key
+    }
+
+    fn next_of(&self, from: usize, level: usize) -> Option<usize> {
+        let next = self.nexts(from)[level];
+        (next != NIL).then_some(next)
+    }
+
+    fn nexts(&self, from: usize) -> &[usize; L] {
+        if from == N {
+            &self.head
+        } else {
+            &self.slots[from].nexts
+        }
+    }
+
+    fn nexts_mut(&mut self, from: usize) -> &mut [usize; L] {
+        if from == N {
+            &mut self.head
+        } else {
+            &mut self.slots[from].nexts
+        }
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>, const N: usize, const L: usize> Drop
+    for FixedSkipList<K, V, G, N, L>
+{
+    fn drop(&mut self) {
+        let mut idx = self.head[0];
+        while idx != NIL {
+            let slot = &mut self.slots[idx];
+            idx = slot.nexts[0];
+            unsafe { slot.entry.assume_init_drop() };
+        }
+    }
+}
+
+pub struct FixedIter<'a, K: 'a, V: 'a, const L: usize> {
+    slots: &'a [Slot<K, V, L>],
+    idx: usize,
+    marker: PhantomData<&'a (K, V)>,
+}
+
+impl<'a, K: 'a, V: 'a, const L: usize> Iterator for FixedIter<'a, K, V, L> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx == NIL {
+            return None;
+        }
+        let slot = &self.slots[self.idx];
+        self.idx = slot.nexts[0];
+        let (key, value) = unsafe { slot.entry.assume_init_ref() };
+        Some((key, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{FixedInsertError, FixedSkipList, RemoveError};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn fills_up() {
+        let info = mockalloc::record_allocs(fills_up_impl);
+        assert_eq!(info.num_allocs(), 0);
+    }
+
+    fn fills_up_impl() {
+        let mut list: FixedSkipList<_, _, _, 4, 3> =
+            FixedSkipList::new(Gen::standard(SmallRng::seed_from_u64(0)));
+
+        for key in [3, 1, 4, 2] {
+            list.insert(key, key * 10).unwrap();
+        }
+        assert!(list.is_full());
+        assert_eq!(list.insert(4, 0), Err(FixedInsertError::Occupied(4, 0)));
+        assert_eq!(list.insert(5, 50), Err(FixedInsertError::Full(5, 50)));
+
+        assert_eq!(list.remove(&3), Ok((3, 30)));
+        assert_eq!(list.remove(&3), Err(RemoveError::NotFound));
+        assert_eq!(list.insert(5, 50), Ok(()));
+        *list.search_mut(&1).unwrap() = 11;
+
+        assert_eq!(list.first(), Some((&1, &11)));
+        assert!(list.iter().map(|(k, _)| *k).eq([1, 2, 4, 5]));
+        assert_eq!(list.count(), list.capacity());
+    }
+
+    #[test]
+    fn random_against_btree() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list: FixedSkipList<_, _, _, 32, 5> =
+            FixedSkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut model = BTreeMap::new();
+
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..64);
+            if rng.gen_bool(0.5) {
+                let expected = if model.contains_key(&key) {
+                    Err(FixedInsertError::Occupied(key, key.to_string()))
+                } else if model.len() == 32 {
+                    Err(FixedInsertError::Full(key, key.to_string()))
+                } else {
+                    model.insert(key, key.to_string());
+                    Ok(())
+                };
+                assert_eq!(list.insert(key, key.to_string()), expected);
+            } else {
+                assert_eq!(list.remove(&key).ok(), model.remove_entry(&key));
+            }
+            assert_eq!(list.search(&key), model.get(&key));
+        }
+
+        assert!(list.iter().eq(model.iter()));
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    iter::Zip,
+    ops::{Bound, RangeBounds},
+    slice,
+};
+
+use crate::{Generator, SkipList};
+
+// keyとvalueを昇順の配列に詰め直したもの．keyの配列をそのまま二分探索するので，
+// nodeを辿らずに済み，rangeは連続した領域になる．変更はできない．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrozenSkipList<K, V> {
+    keys: Vec<K>,
+    values: Vec<V>,
+}
+
+pub type FrozenIter<'a, K, V> = Zip<slice::Iter<'a, K>, slice::Iter<'a, V>>;
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn freeze(self) -> FrozenSkipList<K, V> {
+        let mut keys = Vec::with_capacity(self.count);
+        let mut values = Vec::with_capacity(self.count);
+        for (key, value) in self {
+            keys.push(key);
+            values.push(value);
+        }
+        FrozenSkipList { keys, values }
+    }
+}
+
+impl<K: Ord, V> FrozenSkipList<K, V> {
+    pub fn count(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let pos = self.keys.binary_search(key).ok()?;
+        Some(&self.values[pos])
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.keys.binary_search(key).is_ok()
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        Some((self.keys.first()?, self.values.first()?))
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        Some((self.keys.last()?, self.values.last()?))
+    }
+
+    // SkipList::rangeと同じく，逆さまの範囲ではpanicする．
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> FrozenIter<'_, K, V> {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e))
+                if s > e =>
+            {
+                panic!("range start is greater than range end")
+            }
+            (Bound::Excluded(s), Bound::Excluded(e)) if s == e => {
+                panic!("range start and end are equal and excluded")
+            }
+            _ => {}
+        }
+
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.keys.partition_point(|k| k < start),
+            Bound::Excluded(start) => self.keys.partition_point(|k| k <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.keys.partition_point(|k| k <= end),
+            Bound::Excluded(end) => self.keys.partition_point(|k| k < end),
+            Bound::Unbounded => self.keys.len(),
+        };
+        self.keys[start..end].iter().zip(&self.values[start..end])
+    }
+
+    pub fn iter(&self) -> FrozenIter<'_, K, V> {
+        self.keys.iter().zip(&self.values)
+    }
+
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::ops::Bound;
+
+    #[mockalloc::test]
+    fn freeze() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut copy = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..300 {
+            let key = rng.gen_range(0..1000);
+            if list.insert(key, key * 2).is_ok() {
+                copy.insert(key, key * 2).unwrap();
+            }
+        }
+
+        let frozen = list.freeze();
+        assert_eq!(frozen.count(), copy.count());
+        assert!(frozen.iter().eq(copy.iter()));
+        assert_eq!(frozen.first(), copy.first());
+        assert_eq!(frozen.last(), copy.last());
+        for key in 0..1000 {
+            assert_eq!(frozen.get(&key), copy.search(&key));
+        }
+        for _ in 0..50 {
+            let start = rng.gen_range(0..1000);
+            let end = rng.gen_range(start..1000);
+            let bounds = (Bound::Excluded(start), Bound::Included(end));
+            assert!(frozen.range(bounds).eq(copy.range(bounds)));
+            assert!(frozen.range(start..).eq(copy.range(start..)));
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::SkipList;
+
 pub trait Generator<T> {
     fn gen(&mut self) -> T;
 }
@@ -7,3 +9,103 @@ impl<T, F: FnMut() -> T> Generator<T> for F {
         self()
     }
 }
+
+// 型に出さずにGeneratorを取り替えられるよう，boxしたものもGeneratorとして使える．
+impl<T> Generator<T> for Box<dyn Generator<T> + Send> {
+    fn gen(&mut self) -> T {
+        (**self).gen()
+    }
+}
+
+pub type DynSkipList<K, V> = SkipList<K, V, Box<dyn Generator<bool> + Send>>;
+
+impl<K: Ord, V> DynSkipList<K, V> {
+    pub fn new_boxed(gen: impl Generator<bool> + Send + 'static) -> Self {
+        Self::new(Box::new(gen))
+    }
+}
+
+// OSの乱数を使わずに，与えたseedから決まった列を返すxorshift64*．wasm32-unknown-unknownのように
+// entropyを得られない環境でも使える．
+#[derive(Debug, Clone)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    // 0は不動点になるので避ける．
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+}
+
+impl Default for XorShift {
+    fn default() -> Self {
+        Self::new(0x9e37_79b9_7f4a_7c15)
+    }
+}
+
+impl Generator<bool> for XorShift {
+    fn gen(&mut self) -> bool {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        // 下位のbitは偏るので，最上位のbitを使う．
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 63 == 1
+    }
+}
+
+impl<K: Ord, V> Default for SkipList<K, V, XorShift> {
+    fn default() -> Self {
+        Self::new(XorShift::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{DynSkipList, Generator, SkipList, XorShift};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn dyn_generator() {
+        let mut flips = false;
+        let mut lists: Vec<DynSkipList<i32, i32>> = vec![
+            DynSkipList::new_boxed(Gen::standard(SmallRng::from_entropy())),
+            DynSkipList::new_boxed(|| false),
+            DynSkipList::new_boxed(move || {
+                flips = !flips;
+                flips
+            }),
+        ];
+        for list in &mut lists {
+            for key in 0..100 {
+                list.insert(key, key).unwrap();
+            }
+            assert_eq!(list.check_invariants(), Ok(()));
+        }
+        assert_eq!(lists[1].height(), 1);
+    }
+
+    #[mockalloc::test]
+    fn xorshift() {
+        let mut a = XorShift::new(42);
+        let mut b = XorShift::new(42);
+        let bits: Vec<bool> = (0..10_000).map(|_| a.gen()).collect();
+        assert!((0..10_000).all(|i| b.gen() == bits[i]));
+        let ones = bits.iter().filter(|b| **b).count();
+        assert!((4500..5500).contains(&ones));
+
+        let mut list = SkipList::default();
+        for key in 0..1000 {
+            list.insert(key, key).unwrap();
+        }
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.height() > 5);
+        // seedが0でも同じ値に張りつかない．
+        let mut zero = XorShift::new(0);
+        let bits: Vec<bool> = (0..100).map(|_| zero.gen()).collect();
+        assert!(bits.contains(&true) && bits.contains(&false));
+    }
+}
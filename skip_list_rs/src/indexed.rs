@@ -0,0 +1,257 @@
+use std::{
+    cmp::Ordering,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{iter::Iter, node::Node, Generator, InsertError, RemoveError, SkipList};
+
+// 副keyが同じentryは主keyの順に並べる．
+struct Secondary<K: Ord, V, S: Ord> {
+    key: S,
+    node: Node<K, V>,
+}
+
+impl<K: Ord, V, S: Ord> Secondary<K, V, S> {
+    fn cmp_with(&self, key: &S, primary: &K) -> Ordering {
+        (&self.key, self.node.key()).cmp(&(key, primary))
+    }
+}
+
+impl<K: Ord, V, S: Ord> PartialEq for Secondary<K, V, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V, S: Ord> Eq for Secondary<K, V, S> {}
+
+impl<K: Ord, V, S: Ord> PartialOrd for Secondary<K, V, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V, S: Ord> Ord for Secondary<K, V, S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_with(&other.key, other.node.key())
+    }
+}
+
+// valueの射影を副keyとするlistを併せて持つ．射影は同じvalueに対して同じ結果を返すこと．
+// valueはupdateからしか書き換えられないので，副keyは常にvalueと一致する．
+pub struct SkipIndexedMap<K: Ord, V, S: Ord, F: Fn(&V) -> S, G: Generator<bool>> {
+    primary: SkipList<K, V, G>,
+    secondary: SkipList<Secondary<K, V, S>, (), G>,
+    project: F,
+}
+
+impl<K: Ord, V, S: Ord, F: Fn(&V) -> S, G: Generator<bool>> SkipIndexedMap<K, V, S, F, G> {
+    pub fn new(project: F, mut make_gen: impl FnMut() -> G) -> Self {
+        Self {
+            primary: SkipList::new(make_gen()),
+            secondary: SkipList::new(make_gen()),
+            project,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.primary.count()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        let (node, _) = self
+            .primary
+            .insert_by(|next, _, key| next.cmp(key), key, value)
+            .map_err(|(key, value)| InsertError::Occupied(key, value))?;
+        self.link(node);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.primary.search(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.primary.contains_key(key)
+    }
+
+    // 副keyを外してからvalueを書き換え，新しい副keyでつなぎ直す．
+    // fがpanicしても，書き換えられたところまでのvalueの副keyでつなぎ直す．
+    pub fn update<R>(&mut self, key: &K, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        let node = self.primary.find_first(|k| k < key).take()?;
+        if node.key() != key {
+            return None;
+        }
+
+        self.unlink(node);
+        let relink = Relink { map: self, node };
+        let result = f(node.value_mut());
+        drop(relink);
+        Some(result)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let node = self
+            .primary
+            .remove_by(|next, _| next.cmp(key))
+            .map_err(|()| RemoveError::NotFound)?;
+        self.unlink(node);
+        Ok(node.dispose())
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.primary.iter()
+    }
+
+    pub fn get_by_secondary<'a>(&'a self, key: &'a S) -> impl Iterator<Item = (&'a K, &'a V)> {
+        self.range_by_secondary((Bound::Included(key), Bound::Included(key)))
+    }
+
+    // 副keyの順，同じ副keyの中では主keyの順に返す．
+    pub fn range_by_secondary<R: RangeBounds<S>>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = (&K, &V)> {
+        let start = |e: &Secondary<K, V, S>| match range.start_bound() {
+            Bound::Included(b) => &e.key < b,
+            Bound::Excluded(b) => &e.key <= b,
+            Bound::Unbounded => false,
+        };
+        let end = |e: &Secondary<K, V, S>| match range.end_bound() {
+            Bound::Included(b) => &e.key <= b,
+            Bound::Excluded(b) => &e.key < b,
+            Bound::Unbounded => true,
+        };
+
+        self.secondary
+            .range_by(start, end)
+            .map(|(e, _)| (e.node.key(), e.node.value()))
+    }
+
+    fn link(&mut self, node: Node<K, V>) {
+        let entry = Secondary {
+            key: (self.project)(node.value()),
+            node,
+        };
+        let inserted = self
+            .secondary
+            .insert_by(|next, _, e| next.cmp(e), entry, ());
+        debug_assert!(inserted.is_ok());
+    }
+
+    fn unlink(&mut self, node: Node<K, V>) {
+        let key = (self.project)(node.value());
+        let removed = self
+            .secondary
+            .remove_by(|next, _| next.cmp_with(&key, node.key()));
+        let Ok(removed) = removed else {
+            unreachable!("every entry has a secondary key");
+        };
+        removed.dispose();
+    }
+}
+
+struct Relink<'a, K: Ord, V, S: Ord, F: Fn(&V) -> S, G: Generator<bool>> {
+    map: &'a mut SkipIndexedMap<K, V, S, F, G>,
+    node: Node<K, V>,
+}
+
+impl<K: Ord, V, S: Ord, F: Fn(&V) -> S, G: Generator<bool>> Drop for Relink<'_, K, V, S, F, G> {
+    fn drop(&mut self) {
+        self.map.link(self.node);
+    }
+}
+
+// secondaryはprimaryが所有するnodeだけを指すので，SkipListと同じくSend/Syncにできる．
+unsafe impl<K, V, S, F, G> Send for SkipIndexedMap<K, V, S, F, G>
+where
+    K: Ord + Send,
+    V: Send,
+    S: Ord + Send,
+    F: Fn(&V) -> S + Send,
+    G: Generator<bool> + Send,
+{
+}
+unsafe impl<K, V, S, F, G> Sync for SkipIndexedMap<K, V, S, F, G>
+where
+    K: Ord + Sync,
+    V: Sync,
+    S: Ord + Sync,
+    F: Fn(&V) -> S + Sync,
+    G: Generator<bool> + Sync,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{InsertError, RemoveError, SkipIndexedMap};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn by_price() {
+        let mut items = SkipIndexedMap::new(
+            |(price, _): &(u32, &str)| *price,
+            || Gen::standard(SmallRng::from_entropy()),
+        );
+
+        items.insert(1, (300, "c")).unwrap();
+        items.insert(2, (100, "a")).unwrap();
+        items.insert(3, (200, "b")).unwrap();
+        items.insert(4, (100, "d")).unwrap();
+        assert_eq!(
+            items.insert(4, (0, "x")),
+            Err(InsertError::Occupied(4, (0, "x")))
+        );
+
+        let keys = |iter: &mut dyn Iterator<Item = (&i32, &(u32, &str))>| {
+            iter.map(|(k, _)| *k).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&mut items.get_by_secondary(&100)), [2, 4]);
+        assert_eq!(keys(&mut items.range_by_secondary(150..)), [3, 1]);
+        assert_eq!(keys(&mut items.range_by_secondary(..)), [2, 4, 3, 1]);
+
+        assert_eq!(items.update(&2, |(price, _)| *price = 400), Some(()));
+        assert_eq!(items.update(&9, |_| ()), None);
+        assert_eq!(keys(&mut items.range_by_secondary(..)), [4, 3, 1, 2]);
+
+        assert_eq!(items.remove(&3), Ok((3, (200, "b"))));
+        assert_eq!(items.remove(&3), Err(RemoveError::NotFound));
+        assert_eq!(keys(&mut items.range_by_secondary(200..=400)), [1, 2]);
+        assert_eq!(keys(&mut items.iter()), [1, 2, 4]);
+        assert_eq!(items.get(&4), Some(&(100, "d")));
+        assert_eq!(items.count(), 3);
+    }
+
+    #[test]
+    fn update_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        let mut items = SkipIndexedMap::new(
+            |price: &u32| *price,
+            || Gen::standard(SmallRng::from_entropy()),
+        );
+        for key in 0..10 {
+            items.insert(key, key * 10).unwrap();
+        }
+
+        // fがpanicしても，書き換えた後のvalueの副keyで引ける．
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            items.update(&3, |price| {
+                *price = 95;
+                panic!("update failed");
+            })
+        }));
+        assert!(result.is_err());
+        let keys: Vec<_> = items.range_by_secondary(..).map(|(k, _)| *k).collect();
+        assert_eq!(keys, [0, 1, 2, 4, 5, 6, 7, 8, 9, 3]);
+        assert_eq!(items.remove(&3), Ok((3, 95)));
+        assert_eq!(items.range_by_secondary(..).count(), 9);
+    }
+
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<SkipIndexedMap<i32, u32, u32, fn(&u32) -> u32, fn() -> bool>>();
+    }
+}
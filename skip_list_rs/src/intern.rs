@@ -0,0 +1,206 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher, RandomState},
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+// poolに一度だけ置いたkeyを指すhandle．同じpoolから得たhandleどうしは，pointerが同じなら比べずにEqualとする．
+pub struct Interned<T>(Arc<T>);
+
+impl<T> Clone for Interned<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Deref for Interned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Eq> PartialEq for Interned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for Interned<T> {}
+
+impl<T: Ord> PartialOrd for Interned<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Interned<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if Arc::ptr_eq(&self.0, &other.0) {
+            return Ordering::Equal;
+        }
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T: Hash> Hash for Interned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Interned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// 多くのlistで同じkeyを繰り返すときに，keyを一度だけ確保して共有する．
+// cloneしたpoolは中身を共有するので，listごとに配っても，threadをまたいでもよい．
+// hashはBorrowの約束どおり，&strとStringのように借用した形でも同じになること．
+pub struct KeyPool<T> {
+    shared: Arc<Mutex<Pool<T>>>,
+}
+
+struct Pool<T> {
+    hasher: RandomState,
+    buckets: HashMap<u64, Vec<Arc<T>>>,
+    len: usize,
+}
+
+impl<T> Clone for KeyPool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T: Hash + Eq> Default for KeyPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq> KeyPool<T> {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Pool {
+                hasher: RandomState::new(),
+                buckets: HashMap::new(),
+                len: 0,
+            })),
+        }
+    }
+
+    // 既にあればそのhandleを返し，なければToOwnedで作って置く．
+    pub fn intern<Q: ?Sized + Hash + Eq + ToOwned<Owned = T>>(&self, key: &Q) -> Interned<T>
+    where
+        T: Borrow<Q>,
+    {
+        let mut pool = self.shared.lock().unwrap();
+        let hash = pool.hasher.hash_one(key);
+        if let Some(found) = pool.find(hash, key) {
+            return found;
+        }
+        let interned = Arc::new(key.to_owned());
+        pool.buckets.entry(hash).or_default().push(interned.clone());
+        pool.len += 1;
+        Interned(interned)
+    }
+
+    // 置かずに探す．listをkeyで引くだけならこちらを使う．
+    pub fn get<Q: ?Sized + Hash + Eq>(&self, key: &Q) -> Option<Interned<T>>
+    where
+        T: Borrow<Q>,
+    {
+        let pool = self.shared.lock().unwrap();
+        let hash = pool.hasher.hash_one(key);
+        pool.find(hash, key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().unwrap().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // どのhandleからも指されなくなったkeyを捨て，その数を返す．
+    pub fn purge(&self) -> usize {
+        let mut pool = self.shared.lock().unwrap();
+        let before = pool.len;
+        pool.buckets.retain(|_, bucket| {
+            bucket.retain(|key| Arc::strong_count(key) > 1);
+            !bucket.is_empty()
+        });
+        pool.len = pool.buckets.values().map(Vec::len).sum();
+        before - pool.len
+    }
+}
+
+impl<T> Pool<T> {
+    fn find<Q: ?Sized + Eq>(&self, hash: u64, key: &Q) -> Option<Interned<T>>
+    where
+        T: Borrow<Q>,
+    {
+        let bucket = self.buckets.get(&hash)?;
+        let found = bucket.iter().find(|e| (***e).borrow() == key)?;
+        Some(Interned(found.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::KeyPool;
+    use crate::test::Gen;
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn shared_keys() {
+        let pool = KeyPool::<String>::new();
+        let mut lists: Vec<_> = (0..10)
+            .map(|_| SkipList::new(Gen::standard(SmallRng::from_entropy())))
+            .collect();
+        let words = ["pear", "apple", "fig", "banana"];
+        for (i, list) in lists.iter_mut().enumerate() {
+            for word in &words[..i % 4 + 1] {
+                list.insert(pool.intern(*word), i).unwrap();
+            }
+        }
+        assert_eq!(pool.len(), 4);
+
+        let apple = pool.get("apple").unwrap();
+        assert!(lists
+            .iter()
+            .enumerate()
+            .all(|(i, list)| list.contains_key(&apple) == (i % 4 > 0)));
+        assert!(lists[0].iter().map(|(k, _)| k.as_str()).eq(["pear"]));
+        assert!(lists[3]
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .eq(["apple", "banana", "fig", "pear"]));
+        assert!(pool.get("kiwi").is_none());
+        drop(apple);
+
+        // listを捨てると，どこからも指されないkeyだけがpurgeで消える．
+        lists.retain(|list| list.count() < 3);
+        assert_eq!(pool.purge(), 2);
+        assert_eq!(pool.purge(), 0);
+        lists.truncate(1);
+        assert_eq!(pool.purge(), 1);
+        assert_eq!(pool.len(), 1);
+        assert!(pool.get("pear").is_some());
+        drop(lists);
+        assert_eq!(pool.purge(), 1);
+        assert!(pool.is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{cell::Cell, marker::PhantomData};
+
+use crate::Generator;
+
+// 要素の中に埋め込むtower．levelが0ならどのlistにもつながっていない．
+pub struct Tower<'a, T, const N: usize> {
+    nexts: [Cell<Option<&'a T>>; N],
+    level: Cell<usize>,
+}
+
+impl<'a, T, const N: usize> Tower<'a, T, N> {
+    pub const fn new() -> Self {
+        Self {
+            nexts: [const { Cell::new(None) }; N],
+            level: Cell::new(0),
+        }
+    }
+
+    pub fn is_linked(&self) -> bool {
+        self.level.get() != 0
+    }
+
+    fn reset(&self) {
+        for next in &self.nexts[..self.level.get()] {
+            next.set(None);
+        }
+        self.level.set(0);
+    }
+}
+
+impl<'a, T, const N: usize> Default for Tower<'a, T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// towerを埋め込んだ要素．keyは要素がlistにつながっている間変えないこと．
+pub trait Intrusive<'a, const N: usize>: Sized + 'a {
+    type Key: Ord;
+    fn key(&self) -> &Self::Key;
+    fn tower(&self) -> &Tower<'a, Self, N>;
+}
+
+// 要素の確保は使う側が行い，listは'aの間借用してtowerを書き換えるだけで，何も確保しない．
+// towerの高さはNまで．dropすると，つながっていた要素のtowerを空に戻す．
+pub struct IntrusiveSkipList<'a, T: Intrusive<'a, N>, G: Generator<bool>, const N: usize> {
+    gen: G,
+    head: [Option<&'a T>; N],
+    count: usize,
+}
+
+impl<'a, T: Intrusive<'a, N>, G: Generator<bool>, const N: usize> IntrusiveSkipList<'a, T, G, N> {
+    pub fn new(gen: G) -> Self {
+        assert!(N > 0, "the tower must have at least one level");
+        Self {
+            gen,
+            head: [None; N],
+            count: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // 既に他のlistにつながっているか，同じkeyがあれば，要素をそのまま返す．
+    pub fn insert(&mut self, item: &'a T) -> Result<(), &'a T> {
+        if item.tower().is_linked() {
+            return Err(item);
+        }
+        let preds = self.preds(item.key());
+        if self
+            .next(preds[0], 0)
+            .is_some_and(|next| next.key() == item.key())
+        {
+            return Err(item);
+        }
+
+        let mut level = 1;
+        while level < N && self.gen.gen() {
+            level += 1;
+        }
+        let tower = item.tower();
+        for (l, pred) in preds.iter().enumerate().take(level) {
+            tower.nexts[l].set(self.next(*pred, l));
+            self.set_next(*pred, l, Some(item));
+        }
+        tower.level.set(level);
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn search(&self, key: &T::Key) -> Option<&'a T> {
+        let preds = self.preds(key);
+        self.next(preds[0], 0).filter(|next| next.key() == key)
+    }
+
+    pub fn remove(&mut self, key: &T::Key) -> Option<&'a T> {
+        let preds = self.preds(key);
+        let item = self.next(preds[0], 0).filter(|next| next.key() == key)?;
+        let tower = item.tower();
+        for (l, pred) in preds.iter().enumerate().take(tower.level.get()) {
+            self.set_next(*pred, l, tower.nexts[l].get());
+        }
+        tower.reset();
+        self.count -= 1;
+        Some(item)
+    }
+
+    pub fn first(&self) -> Option<&'a T> {
+        self.head[0]
+    }
+
+    pub fn iter(&self) -> IntrusiveIter<'a, '_, T, N> {
+        IntrusiveIter {
+            item: self.head[0],
+            marker: PhantomData,
+        }
+    }
+
+    // 各levelで，keyより小さい最後の要素(Noneはhead)．
+    fn preds(&self, key: &T::Key) -> [Option<&'a T>; N] {
+        let mut preds = [None; N];
+        let mut pred = None;
+        for level in (0..N).rev() {
+            while let Some(next) = self.next(pred, level) {
+                if next.key() >= key {
+                    break;
+                }
+                pred = Some(next);
+            }
+            preds[level] = pred;
+        }
+        preds
+    }
+
+    fn next(&self, pred: Option<&'a T>, level: usize) -> Option<&'a T> {
+        match pred {
+            Some(pred) => pred.tower().nexts[level].get(),
+            None => self.head[level],
+        }
+    }
+
+    fn set_next(&mut self, pred: Option<&'a T>, level: usize, next: Option<&'a T>) {
+        match pred {
+            Some(pred) => pred.tower().nexts[level].set(next),
+            None => self.head[level] = next,
+        }
+    }
+}
+
+impl<'a, T: Intrusive<'a, N>, G: Generator<bool>, const N: usize> Drop
+    for IntrusiveSkipList<'a, T, G, N>
+{
+    fn drop(&mut self) {
+        let mut item = self.head[0];
+        while let Some(i) = item {
+            item = i.tower().nexts[0].get();
+            i.tower().reset();
+        }
+    }
+}
+
+pub struct IntrusiveIter<'a, 'l, T: Intrusive<'a, N>, const N: usize> {
+    item: Option<&'a T>,
+    marker: PhantomData<&'l ()>,
+}
+
+impl<'a, T: Intrusive<'a, N>, const N: usize> Iterator for IntrusiveIter<'a, '_, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.item?;
+        self.item = item.tower().nexts[0].get();
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Intrusive, IntrusiveSkipList, Tower};
+    use crate::test::Gen;
+    use rand::rngs::SmallRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    struct Task<'a> {
+        deadline: u32,
+        name: String,
+        tower: Tower<'a, Task<'a>, 8>,
+    }
+
+    impl<'a> Intrusive<'a, 8> for Task<'a> {
+        type Key = u32;
+
+        fn key(&self) -> &u32 {
+            &self.deadline
+        }
+
+        fn tower(&self) -> &Tower<'a, Self, 8> {
+            &self.tower
+        }
+    }
+
+    #[mockalloc::test]
+    fn intrusive() {
+        let mut deadlines: Vec<u32> = (0..200).collect();
+        deadlines.shuffle(&mut SmallRng::from_entropy());
+        let tasks: Vec<_> = deadlines
+            .iter()
+            .map(|d| Task {
+                deadline: *d,
+                name: format!("task {d}"),
+                tower: Tower::new(),
+            })
+            .collect();
+        let twin = Task {
+            deadline: 5,
+            name: "twin".to_string(),
+            tower: Tower::new(),
+        };
+
+        {
+            let mut list = IntrusiveSkipList::new(Gen::standard(SmallRng::from_entropy()));
+            for task in &tasks {
+                list.insert(task).ok().unwrap();
+            }
+            assert!(list.insert(&tasks[0]).is_err());
+            assert!(list.insert(&twin).is_err());
+            assert!(!twin.tower.is_linked());
+            assert_eq!(list.count(), 200);
+            assert!(list.iter().map(|t| t.deadline).eq(0..200));
+            assert_eq!(list.search(&42).unwrap().name, "task 42");
+
+            let removed = list.remove(&5).unwrap();
+            assert!(!removed.tower.is_linked());
+            assert!(list.remove(&5).is_none());
+            list.insert(&twin).ok().unwrap();
+            assert_eq!(list.search(&5).unwrap().name, "twin");
+            assert_eq!(list.first().unwrap().deadline, 0);
+            assert_eq!(list.count(), 200);
+        }
+        // listをdropすると，要素は他のlistに入れられる．
+        assert!(tasks.iter().all(|t| !t.tower.is_linked()));
+        let mut other = IntrusiveSkipList::new(|| false);
+        for task in &tasks[..10] {
+            other.insert(task).ok().unwrap();
+        }
+        assert_eq!(other.count(), 10);
+    }
+}
@@ -1,52 +1,100 @@
-use std::{marker::PhantomData, mem::ManuallyDrop};
+use std::marker::PhantomData;
 
-use crate::{node::MaybeNode, Generator, SkipList};
+use crate::{
+    merge::seek,
+    node::{Chain, Link, MaybeNode, Node},
+    Generator, SkipList,
+};
+
+// nodeからlevel 0でn歩進んだnode．towerのうち飛び越さない最も高いlinkを選んで進むので，O(log n)で着く．
+// nodeの後ろにn個以上のnodeがつながっていること．
+fn jump<K: Ord, V>(mut node: Node<K, V>, mut n: usize) -> Node<K, V> {
+    while n > 0 {
+        let Some(link) = node
+            .nexts()
+            .iter()
+            .rev()
+            .find(|link| !link.next.is_null() && link.span <= n)
+        else {
+            unreachable!("remaining entries are linked at level 0");
+        };
+        n -= link.span;
+        node = link.next.take().unwrap();
+    }
+    node
+}
+
+// nodeの位置(1始まり)をheadから探す．nullなら最後のnodeの位置に1を足したもの．
+fn position<K: Ord, V>(head: &[Link<K, V>], node: MaybeNode<K, V>) -> usize {
+    let key = node.take().map(|node| node.key());
+    let mut forwards = head;
+    let mut rank = 0;
+    for level in (0..forwards.len()).rev() {
+        while let Some(next) = forwards[level].next.take() {
+            if key.is_some_and(|key| next.key() >= key) {
+                break;
+            }
+            rank += forwards[level].span;
+            forwards = next.nexts();
+        }
+    }
+    rank + 1
+}
 
 pub struct IntoIter<K: Ord, V> {
     pub(crate) node: MaybeNode<K, V>,
 }
 
 impl<K: Ord, V> IntoIter<K, V> {
-    pub(crate) fn new<G: Generator<bool>>(list: SkipList<K, V, G>) -> Self {
-        let mut me = ManuallyDrop::new(list);
-        unsafe { std::ptr::drop_in_place(&mut me.nodes) };
-        unsafe { std::ptr::drop_in_place(&mut me.gen) };
-
-        let head = me.nodes[0];
-
-        Self { node: head }
+    // level 0の鎖だけを切り離して持ち出す．残ったlistはnodeを持たないので，そのままdropしてよい．
+    pub(crate) fn new<G: Generator<bool>>(mut list: SkipList<K, V, G>) -> Self {
+        let head = std::mem::replace(&mut list.nodes[0], Link::null());
+        Self { node: head.next }
     }
 }
 
-impl<'a, K: Ord, V> Iterator for IntoIter<K, V> {
+impl<K: Ord, V> Iterator for IntoIter<K, V> {
     type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(node) = self.node.take() else {
-            return None;
-        };
+        let node = self.node.take()?;
 
-        self.node = node.nexts()[0];
+        self.node = node.nexts()[0].next;
 
         let pair = node.dispose();
         Some(pair)
     }
 }
 
+impl<K: Ord, V> Drop for IntoIter<K, V> {
+    // nullまで解放する．
+    fn drop(&mut self) {
+        drop(Chain {
+            head: self.node,
+            len: usize::MAX,
+        });
+    }
+}
+
+// remainingはnodeを含めて残っているentryの数．count，last，nthはspanで飛ぶ．
 pub struct Iter<'a, K: Ord + 'a, V: 'a> {
     pub(crate) node: MaybeNode<K, V>,
+    pub(crate) remaining: usize,
     pub(crate) marker: PhantomData<&'a ()>,
 }
 
+// std::slice::Iterと同じく，&Kと&Vを渡すだけなので，KとVがSyncなら他のthreadへ渡せる．
+unsafe impl<K: Ord + Sync, V: Sync> Send for Iter<'_, K, V> {}
+unsafe impl<K: Ord + Sync, V: Sync> Sync for Iter<'_, K, V> {}
+
 impl<'a, K: Ord + 'a, V: 'a> Iterator for Iter<'a, K, V> {
     type Item = (&'a K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(node) = self.node.take() else {
-            return None;
-        };
+        let node = self.node.take()?;
 
-        self.node = node.nexts()[0];
+        self.node = node.nexts()[0].next;
+        self.remaining -= 1;
 
         unsafe {
             let key = node.key_ptr().as_ref().unwrap();
@@ -55,22 +103,58 @@ impl<'a, K: Ord + 'a, V: 'a> Iterator for Iter<'a, K, V> {
             Some((key, val))
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+
+    fn last(mut self) -> Option<Self::Item> {
+        let n = self.remaining.checked_sub(1)?;
+        self.nth(n)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let node = self.node.take()?;
+        if n >= self.remaining {
+            self.node = MaybeNode::null();
+            self.remaining = 0;
+            return None;
+        }
+        self.node = jump(node, n).into();
+        self.remaining -= n;
+        self.next()
+    }
 }
 
+impl<'a, K: Ord + 'a, V: 'a> ExactSizeIterator for Iter<'a, K, V> {}
+
+// &'a mut Vと同じくVについて不変にする．共変だと，短い寿命の値を書き込めてしまう．
+/// ```compile_fail
+/// let mut list = skip_list_rs::SkipList::<i32, &'static str, _>::new(|| false);
+/// list.insert(1, "static").unwrap();
+/// let s = String::from("short");
+/// *list.iter_mut().next().unwrap().1 = s.as_str();
+/// drop(s);
+/// println!("{}", list.search(&1).unwrap());
+/// ```
 pub struct IterMut<'a, K: Ord, V> {
     pub(crate) node: MaybeNode<K, V>,
-    pub(crate) marker: PhantomData<&'a ()>,
+    pub(crate) remaining: usize,
+    pub(crate) marker: PhantomData<&'a mut (K, V)>,
 }
 
 impl<'a, K: Ord + 'a, V: 'a> Iterator for IterMut<'a, K, V> {
     type Item = (&'a K, &'a mut V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(node) = self.node.take() else {
-            return None;
-        };
+        let node = self.node.take()?;
 
-        self.node = node.nexts()[0];
+        self.node = node.nexts()[0].next;
+        self.remaining -= 1;
 
         unsafe {
             let key = node.key_ptr().as_ref().unwrap();
@@ -79,4 +163,176 @@ impl<'a, K: Ord + 'a, V: 'a> Iterator for IterMut<'a, K, V> {
             Some((key, val))
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+
+    fn last(mut self) -> Option<Self::Item> {
+        let n = self.remaining.checked_sub(1)?;
+        self.nth(n)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let node = self.node.take()?;
+        if n >= self.remaining {
+            self.node = MaybeNode::null();
+            self.remaining = 0;
+            return None;
+        }
+        self.node = jump(node, n).into();
+        self.remaining -= n;
+        self.next()
+    }
+}
+
+impl<'a, K: Ord + 'a, V: 'a> ExactSizeIterator for IterMut<'a, K, V> {}
+
+// levelの鎖だけを辿る．
+pub struct LevelIter<'a, K: Ord + 'a, V: 'a> {
+    pub(crate) node: MaybeNode<K, V>,
+    pub(crate) level: usize,
+    pub(crate) marker: PhantomData<&'a ()>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for LevelIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node.take()?;
+
+        self.node = node.nexts()[self.level].next;
+
+        Some((node.key(), node.value()))
+    }
+}
+
+pub struct Range<'a, K: Ord + 'a, V: 'a> {
+    pub(crate) node: MaybeNode<K, V>,
+    pub(crate) end: MaybeNode<K, V>,
+    pub(crate) head: &'a [Link<K, V>],
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.node == self.end {
+            return None;
+        }
+        let node = self.node.take()?;
+
+        self.node = node.nexts()[0].next;
+
+        Some((node.key(), node.value()))
+    }
+
+    // 両端の位置をheadから探して引く．
+    fn count(self) -> usize {
+        if self.node == self.end {
+            return 0;
+        }
+        position(self.head, self.end) - position(self.head, self.node)
+    }
+
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.node == self.end {
+            return None;
+        }
+        let node = self.node.take()?;
+        let len = position(self.head, self.end) - position(self.head, self.node);
+        if n >= len {
+            self.node = self.end;
+            return None;
+        }
+        self.node = jump(node, n).into();
+        self.next()
+    }
+}
+
+// step個ずつ飛ばして返す．jumpで進むので，1回の移動はstepに対してO(log step)で済む．
+pub struct StepRange<'a, K: Ord + 'a, V: 'a> {
+    pub(crate) node: MaybeNode<K, V>,
+    // nodeを含めて，範囲に残っているentryの数．
+    pub(crate) remaining: usize,
+    pub(crate) step: usize,
+    pub(crate) marker: PhantomData<&'a ()>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for StepRange<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.node.take()?;
+
+        if self.remaining > self.step {
+            self.remaining -= self.step;
+            self.node = jump(node, self.step).into();
+        } else {
+            self.remaining = 0;
+        }
+
+        Some((node.key(), node.value()))
+    }
+}
+
+// 射影が同じ連続したentryをまとめて返す．次のまとまりの先頭はseekで飛ばし探すので，
+// まとまりの中を辿らずに済む．
+pub struct GroupBy<'a, K: Ord + 'a, V: 'a, F> {
+    pub(crate) node: MaybeNode<K, V>,
+    pub(crate) head: &'a [Link<K, V>],
+    pub(crate) f: F,
+}
+
+impl<'a, K: Ord + 'a, V: 'a, P: PartialEq, F: FnMut(&K) -> P> Iterator for GroupBy<'a, K, V, F> {
+    type Item = (P, Range<'a, K, V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.node.take()?;
+        let group = (self.f)(node.key());
+        let end = seek(self.node, |k| (self.f)(k) == group);
+        let range = Range {
+            node: self.node,
+            end,
+            head: self.head,
+        };
+        self.node = end;
+        Some((group, range))
+    }
+}
+
+// 後ろ向きのlinkはないので，endの直前のnodeをheadから探す．1つごとにO(log n)かかる．
+impl<'a, K: Ord + 'a, V: 'a> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.node == self.end {
+            return None;
+        }
+        let end = self.end.take();
+        let mut forwards = self.head;
+        let mut last = None;
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                if end.is_some_and(|end| next.key() >= end.key()) {
+                    break;
+                }
+                last = Some(next);
+                forwards = next.nexts();
+            }
+        }
+
+        let node = last?;
+        self.end = node.into();
+        Some((node.key(), node.value()))
+    }
 }
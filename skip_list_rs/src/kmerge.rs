@@ -0,0 +1,149 @@
+use std::{cmp::Ordering, collections::BinaryHeap, marker::PhantomData};
+
+use crate::{node::Node, Generator, SkipList};
+
+// 同じkeyが複数のlistにあるときに返すもの．Firstは最初のlistの，Lastは最後のlistのentryだけを返す．
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ties {
+    All,
+    First,
+    Last,
+}
+
+struct Cursor<K: Ord, V> {
+    node: Node<K, V>,
+    source: usize,
+}
+
+impl<K: Ord, V> PartialEq for Cursor<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V> Eq for Cursor<K, V> {}
+
+impl<K: Ord, V> PartialOrd for Cursor<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// BinaryHeapは最大を取り出すので，keyとlistの順を逆にする．
+impl<K: Ord, V> Ord for Cursor<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .node
+            .key()
+            .cmp(self.node.key())
+            .then(other.source.cmp(&self.source))
+    }
+}
+
+// 各listの先頭をheapに置き，最小のものから返す．N個のlistでO(log N)ずつ進む．
+pub struct KMerge<'a, K: Ord, V> {
+    heap: BinaryHeap<Cursor<K, V>>,
+    ties: Ties,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> KMerge<'a, K, V> {
+    pub fn new<G: Generator<bool> + 'a>(
+        lists: impl IntoIterator<Item = &'a SkipList<K, V, G>>,
+        ties: Ties,
+    ) -> Self {
+        let heap = lists
+            .into_iter()
+            .enumerate()
+            .filter_map(|(source, list)| {
+                let node = list.nodes[0].next.take()?;
+                Some(Cursor { node, source })
+            })
+            .collect();
+        Self {
+            heap,
+            ties,
+            marker: PhantomData,
+        }
+    }
+
+    fn advance(&mut self, cursor: &Cursor<K, V>) {
+        if let Some(node) = cursor.node.nexts()[0].next.take() {
+            self.heap.push(Cursor {
+                node,
+                source: cursor.source,
+            });
+        }
+    }
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for KMerge<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let top = self.heap.pop()?;
+        self.advance(&top);
+        let mut chosen = top.node;
+
+        if self.ties != Ties::All {
+            while self
+                .heap
+                .peek()
+                .is_some_and(|c| c.node.key() == chosen.key())
+            {
+                let Some(tie) = self.heap.pop() else {
+                    unreachable!("the heap was just peeked");
+                };
+                self.advance(&tie);
+                if self.ties == Ties::Last {
+                    chosen = tie.node;
+                }
+            }
+        }
+        Some((chosen.key(), chosen.value()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{KMerge, SkipList, Ties};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[mockalloc::test]
+    fn merge() {
+        let mut rng = SmallRng::from_entropy();
+        let mut lists: Vec<_> = (0..5)
+            .map(|_| SkipList::new(Gen::standard(SmallRng::from_entropy())))
+            .collect();
+        let mut all = Vec::new();
+        let mut first = BTreeMap::new();
+        let mut last = BTreeMap::new();
+        for (source, list) in lists.iter_mut().enumerate() {
+            for _ in 0..100 {
+                let key = rng.gen_range(0..200);
+                if list.insert(key, source).is_ok() {
+                    all.push((key, source));
+                    first.entry(key).or_insert(source);
+                    last.insert(key, source);
+                }
+            }
+        }
+        all.sort();
+
+        let entries = |ties| {
+            KMerge::new(&lists, ties)
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(entries(Ties::All), all);
+        assert!(entries(Ties::First).into_iter().eq(first));
+        assert!(entries(Ties::Last).into_iter().eq(last));
+
+        let gen = || false;
+        let empty = [SkipList::<i32, usize, _>::new(gen), SkipList::new(gen)];
+        assert!(KMerge::new(&empty, Ties::All).next().is_none());
+    }
+}
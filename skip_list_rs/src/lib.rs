@@ -1,14 +1,191 @@
+#![cfg_attr(feature = "may-dangle", feature(dropck_eyepatch))]
+
+mod adjust;
+mod aggregate;
+mod bimap;
+mod bloom;
+mod bounded;
+mod budget;
+mod change;
+#[cfg(feature = "checked")]
+mod checked;
+#[cfg(feature = "collation")]
+mod collation;
+mod cursor;
+mod delay;
+mod diff;
+mod digest;
+mod entry;
+mod error;
+mod expiring;
+mod explain;
+mod fallible;
+mod fixed;
+mod frozen;
 mod generator;
+mod indexed;
+mod intern;
+mod intrusive;
 mod iter;
+mod kmerge;
+mod lookup;
+mod lww;
+mod mapped;
+mod merge;
+mod merkle;
+mod metrics;
+mod multimap;
+mod multiset;
 mod node;
-pub use generator::Generator;
-use iter::{IntoIter, Iter, IterMut};
-use node::{MaybeNode, Node};
-use std::{iter::repeat, marker::PhantomData};
+mod patch;
+mod prefix;
+#[cfg(kani)]
+mod proofs;
+mod queue;
+#[cfg(feature = "raw")]
+pub mod raw;
+mod record;
+mod rope;
+mod sample;
+mod scored;
+mod set;
+mod sharded;
+mod shared;
+mod snapshot;
+mod stable;
+mod stats;
+mod stream;
+#[cfg(any(test, feature = "structure"))]
+mod structure;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+mod vec;
+mod visit;
+mod wal;
+mod weighted;
+mod yielding;
+use adjust::Tuner;
+pub use adjust::Tuning;
+pub use aggregate::{AggregateSkipList, Max, Min, Monoid, Sum};
+pub use bimap::SkipBiMap;
+use bloom::Bloom;
+pub use bounded::{BoundedSkipList, EvictionPolicy, EvictionReason};
+pub use budget::BudgetedSkipList;
+use change::Watchers;
+pub use change::{Change, SubscriptionId};
+#[cfg(feature = "checked")]
+pub use checked::CheckedSkipList;
+#[cfg(feature = "collation")]
+pub use collation::CaseInsensitive;
+pub use cursor::{Cursor, CursorMut};
+pub use delay::SkipDelayQueue;
+pub use diff::{Diff, DiffIter};
+use digest::Digest;
+pub use entry::{EntryRef, OccupiedEntryRef, VacantEntryRef};
+pub use error::{
+    AllocError, CapacityError, CompareInsertError, InsertError, InvariantViolation, RemoveError,
+    TryInsertError,
+};
+pub use expiring::{Expired, ExpiringSkipList};
+pub use explain::{SearchPath, Step};
+pub use fixed::{FixedInsertError, FixedIter, FixedSkipList};
+pub use frozen::{FrozenIter, FrozenSkipList};
+pub use generator::{DynSkipList, Generator, XorShift};
+pub use indexed::SkipIndexedMap;
+pub use intern::{Interned, KeyPool};
+pub use intrusive::{Intrusive, IntrusiveIter, IntrusiveSkipList, Tower};
+use iter::{GroupBy, IntoIter, Iter, IterMut, LevelIter, Range, StepRange};
+pub use kmerge::{KMerge, Ties};
+use lookup::HashIndex;
+pub use lww::{LwwSkipMap, Stamp};
+pub use mapped::{MappedRange, MappedSkipList};
+pub use merge::{Difference, Intersection, Join, Union};
+pub use merkle::{DigestRange, MerkleSkipList, RangeDigest};
+pub use metrics::{Metrics, Operation};
+pub use multimap::{MultiIter, SkipMultiMap};
+pub use multiset::{MultiSetIter, SkipMultiSet};
+use node::{Chain, Link, MaybeNode, Node, Unlinked};
+pub use patch::Patch;
+pub use prefix::KeyPrefix;
+pub use queue::{QueueHandle, SkipPriorityQueue};
+pub use record::{replay, RecordingSkipList, Replayed, Tap};
+pub use rope::SkipRope;
+pub use scored::{ScoredIter, ScoredSet};
+pub use set::{SetIntoIter, SetIter, SetRange, SkipSet};
+pub use sharded::{
+    HashRouter, PrefixRouter, RangeRouter, Router, ShardWriter, ShardedIter, ShardedSkipList,
+};
+pub use shared::ArcSkipList;
+#[cfg(feature = "derive")]
+pub use skip_list_rs_derive::SkipKey;
+pub use snapshot::{Codec, Plain};
+pub use stable::{EntryId, StableSkipList};
+pub use stats::{MemoryUsage, Stats};
+use std::{
+    cmp::Ordering,
+    iter::repeat_n,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::{Bound, RangeBounds},
+    ptr,
+};
+pub use vec::{SkipVec, VecIntoIter, VecIter, VecIterMut};
+pub use visit::Visitor;
+pub use wal::WalSkipList;
+pub use weighted::{Weigher, WeightedSkipList};
+pub use yielding::ForEachYielding;
 pub struct SkipList<K: Ord, V, G: Generator<bool>> {
     gen: G,
     count: usize,
-    nodes: Vec<MaybeNode<K, V>>,
+    nodes: Vec<Link<K, V>>,
+    // 各levelの最後のnode(nullはhead)と，その後ろに続くnodeの数．
+    tails: Vec<Link<K, V>>,
+    metrics: Option<Box<dyn Metrics>>,
+    subscriber: Option<Box<Subscriber<K>>>,
+    watchers: Watchers<K>,
+    digest: Option<Digest<K, V>>,
+    bloom: Option<Bloom<K>>,
+    index: Option<HashIndex<K, V>>,
+    self_adjusting: bool,
+    tuner: Option<Tuner>,
+    // nodeの中のKとVを所有していることをdropckに示す．
+    owned: PhantomData<(K, V)>,
+}
+
+type Subscriber<K> = dyn FnMut(Change<'_, K>) + Send + Sync;
+
+struct Bookkeeping<'a, K: Ord, V> {
+    count: &'a mut usize,
+    tails: &'a mut [Link<K, V>],
+}
+
+// debug buildでは，逆向きにも比較して反対称性を確かめる．
+fn checked_cmp<K: Ord>(next: &K, pos: usize, key: &K) -> Ordering {
+    let ord = next.cmp(key);
+    if cfg!(debug_assertions) {
+        let rev = key.cmp(next);
+        if rev != ord.reverse() {
+            panic!(
+                "inconsistent Ord: the key at position {pos} compares {ord:?} to the given key, but the given key compares {rev:?} to it"
+            );
+        }
+    }
+    ord
+}
+
+// BTreeMap::rangeと同じく，逆さまの範囲を拒む．
+fn check_range<K: Ord>(range: &impl RangeBounds<K>) {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e))
+            if s > e =>
+        {
+            panic!("range start is greater than range end")
+        }
+        (Bound::Excluded(s), Bound::Excluded(e)) if s == e => {
+            panic!("range start and end are equal and excluded")
+        }
+        _ => {}
+    }
 }
 
 impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
@@ -16,341 +193,2213 @@ impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
         Self {
             gen,
             count: 0,
-            nodes: vec![MaybeNode::null()],
+            nodes: vec![Link::null()],
+            tails: vec![Link::null()],
+            metrics: None,
+            subscriber: None,
+            watchers: Watchers::new(),
+            digest: None,
+            bloom: None,
+            index: None,
+            self_adjusting: false,
+            tuner: None,
+            owned: PhantomData,
         }
     }
 
+    // 以後の変更をfに知らせる．search_mutやiter_mutでの書き換えは知らせないので，updateを使う．
+    pub fn subscribe(&mut self, f: impl FnMut(Change<'_, K>) + Send + Sync + 'static) {
+        self.subscriber = Some(Box::new(f));
+    }
+
+    pub fn unsubscribe(&mut self) {
+        self.subscriber = None;
+    }
+
+    pub fn set_metrics(&mut self, metrics: impl Metrics + 'static) {
+        self.metrics = Some(Box::new(metrics));
+    }
+
+    pub fn take_metrics(&mut self) -> Option<Box<dyn Metrics>> {
+        self.metrics.take()
+    }
+
     pub fn count(&self) -> usize {
         self.count
     }
 
-    pub fn insert(&mut self, key: K, value: V) -> Result<(), (K, V)> {
-        let len = self.nodes.len();
-        let level = len - 1;
-        let forwards = unsafe { std::slice::from_raw_parts_mut(self.nodes.as_mut_ptr(), len) };
-        let inserted = self.insert_impl(forwards, level, key, value)?;
+    // headのtowerの高さ．空のlistでも1．
+    pub fn height(&self) -> usize {
+        self.nodes.len()
+    }
 
-        if let Some(d) = inserted.level().checked_sub(len) {
-            self.nodes
-                .extend(repeat::<MaybeNode<K, V>>(inserted.into()).take(d));
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        // 最大のkeyより大きければ，appendと同じく探索せずに末尾へつなぐ．
+        let count = self.count;
+        let after = |last: Node<K, V>| checked_cmp(last.key(), count, &key) == Ordering::Less;
+        if self.tails[0].next.take().is_some_and(after) {
+            self.push_back_as(Operation::Insert, key, value);
+            return Ok(());
         }
-
-        Ok(())
+        self.insert_as(Operation::Insert, key, value)
     }
 
-    // levelごとに再帰を行う．
-    // 各levelで前方に進められるだけ進め，進められなくなればlevelを下げて再帰．
-    // 巻き上げにおいて，forwardsの該当levelを挿入された要素にする．ただし，挿入されたnodeのlevelを超えた場合は何もしない．
-    fn insert_impl(
+    // Generatorを使わずにtowerの高さを指定して挿入する．高さはGeneratorで選ばれうる範囲
+    // (1以上，挿入前のentryの数のbit長以下)に丸める．
+    pub fn insert_with_level(
         &mut self,
-        mut forwards: &mut [MaybeNode<K, V>],
-        level: usize,
         key: K,
         value: V,
-    ) -> Result<Node<K, V>, (K, V)> {
-        loop {
-            //前方に進める．
-            assert!(level < forwards.len());
+        level: usize,
+    ) -> Result<(), InsertError<K, V>> {
+        let level = level.clamp(1, self.level_limit().max(1));
+        let node = Node::new(key, value, level);
+        self.allocated(node);
+        let mut comparisons = 0;
+        let locate = |next: &K, pos, key: &K| {
+            comparisons += 1;
+            checked_cmp(next, pos, key)
+        };
+        let linked = self.link_by(locate, node);
+        self.report(Operation::Insert, comparisons);
+        let (node, pos) = linked.map_err(|(key, value)| InsertError::Occupied(key, value))?;
+        self.check_neighbours(node, pos);
+        self.debug_check(true);
+        self.hash_in(node);
+        self.notify(Change::Inserted(node.key()));
+        Ok(())
+    }
 
-            let Some(next) = forwards[level].take() else {
-                break;
-            };
+    fn insert_as(&mut self, op: Operation, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        let mut comparisons = 0;
+        let locate = |next: &K, pos, key: &K| {
+            comparisons += 1;
+            checked_cmp(next, pos, key)
+        };
+        let inserted = self.insert_by(locate, key, value);
+        self.report(op, comparisons);
+        let (node, pos) = inserted.map_err(|(key, value)| InsertError::Occupied(key, value))?;
+        self.check_neighbours(node, pos);
+        self.debug_check(true);
+        self.hash_in(node);
+        self.notify(Change::Inserted(node.key()));
+        Ok(())
+    }
 
-            if next.key() == &key {
-                return Err((key, value));
+    // 末尾への追加はtailsから直接つなぎ，探索をしない．末尾でなければinsertと同じ．
+    pub fn append(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        if let Some(last) = self.tails[0].next.take() {
+            if last.key() >= &key {
+                return self.insert_as(Operation::Append, key, value);
             }
+        }
+        self.push_back_as(Operation::Append, key, value);
+        Ok(())
+    }
 
-            if next.key() > &key {
-                break;
+    // 最後のkeyとの1回だけの比較で済んだものとして数える．
+    fn push_back_as(&mut self, op: Operation, key: K, value: V) {
+        self.report(op, self.count.min(1));
+        let node = self.alloc(key, value);
+        self.push_back(node);
+        self.debug_check(true);
+        self.hash_in(node);
+        self.notify(Change::Inserted(node.key()));
+    }
+
+    // 最後のnodeより大きいkeyのnodeを，tailsから直接末尾につなぐ．
+    fn push_back(&mut self, node: Node<K, V>) {
+        self.count += 1;
+        let pos = self.count;
+
+        for level in 0..node.level() {
+            let tail = Link {
+                next: node.into(),
+                span: 0,
+            };
+            if level == self.nodes.len() {
+                self.nodes.push(Link {
+                    next: node.into(),
+                    span: pos,
+                });
+                self.tails.push(tail);
+                continue;
             }
 
-            forwards = next.nexts_mut();
+            let link = Link {
+                next: node.into(),
+                span: self.tails[level].span + 1,
+            };
+            match self.tails[level].next.take() {
+                Some(n) => n.nexts_mut()[level] = link,
+                None => self.nodes[level] = link,
+            }
+            self.tails[level] = tail;
         }
+        for tail in &mut self.tails[node.level()..] {
+            tail.span += 1;
+        }
+    }
 
-        let node = if level == 0 {
-            let n = self.alloc(key, value);
-            self.count += 1;
-            n
-        } else {
-            self.insert_impl(forwards, level - 1, key, value)?
+    // 各nodeの高さをそのまま使い，先頭から順に末尾へつなぎ直すので，探索も再挿入もしない．
+    // content_hashはvalueの型が変わるので引き継がない．hash indexは新しいnodeで引き直す．
+    pub fn map_values<W>(self, mut f: impl FnMut(&K, V) -> W) -> SkipList<K, W, G> {
+        let mut this = ManuallyDrop::new(self);
+        // Dropを通さずにfieldを取り出す．nodeはrestが持ち，fが途中でpanicしても残りを解放する．
+        let (gen, metrics, subscriber, watchers, bloom, tuner) = unsafe {
+            (
+                ptr::read(&this.gen),
+                ptr::read(&this.metrics),
+                ptr::read(&this.subscriber),
+                ptr::read(&this.watchers),
+                ptr::read(&this.bloom),
+                ptr::read(&this.tuner),
+            )
         };
-
-        if level >= node.level() {
-            return Ok(node);
+        let index = this.index.as_ref().map(HashIndex::emptied);
+        let mut rest = IntoIter {
+            node: this.nodes[0].next,
+        };
+        unsafe {
+            ptr::drop_in_place(&mut this.nodes);
+            ptr::drop_in_place(&mut this.tails);
+            ptr::drop_in_place(&mut this.digest);
+            ptr::drop_in_place(&mut this.index);
         }
 
-        node.nexts_mut()[level] = forwards[level];
-        forwards[level] = node.into();
-
-        return Ok(node);
+        let mut list = SkipList::new(gen);
+        list.metrics = metrics;
+        list.subscriber = subscriber;
+        list.watchers = watchers;
+        list.bloom = bloom;
+        list.self_adjusting = this.self_adjusting;
+        list.tuner = tuner;
+        list.index = index;
+        while let Some(node) = rest.node.take() {
+            rest.node = node.nexts()[0].next;
+            let level = node.level();
+            let (key, value) = node.dispose();
+            let value = f(&key, value);
+            let node = Node::new(key, value, level);
+            list.allocated(node);
+            list.push_back(node);
+        }
+        list.rebuild_hash_index();
+        list.debug_check(true);
+        list
     }
 
-    pub fn search(&self, key: &K) -> Option<&V> {
+    // keyより小さいnodeをすべて取り除き，その数を返す．
+    pub fn truncate_before(&mut self, key: &K) -> usize {
         let mut forwards = self.nodes.as_slice();
+        let mut rank = 0;
+        let mut cuts = Vec::with_capacity(forwards.len());
+        let mut comparisons = 0;
 
         for level in (0..forwards.len()).rev() {
-            loop {
-                let Some(next) = forwards.get(level).and_then(|e| e.take()) else {
-                    break;
-                };
+            while let Some(next) = forwards[level].next.take() {
+                comparisons += 1;
                 if next.key() >= key {
                     break;
                 }
+                rank += forwards[level].span;
                 forwards = next.nexts();
             }
+            cuts.push((forwards[level], rank));
         }
 
-        let Some(node) = forwards.get(0).and_then(|e| e.take()) else {
-            return None;
-        };
+        let removed = rank;
+        self.report(Operation::Truncate, comparisons);
+        if removed == 0 {
+            return 0;
+        }
 
-        if node.key() == key {
-            Some(node.value())
-        } else {
-            None
+        let node = self.nodes[0].next;
+        self.count -= removed;
+        for (level, (link, rank)) in cuts.into_iter().rev().enumerate() {
+            self.nodes[level] = if link.next.is_null() {
+                self.tails[level] = Link {
+                    next: MaybeNode::null(),
+                    span: self.count,
+                };
+                Link::null()
+            } else {
+                Link {
+                    next: link.next,
+                    span: rank + link.span - removed,
+                }
+            };
         }
-    }
 
-    pub fn remove(&mut self, key: &K) -> Result<(K, V), ()> {
-        let len = self.nodes.len();
-        let level = len - 1;
-        let forwards = unsafe { std::slice::from_raw_parts_mut(self.nodes.as_mut_ptr(), len) };
-        let removed = self.remove_impl(forwards, level, key)?;
-        Ok(removed.dispose())
+        self.debug_check(false);
+        if self.notifies() || self.digest.is_some() || self.index.is_some() {
+            let mut n = node;
+            for _ in 0..removed {
+                let Some(removed) = n.take() else {
+                    unreachable!("removed nodes are linked at level 0");
+                };
+                self.hash_out(removed);
+                self.notify(Change::Removed(removed.key()));
+                n = removed.nexts()[0].next;
+            }
+        }
+        drop(Chain {
+            head: node,
+            len: removed,
+        });
+
+        removed
     }
 
-    fn remove_impl(
-        &mut self,
-        mut forwards: &mut [MaybeNode<K, V>],
-        level: usize,
-        key: &K,
-    ) -> Result<Node<K, V>, ()> {
-        loop {
-            //前方に進める．
-            assert!(level < forwards.len());
+    // 先頭からn個を残し，残りを新しいlistに移して返す．各levelで境界をまたぐlinkだけをつなぎ替えるので，
+    // nodeは動かさない．移ったentryはselfから取り除かれたものとして知らせる．
+    pub fn split_at_rank(&mut self, n: usize, gen: G) -> Self {
+        let rest = self.cut_at_rank(n, gen);
+        self.forget(rest.nodes[0].next);
+        rest
+    }
 
-            let Some(next) = forwards[level].take() else {
-                break;
-            };
+    // 位置がnより後ろのnodeを，つないだまま新しいlistへ移す．content_hashと購読者には知らせない．
+    pub(crate) fn cut_at_rank(&mut self, n: usize, gen: G) -> Self {
+        let mut rest = Self::new(gen);
+        if n >= self.count {
+            return rest;
+        }
 
-            if next.key() >= &key {
-                break;
+        // 各levelで，位置がn以下の最後のnodeとその位置．
+        let mut forwards = self.nodes.as_slice();
+        let mut node = MaybeNode::null();
+        let mut rank = 0;
+        let mut cuts = Vec::with_capacity(forwards.len());
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                if rank + forwards[level].span > n {
+                    break;
+                }
+                rank += forwards[level].span;
+                node = next.into();
+                forwards = next.nexts();
             }
-
-            forwards = next.nexts_mut();
+            cuts.push((node, rank));
         }
 
-        let removed = if level == 0 {
-            let Some(node) = forwards[level].take() else {
-                return Err(());
+        let moved = self.count - n;
+        rest.count = moved;
+        rest.nodes.clear();
+        rest.tails.clear();
+        for (level, (node, rank)) in cuts.into_iter().rev().enumerate() {
+            let link = match node.take() {
+                Some(node) => std::mem::replace(&mut node.nexts_mut()[level], Link::null()),
+                None => std::mem::replace(&mut self.nodes[level], Link::null()),
+            };
+            if link.next.is_null() {
+                rest.nodes.push(Link::null());
+                rest.tails.push(Link {
+                    next: MaybeNode::null(),
+                    span: moved,
+                });
+            } else {
+                rest.nodes.push(Link {
+                    next: link.next,
+                    span: rank + link.span - n,
+                });
+                rest.tails.push(self.tails[level]);
+            }
+            self.tails[level] = Link {
+                next: node,
+                span: n - rank,
             };
-            self.count -= 1;
-            node
-        } else {
-            self.remove_impl(forwards, level - 1, key)?
-        };
-
-        if level >= removed.level() {
-            return Ok(removed);
         }
+        self.count = n;
 
-        let next = &mut removed.nexts_mut()[level];
-        forwards[level] = *next;
-        *next = MaybeNode::null();
+        self.debug_check(false);
+        rest.debug_check(false);
+        rest
+    }
 
-        return Ok(removed);
+    // fromから末尾までのnodeを，取り除いたものとしてcontent_hashと購読者に知らせる．
+    pub(crate) fn forget(&mut self, from: MaybeNode<K, V>) {
+        if self.notifies() || self.digest.is_some() || self.index.is_some() {
+            let mut node = from;
+            while let Some(n) = node.take() {
+                self.hash_out(n);
+                self.notify(Change::Removed(n.key()));
+                node = n.nexts()[0].next;
+            }
+        }
     }
 
-    fn alloc(&mut self, key: K, value: V) -> Node<K, V> {
-        let level = {
-            let limit = (usize::BITS - self.count.leading_zeros()) as usize;
-            let mut size = 1;
+    // boundsの中のentryを新しいlistに移して返す．境界の前後の2か所でlinkをつなぎ替えるので，nodeは動かさない．
+    // rangeと同じく，逆さまの範囲ではpanicする．
+    pub fn extract_range(&mut self, bounds: impl RangeBounds<K>, gen: G) -> Self {
+        check_range(&bounds);
+        let before_start = self.cuts_by(|k| match bounds.start_bound() {
+            Bound::Included(s) => k < s,
+            Bound::Excluded(s) => k <= s,
+            Bound::Unbounded => false,
+        });
+        let before_end = self.cuts_by(|k| match bounds.end_bound() {
+            Bound::Included(e) => k <= e,
+            Bound::Excluded(e) => k < e,
+            Bound::Unbounded => true,
+        });
 
-            while size < limit && self.gen.gen() {
-                size += 1;
+        let mut extracted = Self::new(gen);
+        let (start, end) = (before_start[0].1, before_end[0].1);
+        let moved = end - start;
+        if moved == 0 {
+            return extracted;
+        }
+        let first = before_start[0]
+            .0
+            .take()
+            .map_or(self.nodes[0].next, |n| n.nexts()[0].next);
+        self.count -= moved;
+        extracted.count = moved;
+        extracted.nodes.clear();
+        extracted.tails.clear();
+
+        let heads = before_start.into_iter().zip(before_end);
+        for (level, ((from, from_rank), (last, last_rank))) in heads.enumerate() {
+            if from == last {
+                extracted.nodes.push(Link::null());
+                extracted.tails.push(Link {
+                    next: MaybeNode::null(),
+                    span: moved,
+                });
+                match from.take() {
+                    Some(node) if !node.nexts()[level].next.is_null() => {
+                        node.nexts_mut()[level].span -= moved
+                    }
+                    None if !self.nodes[level].next.is_null() => self.nodes[level].span -= moved,
+                    _ => self.tails[level].span -= moved,
+                }
+                continue;
             }
-            size
-        };
 
-        Node::new(key, value, level)
-    }
+            let Some(last) = last.take() else {
+                unreachable!("the end cut is after the start cut");
+            };
+            let after = std::mem::replace(&mut last.nexts_mut()[level], Link::null());
+            let from_link = match from.take() {
+                Some(node) => &mut node.nexts_mut()[level],
+                None => &mut self.nodes[level],
+            };
+            extracted.nodes.push(Link {
+                next: from_link.next,
+                span: from_rank + from_link.span - start,
+            });
+            extracted.tails.push(Link {
+                next: last.into(),
+                span: end - last_rank,
+            });
+            *from_link = if after.next.is_null() {
+                self.tails[level] = Link {
+                    next: from,
+                    span: self.count - from_rank,
+                };
+                Link::null()
+            } else {
+                Link {
+                    next: after.next,
+                    span: last_rank + after.span - moved - from_rank,
+                }
+            };
+        }
 
-    pub fn iter(&self) -> Iter<'_, K, V> {
-        Iter {
-            node: self.nodes[0],
-            marker: PhantomData,
+        self.debug_check(true);
+        extracted.debug_check(true);
+        if self.notifies() || self.digest.is_some() || self.index.is_some() {
+            let mut node = first;
+            while let Some(n) = node.take() {
+                self.hash_out(n);
+                self.notify(Change::Removed(n.key()));
+                node = n.nexts()[0].next;
+            }
         }
+        extracted
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
-        IterMut {
-            node: self.nodes[0],
-            marker: PhantomData,
+    // 各levelで，before(key)を満たす最後のnode(nullはhead)とその位置．levelの昇順に並ぶ．
+    fn cuts_by(&self, mut before: impl FnMut(&K) -> bool) -> Vec<(MaybeNode<K, V>, usize)> {
+        let mut forwards = self.nodes.as_slice();
+        let mut node = MaybeNode::null();
+        let mut rank = 0;
+        let mut cuts = vec![(node, rank); forwards.len()];
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                if !before(next.key()) {
+                    break;
+                }
+                rank += forwards[level].span;
+                node = next.into();
+                forwards = next.nexts();
+            }
+            cuts[level] = (node, rank);
         }
+        cuts
     }
 
-    pub fn into_iter(self) -> IntoIter<K, V> {
-        IntoIter::new(self)
+    // 確保に失敗したときはentryを返し，listは変更しない．
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<(), TryInsertError<K, V>> {
+        // 新しいlevelの分のheadとtailsを先に確保しておく．
+        let grow = self.level_limit().saturating_sub(self.nodes.len());
+        if self.nodes.try_reserve(grow).is_err() || self.tails.try_reserve(grow).is_err() {
+            return Err(TryInsertError::AllocError(key, value));
+        }
+
+        let level = self.gen_level();
+        let node = Node::try_new(key, value, level)
+            .map_err(|(key, value)| TryInsertError::AllocError(key, value))?;
+        self.allocated(node);
+        let mut comparisons = 0;
+        let locate = |next: &K, pos, key: &K| {
+            comparisons += 1;
+            checked_cmp(next, pos, key)
+        };
+        let linked = self.link_by(locate, node);
+        self.report(Operation::Insert, comparisons);
+        let (node, pos) = linked.map_err(|(key, value)| TryInsertError::Occupied(key, value))?;
+        self.check_neighbours(node, pos);
+        self.debug_check(true);
+        self.hash_in(node);
+        self.notify(Change::Inserted(node.key()));
+        Ok(())
     }
-}
 
-impl<K: Ord, V, R: Generator<bool>> Drop for SkipList<K, V, R> {
-    fn drop(&mut self) {
-        let nodes = &mut self.nodes;
-        loop {
-            let Some(next) = nodes[0].take() else {
-                break;
-            };
+    // locate(nextのkey, nextの位置, 挿入するkey)がLessを返す間前方に進め，Equalなら重複として失敗する．
+    // 位置はheadを0とした1始まりで，挿入されたnodeとその位置を返す．
+    pub(crate) fn insert_by(
+        &mut self,
+        locate: impl FnMut(&K, usize, &K) -> Ordering,
+        key: K,
+        value: V,
+    ) -> Result<(Node<K, V>, usize), (K, V)> {
+        let node = self.alloc(key, value);
+        self.link_by(locate, node)
+    }
 
-            nodes.clear();
-            nodes.extend_from_slice(next.nexts());
+    // 確保済みのnodeをつなぐ．重複して失敗したときはnodeを解放する．
+    fn link_by(
+        &mut self,
+        mut locate: impl FnMut(&K, usize, &K) -> Ordering,
+        inserted: Node<K, V>,
+    ) -> Result<(Node<K, V>, usize), (K, V)> {
+        let len = self.nodes.len();
+        let (forwards, mut book) = self.split_head();
+        let guard = Unlinked(inserted);
+        let result = Self::insert_impl(forwards, len - 1, 0, &mut locate, inserted, &mut book);
+        let inserted = guard.release();
+        let Ok(pos) = result else {
+            return Err(inserted.dispose());
+        };
 
-            next.dispose();
+        if let Some(d) = inserted.level().checked_sub(len) {
+            let link = Link {
+                next: inserted.into(),
+                span: pos,
+            };
+            let tail = Link {
+                next: inserted.into(),
+                span: self.count - pos,
+            };
+            self.nodes.extend(repeat_n(link, d));
+            self.tails.extend(repeat_n(tail, d));
         }
+        self.debug_check(false);
+
+        Ok((inserted, pos))
     }
-}
 
-impl<K: Ord, V, R: Generator<bool>> IntoIterator for SkipList<K, V, R> {
-    type Item = (K, V);
+    // levelごとに再帰を行う．
+    // 各levelで前方に進められるだけ進め，進められなくなればlevelを下げて再帰．
+    // 巻き上げにおいて，forwardsの該当levelを挿入された要素にする．ただし，挿入されたnodeのlevelを超えた場合はspanを伸ばすだけにする．
+    // locateは巻き上げより前にしか呼ばないので，locateがpanicしてもlinkは書き換わっていない．
+    fn insert_impl(
+        mut forwards: &mut [Link<K, V>],
+        level: usize,
+        mut rank: usize,
+        locate: &mut impl FnMut(&K, usize, &K) -> Ordering,
+        node: Node<K, V>,
+        book: &mut Bookkeeping<'_, K, V>,
+    ) -> Result<usize, ()> {
+        loop {
+            //前方に進める．
+            assert!(level < forwards.len());
 
-    type IntoIter = IntoIter<K, V>;
+            let link = forwards[level];
+            let Some(next) = link.next.take() else {
+                break;
+            };
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.into_iter()
-    }
-}
+            match locate(next.key(), rank + link.span, node.key()) {
+                Ordering::Less => {}
+                Ordering::Equal => return Err(()),
+                Ordering::Greater => break,
+            }
 
-#[cfg(test)]
-mod test {
-    use crate::node::Node;
-    use crate::{Generator, SkipList};
-    use mockalloc::Mockalloc;
-    use rand::distributions::Distribution;
-    use rand::distributions::Standard;
-    use rand::rngs::SmallRng;
-    use rand::SeedableRng;
-    use std::alloc::System;
-    use std::fmt::Debug;
-    use std::marker::PhantomData;
+            rank += link.span;
+            forwards = next.nexts_mut();
+        }
 
-    #[global_allocator]
-    static ALLOCATOR: Mockalloc<System> = Mockalloc(System);
+        let pos = if level == 0 {
+            *book.count += 1;
+            rank + 1
+        } else {
+            Self::insert_impl(&mut *forwards, level - 1, rank, locate, node, book)?
+        };
 
-    #[mockalloc::test]
-    fn test() {
-        use rand::seq::SliceRandom;
-        let mut rng = SmallRng::from_entropy();
-        let gen = Gen::standard(SmallRng::from_entropy());
-        let mut list = SkipList::new(gen);
+        let link = &mut forwards[level];
 
-        {
-            println!("\n----- insert -----\n");
-            let mut items: Vec<_> = (0..9).collect();
-            items.shuffle(&mut rng);
-            debug(&list);
-            for item in items.iter().copied() {
-                let result = list.insert(item, item);
-                println!("insert({}) = {:?}", item, result);
-                debug(&list);
+        // linkがnullなら，このlevelの最後のnodeの後ろに挿入している．
+        if level >= node.level() {
+            if link.next.is_null() {
+                book.tails[level].span += 1;
+            } else {
+                link.span += 1;
             }
+            return Ok(pos);
         }
 
-        {
-            println!("\n----- search -----\n");
-            let mut keys: Vec<_> = (0..10).collect();
-            keys.shuffle(&mut rng);
-            for key in keys.iter().copied() {
-                let result = list.search(&key);
+        if link.next.is_null() {
+            book.tails[level] = Link {
+                next: node.into(),
+                span: *book.count - pos,
+            };
+        }
 
-                println!("search({}) = {:?}", key, result);
-            }
+        let node_link = &mut node.nexts_mut()[level];
+        node_link.next = link.next;
+        node_link.span = if link.next.is_null() {
+            0
+        } else {
+            rank + link.span + 1 - pos
+        };
+        link.next = node.into();
+        link.span = pos - rank;
+
+        Ok(pos)
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        if !self.may_contain(key) {
+            self.report(Operation::Search, 0);
+            return None;
         }
+        if let Some(index) = &self.index {
+            self.report(Operation::Search, 0);
+            return index.get(key).map(|node| node.value());
+        }
+        let mut forwards = self.nodes.as_slice();
+        let mut comparisons = 0;
 
-        {
-            println!("\n----- remove -----\n");
-            let mut keys: Vec<_> = (0..10).collect();
-            keys.shuffle(&mut rng);
-            for key in keys.iter().copied() {
-                let result = list.remove(&key);
-                println!("remove({}) = {:?}", key, result);
-                debug(&list);
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards.get(level).and_then(|e| e.next.take()) {
+                comparisons += 1;
+                if next.key() >= key {
+                    break;
+                }
+                forwards = next.nexts();
             }
         }
+        self.report(Operation::Search, comparisons);
 
-        {
-            println!("\n----- search -----\n");
-            let mut keys: Vec<_> = (0..10).collect();
-            keys.shuffle(&mut rng);
-            for key in keys.iter().copied() {
-                let result = list.search(&key);
+        let node = forwards.first().and_then(|e| e.next.take())?;
 
-                println!("search({}) = {:?}", key, result);
-            }
+        if node.key() == key {
+            Some(node.value())
+        } else {
+            None
         }
     }
 
-    #[mockalloc::test]
-    fn iter() {
-        use rand::seq::SliceRandom;
-        let mut rng = SmallRng::from_entropy();
-        let gen = Gen::standard(SmallRng::from_entropy());
-        let mut list = SkipList::new(gen);
-
-        println!("\n----- insert -----\n");
-        let mut items: Vec<_> = (0..9).collect();
-        items.shuffle(&mut rng);
-        for item in items.iter().copied() {
-            let result = list.insert(item, item);
-            println!("insert({}) = {:?}", item, result);
+    pub fn search_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.may_contain(key) {
+            self.report(Operation::Search, 0);
+            return None;
+        }
+        let mut node = if let Some(index) = &self.index {
+            let node = index.get(key);
+            self.report(Operation::Search, 0);
+            node?
+        } else {
+            let mut comparisons = 0;
+            let node = self.find_first(|k| {
+                comparisons += 1;
+                k < key
+            });
+            self.report(Operation::Search, comparisons);
+            node.take().filter(|node| node.key() == key)?
+        };
 
-            println!("{:?}", list.iter().collect::<Vec<_>>());
+        if self.self_adjusting {
+            node = self.promote(node);
         }
+        self.mark_stale();
+        Some(node.value_mut())
     }
 
-    fn debug<K: Ord + Debug, V, R: Generator<bool>>(list: &SkipList<K, V, R>) {
-        use std::fmt::Write;
-        use std::iter::{repeat, repeat_with};
-        let mut forwards = list.nodes.as_slice();
-        let mut lines: Vec<_> = repeat_with(String::new).take(forwards.len()).collect();
-        let mut baseline = String::new();
-        let mut current_node: Option<Node<K, V>> = None;
-        let align = 10;
-        loop {
-            match current_node {
-                Some(n) => write!(baseline, "{:<align$}", format!("{:?}", n.key())).unwrap(),
-                None => baseline.extend(repeat(' ').take(align)),
-            }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.search(key).is_some()
+    }
 
-            for (no, node) in forwards.iter().enumerate() {
-                write!(
-                    lines[no],
-                    "{:<align$}",
-                    format!("{:?}", node.take().map(|e| e.key()))
-                )
-                .unwrap();
+    // valueを書き換え，購読者にUpdatedを知らせる．
+    pub fn update<R>(&mut self, key: &K, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        let node = self.find_first(|k| k < key).take()?;
+        if node.key() != key {
+            return None;
+        }
+        let result = self.rehash(node, f);
+        self.notify(Change::Updated(node.key()));
+        Some(result)
+    }
+
+    // 両方のkeyがあればvalueを入れ替えてtrueを返す．同じkeyなら何もしない．
+    pub fn swap_values(&mut self, a: &K, b: &K) -> bool {
+        let find = |key: &K| {
+            self.find_first(|k| k < key)
+                .take()
+                .filter(|n| n.key() == key)
+        };
+        let (Some(a), Some(b)) = (find(a), find(b)) else {
+            return false;
+        };
+        if a.key() == b.key() {
+            return true;
+        }
+        self.hash_out(a);
+        self.hash_out(b);
+        // 別々のnodeなので，2つの&mut Vは重ならない．
+        std::mem::swap(a.value_mut(), b.value_mut());
+        self.hash_in(a);
+        self.hash_in(b);
+        self.notify(Change::Updated(a.key()));
+        self.notify(Change::Updated(b.key()));
+        true
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        let node = self.nodes[0].next.take()?;
+        Some((node.key(), node.value()))
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        let node = self.tails[0].next.take()?;
+        Some((node.key(), node.value()))
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let node = self.nodes[0].next.take()?;
+        self.report(Operation::Remove, 0);
+        // 先頭nodeのtowerはすべてheadから直接指されている．
+        // 先頭nodeの位置は1なので，そのspanはそのままheadからのspanになる．
+        self.count -= 1;
+        for (level, link) in node.nexts().iter().enumerate() {
+            self.nodes[level] = *link;
+            if link.next.is_null() {
+                self.tails[level] = Link {
+                    next: MaybeNode::null(),
+                    span: self.count,
+                };
+            }
+        }
+        for level in node.level()..self.nodes.len() {
+            if self.nodes[level].next.is_null() {
+                self.tails[level].span -= 1;
+            } else {
+                self.nodes[level].span -= 1;
+            }
+        }
+        self.debug_check(false);
+        self.hash_out(node);
+        self.notify(Change::Removed(node.key()));
+        Some(node.dispose())
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let node = self.tails[0].next.take()?;
+        self.report(Operation::Remove, 0);
+        // 末尾nodeより高いlevelのtailsはnodeより前にある．そこから各levelでnodeの直前まで辿る．
+        // keyは比較せず，辿る距離は期待値で末尾nodeの高さ程度になる．
+        let height = node.level();
+        let (mut pred, mut rank) = match self.tails.get(height) {
+            Some(tail) => (tail.next, self.count - tail.span),
+            None => (MaybeNode::null(), 0),
+        };
+        self.count -= 1;
+        for tail in &mut self.tails[height..] {
+            tail.span -= 1;
+        }
+        for level in (0..height).rev() {
+            let link = loop {
+                let link = match pred.take() {
+                    Some(pred) => &mut pred.nexts_mut()[level],
+                    None => &mut self.nodes[level],
+                };
+                if link.next == node.into() {
+                    break link;
+                }
+                rank += link.span;
+                pred = link.next;
+            };
+            *link = Link::null();
+            self.tails[level] = Link {
+                next: pred,
+                span: self.count - rank,
+            };
+        }
+        self.debug_check(false);
+        self.hash_out(node);
+        self.notify(Change::Removed(node.key()));
+        Some(node.dispose())
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+        check_range(&range);
+
+        let start = |k: &K| match range.start_bound() {
+            Bound::Included(s) => k < s,
+            Bound::Excluded(s) => k <= s,
+            Bound::Unbounded => false,
+        };
+        let end = |k: &K| match range.end_bound() {
+            Bound::Included(e) => k <= e,
+            Bound::Excluded(e) => k < e,
+            Bound::Unbounded => true,
+        };
+
+        self.range_by(start, end)
+    }
+
+    // rangeの中の先頭のentryから，step個ごとのentryを返す．stepが0ならpanicする．
+    pub fn range_step<R: RangeBounds<K>>(&self, range: R, step: usize) -> StepRange<'_, K, V> {
+        assert!(step != 0, "step must be non-zero");
+        check_range(&range);
+        let start = self.rank_by(|k| match range.start_bound() {
+            Bound::Included(s) => k < s,
+            Bound::Excluded(s) => k <= s,
+            Bound::Unbounded => false,
+        });
+        let end = self.rank_by(|k| match range.end_bound() {
+            Bound::Included(e) => k <= e,
+            Bound::Excluded(e) => k < e,
+            Bound::Unbounded => true,
+        });
+
+        let remaining = end.saturating_sub(start);
+        let node = match remaining {
+            0 => None,
+            _ => self.node_at(start + 1),
+        };
+        StepRange {
+            node: node.map_or(MaybeNode::null(), Into::into),
+            remaining,
+            step,
+            marker: PhantomData,
+        }
+    }
+
+    // 先頭から数えてoffset番目(0始まり)からlimit個を返す．両端はspanを辿ってO(log n)で求める．
+    pub fn page(&self, offset: usize, limit: usize) -> Range<'_, K, V> {
+        let at = |pos: usize| match self.node_at(pos) {
+            Some(node) => node.into(),
+            None => MaybeNode::null(),
+        };
+        let start = at(offset.saturating_add(1));
+        let end = at(offset.saturating_add(limit).saturating_add(1));
+        self.range_between(start, end)
+    }
+
+    // ..k1, k1..k2, ..., k(n-1)..の各範囲がほぼ同じ数になるようなn - 1個のkeyを返す．
+    // 要素がn個に満たないときは，空の範囲ができないよう少なく返す．
+    pub fn split_points(&self, n: usize) -> Vec<&K> {
+        let mut points: Vec<&K> = Vec::with_capacity(n.saturating_sub(1));
+        for i in 1..n {
+            // 先頭のkeyで区切ると最初の範囲が空になる．
+            let pos = (i as u128 * self.count as u128 / n as u128) as usize + 1;
+            let Some(node) = self.node_at(pos).filter(|_| pos > 1) else {
+                continue;
+            };
+            if points.last().is_some_and(|last| *last == node.key()) {
+                continue;
+            }
+            points.push(node.key());
+        }
+        points
+    }
+
+    // predを満たす最初のentry．先頭から順に調べる．
+    pub fn find(&self, mut pred: impl FnMut(&K, &V) -> bool) -> Option<(&K, &V)> {
+        self.iter().find(|(k, v)| pred(k, v))
+    }
+
+    // predを満たす最初のentryの位置(0始まり)．pageのoffsetにそのまま渡せる．
+    pub fn position(&self, mut pred: impl FnMut(&K, &V) -> bool) -> Option<usize> {
+        self.iter().position(|(k, v)| pred(k, v))
+    }
+
+    // keyより後ろのentryのうちn番目(0始まり)．keyはlistになくてよい．spanを辿るので，nによらず2回の探索で済む．
+    pub fn nth_after(&self, key: &K, n: usize) -> Option<(&K, &V)> {
+        let pos = self.rank_by(|k| k <= key).checked_add(n)?.checked_add(1)?;
+        let node = self.node_at(pos)?;
+        Some((node.key(), node.value()))
+    }
+
+    // keyより前のentryのうち，後ろから数えてn番目(0始まり)．
+    pub fn nth_before(&self, key: &K, n: usize) -> Option<(&K, &V)> {
+        let pos = self.rank_by(|k| k < key).checked_sub(n)?;
+        let node = self.node_at(pos)?;
+        Some((node.key(), node.value()))
+    }
+
+    // fは探すものに対するkeyの順序を返す．slice::binary_search_byと同じく，keyの順に対して単調であること．
+    // fがLessを返さない最初のentry．
+    pub fn lower_bound_by(&self, mut f: impl FnMut(&K) -> Ordering) -> Option<(&K, &V)> {
+        let node = self.find_first(|k| f(k) == Ordering::Less).take()?;
+        Some((node.key(), node.value()))
+    }
+
+    // fがGreaterを返す最初のentry．
+    pub fn upper_bound_by(&self, mut f: impl FnMut(&K) -> Ordering) -> Option<(&K, &V)> {
+        let node = self.find_first(|k| f(k) != Ordering::Greater).take()?;
+        Some((node.key(), node.value()))
+    }
+
+    // before(key)を満たすentryの数．
+    fn rank_by(&self, mut before: impl FnMut(&K) -> bool) -> usize {
+        let mut forwards = self.nodes.as_slice();
+        let mut rank = 0;
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                if !before(next.key()) {
+                    break;
+                }
+                rank += forwards[level].span;
+                forwards = next.nexts();
+            }
+        }
+        rank
+    }
+
+    // start(key)を満たさない最初のnodeから，end(key)を満たさない最初のnodeの手前までを返す．
+    // 始点が終点より後ろになる場合は空になる．
+    pub(crate) fn range_by(
+        &self,
+        start: impl FnMut(&K) -> bool,
+        end: impl FnMut(&K) -> bool,
+    ) -> Range<'_, K, V> {
+        let node = self.find_first(start);
+        let end = self.find_first(end);
+        self.range_between(node, end)
+    }
+
+    pub(crate) fn range_between(
+        &self,
+        mut node: MaybeNode<K, V>,
+        end: MaybeNode<K, V>,
+    ) -> Range<'_, K, V> {
+        if let (Some(n), Some(e)) = (node.take(), end.take()) {
+            if n.key() > e.key() {
+                node = end;
+            }
+        }
+
+        Range {
+            node,
+            end,
+            head: &self.nodes,
+        }
+    }
+
+    // keyの位置(1始まり)を返す．
+    pub(crate) fn pos_of(&self, key: &K) -> Option<usize> {
+        let mut forwards = self.nodes.as_slice();
+        let mut rank = 0;
+
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                if next.key() >= key {
+                    break;
+                }
+                rank += forwards[level].span;
+                forwards = next.nexts();
             }
+        }
+
+        let node = forwards[0].next.take()?;
+        (node.key() == key).then_some(rank + 1)
+    }
 
-            for ln in forwards.len()..lines.len() {
-                lines[ln].extend(repeat(' ').take(align));
+    // before(key)を満たす最後のnodeを返す．beforeはkeyの順序に対して単調でなければならない．
+    fn find_last(&self, mut before: impl FnMut(&K) -> bool) -> Option<Node<K, V>> {
+        let mut forwards = self.nodes.as_slice();
+        let mut last = None;
+
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                if !before(next.key()) {
+                    break;
+                }
+                last = Some(next);
+                forwards = next.nexts();
             }
+        }
+
+        last
+    }
+
+    // before(key)を満たさない最初のnodeを返す．
+    fn find_first(&self, before: impl FnMut(&K) -> bool) -> MaybeNode<K, V> {
+        match self.find_last(before) {
+            Some(node) => node.nexts()[0].next,
+            None => self.nodes[0].next,
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let mut comparisons = 0;
+        let removed = self.remove_by(|next, pos| {
+            comparisons += 1;
+            checked_cmp(next, pos, key)
+        });
+        self.report(Operation::Remove, comparisons);
+        let removed = removed.map_err(|()| RemoveError::NotFound)?;
+        self.hash_out(removed);
+        self.notify(Change::Removed(removed.key()));
+        Ok(removed.dispose())
+    }
+
+    // headのtowerと，insert_impl/remove_implが書き換えるそれ以外の状態を別々に借用する．
+    fn split_head(&mut self) -> (&mut [Link<K, V>], Bookkeeping<'_, K, V>) {
+        let book = Bookkeeping {
+            count: &mut self.count,
+            tails: &mut self.tails,
+        };
+        (&mut self.nodes, book)
+    }
+
+    // locate(nextのkey, nextの位置)がLessを返す間前方に進め，Equalとなるnodeを取り除く．
+    pub(crate) fn remove_by(
+        &mut self,
+        mut locate: impl FnMut(&K, usize) -> Ordering,
+    ) -> Result<Node<K, V>, ()> {
+        let len = self.nodes.len();
+        let (forwards, mut book) = self.split_head();
+        let removed = Self::remove_impl(
+            forwards,
+            len - 1,
+            0,
+            MaybeNode::null(),
+            &mut locate,
+            &mut book,
+        )?;
+        self.debug_check(false);
+        Ok(removed)
+    }
+
+    // predはforwardsを持つnode(nullはhead)．
+    // insert_implと同じく，locateは巻き上げより前にしか呼ばない．
+    fn remove_impl(
+        mut forwards: &mut [Link<K, V>],
+        level: usize,
+        mut rank: usize,
+        mut pred: MaybeNode<K, V>,
+        locate: &mut impl FnMut(&K, usize) -> Ordering,
+        book: &mut Bookkeeping<'_, K, V>,
+    ) -> Result<Node<K, V>, ()> {
+        loop {
+            //前方に進める．
+            assert!(level < forwards.len());
+
+            let link = forwards[level];
+            let Some(next) = link.next.take() else {
+                break;
+            };
 
-            let Some(next) = forwards[0].take() else {
+            if locate(next.key(), rank + link.span) != Ordering::Less {
                 break;
+            }
+
+            rank += link.span;
+            pred = next.into();
+            forwards = next.nexts_mut();
+        }
+
+        let removed = if level == 0 {
+            let link = forwards[level];
+            let Some(node) = link.next.take() else {
+                return Err(());
             };
-            current_node = Some(next);
-            forwards = next.nexts();
+            if locate(node.key(), rank + link.span) != Ordering::Equal {
+                return Err(());
+            }
+            *book.count -= 1;
+            node
+        } else {
+            Self::remove_impl(&mut *forwards, level - 1, rank, pred, locate, book)?
+        };
+
+        let link = &mut forwards[level];
+
+        if level >= removed.level() {
+            if link.next.is_null() {
+                book.tails[level].span -= 1;
+            } else {
+                link.span -= 1;
+            }
+            return Ok(removed);
+        }
+
+        let removed_link = &mut removed.nexts_mut()[level];
+        if removed_link.next.is_null() {
+            book.tails[level] = Link {
+                next: pred,
+                span: *book.count - rank,
+            };
+        }
+        link.next = removed_link.next;
+        link.span = if link.next.is_null() {
+            0
+        } else {
+            link.span + removed_link.span - 1
+        };
+        *removed_link = Link::null();
+
+        Ok(removed)
+    }
+
+    // 位置(1始まり)がposのnodeを返す．
+    pub(crate) fn node_at(&self, pos: usize) -> Option<Node<K, V>> {
+        let mut forwards = self.nodes.as_slice();
+        let mut rank = 0;
+
+        for level in (0..forwards.len()).rev() {
+            while let Some(next) = forwards[level].next.take() {
+                let span = forwards[level].span;
+                if rank + span > pos {
+                    break;
+                }
+                rank += span;
+                if rank == pos {
+                    return Some(next);
+                }
+                forwards = next.nexts();
+            }
+        }
+
+        None
+    }
+
+    fn alloc(&mut self, key: K, value: V) -> Node<K, V> {
+        let level = self.gen_level();
+        let node = Node::new(key, value, level);
+        self.allocated(node);
+        node
+    }
+
+    fn notify(&mut self, change: Change<'_, K>) {
+        if let Some(subscriber) = &mut self.subscriber {
+            subscriber(change);
+        }
+        if !self.watchers.is_empty() {
+            self.watchers.notify(change);
+        }
+    }
+
+    fn report(&self, op: Operation, comparisons: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.operation(op, comparisons);
+        }
+        if let Some(tuner) = &self.tuner {
+            tuner.observe(op, comparisons, self.count);
+        }
+    }
+
+    fn allocated(&self, node: Node<K, V>) {
+        if let Some(metrics) = &self.metrics {
+            metrics.allocated(node.allocated_size());
+        }
+    }
+
+    fn level_limit(&self) -> usize {
+        (usize::BITS - self.count.leading_zeros()) as usize
+    }
+
+    fn gen_level(&mut self) -> usize {
+        let (stride, limit) = self.promotion();
+        let mut size = 1;
+
+        while size < limit && (0..stride).all(|_| self.gen.gen()) {
+            size += 1;
+        }
+        size
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            node: self.nodes[0].next,
+            remaining: self.count,
+            marker: PhantomData,
+        }
+    }
+
+    // levelにtowerが届いているnodeだけを返す．levelがheight以上なら何も返さない．
+    pub fn level_iter(&self, level: usize) -> LevelIter<'_, K, V> {
+        let node = match self.nodes.get(level) {
+            Some(link) => link.next,
+            None => MaybeNode::null(),
+        };
+        LevelIter {
+            node,
+            level,
+            marker: PhantomData,
+        }
+    }
+
+    // fの値が同じ連続したentryを，その値とrangeの組にまとめる．fはkeyの順に対して単調であること．
+    pub fn group_by<P: PartialEq, F: FnMut(&K) -> P>(&self, f: F) -> GroupBy<'_, K, V, F> {
+        GroupBy {
+            node: self.nodes[0].next,
+            head: &self.nodes,
+            f,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.mark_stale();
+        IterMut {
+            node: self.nodes[0].next,
+            remaining: self.count,
+            marker: PhantomData,
+        }
+    }
+
+    // debug buildでは，挿入したnodeの前後のkeyが推移律に従っているか確かめる．
+    // 前 < 挿入したkey < 後 と比較されたので，前 < 後 でなければOrdが壊れている．
+    fn check_neighbours(&self, node: Node<K, V>, pos: usize) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let (Some(prev), Some(next)) = (self.node_at(pos - 1), node.nexts()[0].next.take()) else {
+            return;
+        };
+        if prev.key().cmp(next.key()) != Ordering::Less {
+            panic!(
+                "inconsistent Ord: the keys at positions {} and {} are not ordered, though the key inserted between them compares between both",
+                pos - 1,
+                pos + 1
+            );
+        }
+    }
+
+    // debug-invariants featureのときだけ，変更のたびに全体を検査する．
+    // insert_byやremove_byはkeyで並べないlistからも使われるので，そこでは順序を見ない．
+    fn debug_check(&self, ordered: bool) {
+        if !cfg!(feature = "debug-invariants") {
+            return;
+        }
+        let result = match ordered {
+            true => self.check_invariants(),
+            false => self.check_structure(),
+        };
+        if let Err(violation) = result {
+            panic!("skip list invariant violated: {violation:?}");
+        }
+    }
+
+    // 構造を全て辿って検査する．O(n log n)なので，テストやデバッグ向け．
+    // levelごとに，その鎖をlevel 0の鎖と並べて辿り，部分列になっているかとspanを確かめる．
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut prev: Option<&K> = None;
+        for (idx, (key, _)) in self.iter().enumerate() {
+            if prev.is_some_and(|prev| prev >= key) {
+                return Err(InvariantViolation::Unordered { pos: idx + 1 });
+            }
+            prev = Some(key);
+        }
+        self.check_structure()
+    }
+
+    fn check_structure(&self) -> Result<(), InvariantViolation> {
+        if self.tails.len() != self.nodes.len() {
+            return Err(InvariantViolation::Tail { level: 0 });
+        }
+
+        let mut count = 0;
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            count += 1;
+            if n.level() == 0 || n.level() > self.nodes.len() {
+                return Err(InvariantViolation::Tower {
+                    pos: count,
+                    level: 0,
+                });
+            }
+            node = n.nexts()[0].next;
         }
+        if count != self.count {
+            return Err(InvariantViolation::Count {
+                expected: self.count,
+                actual: count,
+            });
+        }
+
+        for level in 0..self.nodes.len() {
+            let mut base = self.nodes[0].next;
+            let mut base_pos = 1;
+            let mut from = MaybeNode::null();
+            let mut from_pos = 0;
+            let mut link = self.nodes[level];
+
+            while let Some(next) = link.next.take() {
+                while base != link.next {
+                    let Some(b) = base.take() else {
+                        return Err(InvariantViolation::NotSubsequence { level });
+                    };
+                    base = b.nexts()[0].next;
+                    base_pos += 1;
+                }
+                if next.level() <= level {
+                    return Err(InvariantViolation::Tower {
+                        pos: base_pos,
+                        level,
+                    });
+                }
+                if link.span != base_pos - from_pos {
+                    return Err(InvariantViolation::Span {
+                        pos: from_pos,
+                        level,
+                    });
+                }
+                from = link.next;
+                from_pos = base_pos;
+                link = next.nexts()[level];
+            }
 
-        println!("┌{:─<x$}┐", "", x = baseline.len());
-        for line in lines.iter().rev() {
-            println!("│{}│", line);
+            if link.span != 0 {
+                return Err(InvariantViolation::Span {
+                    pos: from_pos,
+                    level,
+                });
+            }
+            let tail = self.tails[level];
+            if tail.next != from || tail.span != count - from_pos {
+                return Err(InvariantViolation::Tail { level });
+            }
         }
-        println!("├{:─<x$}┤", "", x = baseline.len());
-        println!("│{}│", baseline);
-        println!("└{:─<x$}┘", "", x = baseline.len());
+
+        Ok(())
+    }
+}
+
+// nodeはlistが排他的に所有しているので，中身に応じてSend/Syncにできる．
+unsafe impl<K: Ord + Send, V: Send, G: Generator<bool> + Send> Send for SkipList<K, V, G> {}
+unsafe impl<K: Ord + Sync, V: Sync, G: Generator<bool> + Sync> Sync for SkipList<K, V, G> {}
+
+#[cfg(not(feature = "may-dangle"))]
+impl<K: Ord, V, R: Generator<bool>> Drop for SkipList<K, V, R> {
+    fn drop(&mut self) {
+        drop(Chain {
+            head: self.nodes[0].next,
+            len: self.count,
+        });
+    }
+}
+
+// dropではKとVを捨てるだけで読まないので，BTreeMapと同じく，KやVの借用がlistより先に切れてもよい．
+#[cfg(feature = "may-dangle")]
+unsafe impl<#[may_dangle] K: Ord, #[may_dangle] V, R: Generator<bool>> Drop for SkipList<K, V, R> {
+    fn drop(&mut self) {
+        drop(Chain {
+            head: self.nodes[0].next,
+            len: self.count,
+        });
+    }
+}
+
+impl<K: Ord, V, R: Generator<bool>> IntoIterator for SkipList<K, V, R> {
+    type Item = (K, V);
+
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Generator, InsertError, InvariantViolation, RemoveError, SkipList, TryInsertError,
+    };
+    use mockalloc::Mockalloc;
+    use rand::distributions::Distribution;
+    use rand::distributions::Standard;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::alloc::System;
+    use std::fmt::Debug;
+    use std::marker::PhantomData;
+
+    #[global_allocator]
+    static ALLOCATOR: Mockalloc<System> = Mockalloc(System);
+
+    #[mockalloc::test]
+    fn test() {
+        use rand::seq::SliceRandom;
+        let mut rng = SmallRng::from_entropy();
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+
+        {
+            let mut items: Vec<_> = (0..9).collect();
+            items.shuffle(&mut rng);
+            for item in items {
+                let result = list.insert(item, item);
+                assert_eq!(result, Ok(()), "insert({})\n{}", item, debug(&list));
+                check_spans(&list);
+            }
+            let result = list.insert(4, 4);
+            assert_eq!(
+                result,
+                Err(InsertError::Occupied(4, 4)),
+                "insert(4)\n{}",
+                debug(&list)
+            );
+        }
+
+        {
+            let mut keys: Vec<_> = (0..10).collect();
+            keys.shuffle(&mut rng);
+            for key in keys {
+                let result = list.search(&key);
+                let expected = if key < 9 { Some(&key) } else { None };
+                assert_eq!(result, expected, "search({})\n{}", key, debug(&list));
+            }
+        }
+
+        {
+            let mut keys: Vec<_> = (0..10).collect();
+            keys.shuffle(&mut rng);
+            for key in keys {
+                let result = list.remove(&key);
+                let expected = if key < 9 {
+                    Ok((key, key))
+                } else {
+                    Err(RemoveError::NotFound)
+                };
+                assert_eq!(result, expected, "remove({})\n{}", key, debug(&list));
+                check_spans(&list);
+            }
+        }
+
+        {
+            let mut keys: Vec<_> = (0..10).collect();
+            keys.shuffle(&mut rng);
+            for key in keys {
+                let result = list.search(&key);
+                assert_eq!(result, None, "search({})\n{}", key, debug(&list));
+            }
+        }
+
+        assert_eq!(list.count(), 0);
+    }
+
+    #[mockalloc::test]
+    fn remove_missing() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+
+        for item in [0, 2, 4] {
+            list.insert(item, item).unwrap();
+        }
+
+        assert_eq!(list.remove(&1), Err(RemoveError::NotFound));
+        assert_eq!(list.remove(&3), Err(RemoveError::NotFound));
+        assert_eq!(list.count(), 3);
+        assert_eq!(
+            list.iter().collect::<Vec<_>>(),
+            [(&0, &0), (&2, &2), (&4, &4)]
+        );
+    }
+
+    #[mockalloc::test]
+    fn iter() {
+        use rand::seq::SliceRandom;
+        let mut rng = SmallRng::from_entropy();
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+
+        let mut items: Vec<_> = (0..9).collect();
+        items.shuffle(&mut rng);
+        let mut inserted = Vec::new();
+        for item in items {
+            list.insert(item, item).unwrap();
+            inserted.push(item);
+            inserted.sort();
+
+            let expected: Vec<_> = inserted.iter().map(|e| (e, e)).collect();
+            assert_eq!(
+                list.iter().collect::<Vec<_>>(),
+                expected,
+                "{}",
+                debug(&list)
+            );
+        }
+    }
+
+    #[mockalloc::test]
+    fn try_insert() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+
+        for item in [3, 1, 2] {
+            assert_eq!(list.try_insert(item, item), Ok(()));
+            check_spans(&list);
+        }
+        assert_eq!(list.try_insert(2, 0), Err(TryInsertError::Occupied(2, 0)));
+        assert!(list.iter().map(|(k, _)| *k).eq([1, 2, 3]));
+    }
+
+    #[mockalloc::test]
+    fn errors() {
+        fn insert_twice<G: Generator<bool>>(
+            list: &mut SkipList<i32, i32, G>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            list.insert(1, 1)?;
+            list.insert(1, 2)?;
+            Ok(())
+        }
+
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        let err = insert_twice(&mut list).unwrap_err();
+        assert_eq!(err.to_string(), "the key is already present");
+        assert_eq!(list.insert(1, 3).unwrap_err().into_inner(), (1, 3));
+        assert_eq!(
+            list.remove(&2).unwrap_err().to_string(),
+            "the key is not present"
+        );
+    }
+
+    #[mockalloc::test]
+    fn zero_sized() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+
+        assert_eq!(list.insert((), ()), Ok(()));
+        assert_eq!(list.insert((), ()), Err(InsertError::Occupied((), ())));
+        assert_eq!(list.search(&()), Some(&()));
+        assert_eq!(list.first(), Some((&(), &())));
+        check_spans(&list);
+        assert_eq!(list.remove(&()), Ok(((), ())));
+        assert_eq!(list.remove(&()), Err(RemoveError::NotFound));
+        assert_eq!(list.append((), ()), Ok(()));
+        assert_eq!(list.into_iter().count(), 1);
+    }
+
+    thread_local! {
+        static LIVE: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+        static ARMED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+        static FRAGILE: std::cell::Cell<Option<i32>> = const { std::cell::Cell::new(None) };
+    }
+
+    // 生きている数を数え，ARMEDの間は比較でpanicする．
+    // FRAGILEと同じ値のBombは，一度だけdropでpanicする．
+    #[derive(Debug)]
+    struct Bomb(i32);
+
+    impl Bomb {
+        fn new(key: i32) -> Self {
+            LIVE.set(LIVE.get() + 1);
+            Bomb(key)
+        }
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            LIVE.set(LIVE.get() - 1);
+            if FRAGILE.get() == Some(self.0) {
+                FRAGILE.set(None);
+                panic!("fragile");
+            }
+        }
+    }
+
+    impl PartialEq for Bomb {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other).is_eq()
+        }
+    }
+
+    impl Eq for Bomb {}
+
+    impl PartialOrd for Bomb {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Bomb {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            assert!(!ARMED.get(), "armed");
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn panic_safety() {
+        use std::cell::Cell;
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        let gen_armed = Cell::new(false);
+        let gen = || {
+            assert!(!gen_armed.get(), "armed");
+            rand::random::<bool>()
+        };
+        let mut list = SkipList::new(gen);
+        for key in 0..20 {
+            list.insert(Bomb::new(key), Bomb::new(key)).unwrap();
+        }
+        let probe = Bomb::new(5);
+
+        // 比較でのpanic．
+        ARMED.set(true);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            list.insert(Bomb::new(100), Bomb::new(100))
+        }));
+        assert!(result.is_err());
+        let result = catch_unwind(AssertUnwindSafe(|| list.remove(&probe)));
+        assert!(result.is_err());
+        ARMED.set(false);
+
+        // Generatorでのpanic．
+        gen_armed.set(true);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            list.insert(Bomb::new(100), Bomb::new(100))
+        }));
+        assert!(result.is_err());
+        gen_armed.set(false);
+
+        check_spans(&list);
+        assert_eq!(list.count(), 20);
+        assert!(list.iter().map(|(k, _)| k.0).eq(0..20));
+        assert_eq!(LIVE.get(), 41);
+
+        drop(list);
+        drop(probe);
+        assert_eq!(LIVE.get(), 0);
+    }
+
+    #[test]
+    fn drop_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        let make = || {
+            let mut list = SkipList::new(rand::random::<bool>);
+            for key in 0..20 {
+                list.insert(Bomb::new(key), Bomb::new(key)).unwrap();
+            }
+            FRAGILE.set(Some(5));
+            list
+        };
+
+        let list = make();
+        assert!(catch_unwind(AssertUnwindSafe(|| drop(list))).is_err());
+        assert_eq!(LIVE.get(), 0);
+
+        let mut iter = make().into_iter();
+        iter.next();
+        assert!(catch_unwind(AssertUnwindSafe(|| drop(iter))).is_err());
+        assert_eq!(LIVE.get(), 0);
+
+        let mut list = make();
+        let probe = Bomb::new(10);
+        let result = catch_unwind(AssertUnwindSafe(|| list.truncate_before(&probe)));
+        assert!(result.is_err());
+        check_spans(&list);
+        assert!(list.iter().map(|(k, _)| k.0).eq(10..20));
+        assert_eq!(LIVE.get(), 21);
+
+        drop(list);
+        drop(probe);
+        assert_eq!(LIVE.get(), 0);
+    }
+
+    #[mockalloc::test]
+    fn check_invariants() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        assert_eq!(list.check_invariants(), Ok(()));
+        for item in 0..50 {
+            list.insert(item, item).unwrap();
+        }
+        assert_eq!(list.check_invariants(), Ok(()));
+
+        list.count += 1;
+        assert_eq!(
+            list.check_invariants(),
+            Err(InvariantViolation::Count {
+                expected: 51,
+                actual: 50
+            })
+        );
+        list.count -= 1;
+
+        list.nodes[0].span += 1;
+        assert_eq!(
+            list.check_invariants(),
+            Err(InvariantViolation::Span { pos: 0, level: 0 })
+        );
+        list.nodes[0].span -= 1;
+
+        let first = list.node_at(1).unwrap();
+        let saved = list.nodes[0];
+        list.nodes[0] = first.nexts()[0];
+        assert!(list.check_invariants().is_err());
+        list.nodes[0] = saved;
+        assert_eq!(list.check_invariants(), Ok(()));
+    }
+
+    #[cfg(feature = "debug-invariants")]
+    #[test]
+    #[should_panic(expected = "skip list invariant violated")]
+    fn debug_invariants() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        list.insert(1, 1).unwrap();
+        list.count += 1;
+        let _ = list.insert(2, 2);
+    }
+
+    // LESSに並べた組の順に小さいとする．推移律を満たさない順序も表せる．
+    #[derive(Debug, PartialEq, Eq)]
+    struct Table(u8);
+
+    const LESS: [(u8, u8); 6] = [(0, 1), (1, 2), (2, 0), (0, 3), (3, 2), (1, 3)];
+
+    impl PartialOrd for Table {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Table {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            use std::cmp::Ordering;
+            if self.0 == other.0 {
+                Ordering::Equal
+            } else if LESS.contains(&(self.0, other.0)) {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        }
+    }
+
+    // 常にLessを返す．
+    #[derive(Debug, PartialEq, Eq)]
+    struct Liar(i32);
+
+    impl PartialOrd for Liar {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Liar {
+        fn cmp(&self, _: &Self) -> std::cmp::Ordering {
+            std::cmp::Ordering::Less
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "inconsistent Ord")]
+    fn antisymmetry() {
+        let mut list = SkipList::new(|| false);
+        list.insert(Liar(1), ()).unwrap();
+        let _ = list.insert(Liar(2), ());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "inconsistent Ord")]
+    fn transitivity() {
+        // 0 < 1 < 2 < 0 と巡回している．1を挟んで0と2を並べてから1を取り除き，間に3を入れる．
+        let mut list = SkipList::new(|| false);
+        list.insert(Table(0), ()).unwrap();
+        list.insert(Table(1), ()).unwrap();
+        list.insert(Table(2), ()).unwrap();
+        list.remove(&Table(1)).unwrap();
+        let _ = list.insert(Table(3), ());
+    }
+
+    #[mockalloc::test]
+    fn level_iter() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        for item in 0..200 {
+            list.insert(item, item).unwrap();
+        }
+
+        assert!(list.level_iter(0).eq(list.iter()));
+        for level in 1..list.height() {
+            let expected = list.level_iter(level - 1).filter(|(k, _)| {
+                let node = list.find_first(|e| e < *k).take().unwrap();
+                node.level() > level
+            });
+            assert!(list.level_iter(level).eq(expected));
+        }
+        assert!(list.level_iter(list.height() - 1).next().is_some());
+        assert_eq!(list.level_iter(list.height()).count(), 0);
+    }
+
+    #[mockalloc::test]
+    fn page() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        for item in 0..50 {
+            list.insert(item, item).unwrap();
+        }
+
+        assert!(list.page(0, 10).map(|(k, _)| *k).eq(0..10));
+        assert!(list.page(45, 10).map(|(k, _)| *k).eq(45..50));
+        assert!(list.page(20, 0).next().is_none());
+        assert!(list.page(50, 10).next().is_none());
+        assert!(list.page(usize::MAX, usize::MAX).next().is_none());
+        assert!(list.page(1, usize::MAX).map(|(k, _)| *k).eq(1..50));
+    }
+
+    #[mockalloc::test]
+    fn iterator_overrides() {
+        use rand::Rng;
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for item in 0..300 {
+            list.insert(item * 2, item).unwrap();
+        }
+        let keys = |it: Option<(&i32, &i32)>| it.map(|(k, _)| *k);
+
+        assert_eq!(list.iter().count(), 300);
+        assert_eq!(list.iter().len(), 300);
+        assert_eq!(keys(list.iter().last()), Some(598));
+        assert_eq!(list.iter_mut().count(), 300);
+        assert_eq!(list.iter_mut().last().map(|(k, _)| *k), Some(598));
+
+        for _ in 0..200 {
+            let n = rng.gen_range(0..320);
+            let mut iter = list.iter();
+            iter.next();
+            assert_eq!(keys(iter.nth(n)), (n < 299).then_some(2 * n as i32 + 2));
+            assert_eq!(iter.len(), 299usize.saturating_sub(n + 1));
+            assert_eq!(keys(iter.next()), (n < 298).then_some(2 * n as i32 + 4));
+
+            let (a, b) = (rng.gen_range(0..620), rng.gen_range(0..620));
+            let (lo, hi) = (a.min(b), a.max(b));
+            let expected: Vec<_> = (lo..hi).filter(|k| k % 2 == 0 && *k < 600).collect();
+            assert_eq!(list.range(lo..hi).count(), expected.len());
+            assert_eq!(keys(list.range(lo..hi).last()), expected.last().copied());
+            let mut range = list.range(lo..hi);
+            let m = rng.gen_range(0..5);
+            assert_eq!(keys(range.nth(m)), expected.get(m).copied());
+            assert_eq!(keys(range.nth(m)), expected.get(2 * m + 1).copied());
+            assert!(range
+                .map(|(k, _)| *k)
+                .eq(expected.into_iter().skip(2 * m + 2)));
+        }
+        let empty = SkipList::<i32, i32, _>::new(|| false);
+        assert_eq!(empty.iter().last(), None);
+        assert_eq!(empty.range(..).count(), 0);
+    }
+
+    #[mockalloc::test]
+    fn range_rev() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for item in 0..200 {
+            list.insert(item * 2, item).unwrap();
+        }
+
+        assert!(list
+            .range(..100)
+            .rev()
+            .take(5)
+            .map(|(k, _)| *k)
+            .eq([98, 96, 94, 92, 90]));
+        assert!(list
+            .range(33..=41)
+            .rev()
+            .map(|(k, _)| *k)
+            .eq([40, 38, 36, 34]));
+        assert!(list
+            .range(..)
+            .rev()
+            .map(|(k, _)| *k)
+            .eq((0..200).rev().map(|e| e * 2)));
+        assert!(list.range(401..).next_back().is_none());
+        assert!(list.range(5..5).next_back().is_none());
+
+        // 両端から読んで，途中で出会ったら止まる．
+        let mut range = list.range(10..20);
+        assert_eq!(range.next(), Some((&10, &5)));
+        assert_eq!(range.next_back(), Some((&18, &9)));
+        assert!(range.by_ref().map(|(k, _)| *k).eq([12, 14, 16]));
+        assert_eq!(range.next_back(), None);
+    }
+
+    #[mockalloc::test]
+    fn swap_values() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..10 {
+            list.insert(key, vec![key; 3]).unwrap();
+        }
+
+        assert!(list.swap_values(&2, &7));
+        assert_eq!(list.search(&2), Some(&vec![7; 3]));
+        assert_eq!(list.search(&7), Some(&vec![2; 3]));
+        assert!(list.swap_values(&4, &4));
+        assert_eq!(list.search(&4), Some(&vec![4; 3]));
+        assert!(!list.swap_values(&4, &10));
+        assert!(!list.swap_values(&-1, &4));
+        assert_eq!(list.search(&4), Some(&vec![4; 3]));
+    }
+
+    #[mockalloc::test]
+    fn map_values() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..500 {
+            list.insert(key, key * 2).unwrap();
+        }
+        let towers: Vec<Vec<_>> = (0..list.nodes.len())
+            .map(|level| list.level_iter(level).map(|(k, _)| *k).collect())
+            .collect();
+
+        let list = list.map_values(|key, value| format!("{key}:{value}"));
+        assert_eq!(list.check_invariants(), Ok(()));
+        check_spans(&list);
+        assert!(list
+            .iter()
+            .map(|(k, v)| (*k, v.clone()))
+            .eq((0..500).map(|k| (k, format!("{k}:{}", k * 2)))));
+        for (level, keys) in towers.iter().enumerate() {
+            assert!(list.level_iter(level).map(|(k, _)| k).eq(keys));
+        }
+
+        let empty = SkipList::<i32, i32, _>::new(|| false).map_values(|_, v| v);
+        assert_eq!(empty.count(), 0);
+    }
+
+    #[test]
+    fn map_values_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use std::rc::Rc;
+        let shared = Rc::new(());
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..500 {
+            list.insert(key, shared.clone()).unwrap();
+        }
+
+        // fが途中でpanicしても，変換前後のnodeはどちらも解放される．
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            list.map_values(|key, value| {
+                assert!(*key < 250);
+                value
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    #[mockalloc::test]
+    fn find_and_position() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in (0..100).rev() {
+            list.insert(key * 3, key.to_string()).unwrap();
+        }
+
+        assert_eq!(list.find(|_, v| v.len() == 2), Some((&30, &"10".into())));
+        assert_eq!(list.position(|_, v| v.len() == 2), Some(10));
+        assert_eq!(list.position(|k, _| *k == 297), Some(99));
+        let pos = list.position(|k, _| k % 7 == 6).unwrap();
+        assert_eq!(list.page(pos, 1).next(), list.find(|k, _| k % 7 == 6));
+        assert_eq!(list.find(|k, _| *k == 1), None);
+        assert_eq!(list.position(|k, _| *k == 1), None);
+    }
+
+    #[mockalloc::test]
+    fn split_at_rank() {
+        for n in [0, 1, 37, 199, 200, 1000] {
+            let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            for key in 0..200 {
+                list.insert(key, key).unwrap();
+            }
+
+            let mut rest = list.split_at_rank(n, Gen::standard(SmallRng::from_entropy()));
+            let n = n.min(200);
+            assert_eq!(list.check_invariants(), Ok(()));
+            assert_eq!(rest.check_invariants(), Ok(()));
+            check_spans(&list);
+            check_spans(&rest);
+            assert!(list.iter().map(|(k, _)| *k).eq(0..n as i32));
+            assert!(rest.iter().map(|(k, _)| *k).eq(n as i32..200));
+
+            // どちらも分けた後でそのまま使える．
+            list.append(1000, 0).unwrap();
+            rest.insert(-1, 0).unwrap();
+            rest.append(1000, 0).unwrap();
+            assert_eq!(list.check_invariants(), Ok(()));
+            assert_eq!(rest.check_invariants(), Ok(()));
+        }
+    }
+
+    #[mockalloc::test]
+    fn extract_range() {
+        use rand::Rng;
+        use std::collections::BTreeMap;
+        use std::ops::Bound;
+        let mut rng = SmallRng::from_entropy();
+        for _ in 0..50 {
+            let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            let mut model = BTreeMap::new();
+            for key in 0..300 {
+                if rng.gen_bool(0.5) {
+                    list.insert(key, key).unwrap();
+                    model.insert(key, key);
+                }
+            }
+            let start = rng.gen_range(-10..310);
+            let end = rng.gen_range(start..320);
+            let bounds = (Bound::Included(start), Bound::Excluded(end));
+
+            let mut extracted = list.extract_range(bounds, Gen::standard(SmallRng::from_entropy()));
+            let mut rest = model.split_off(&start);
+            let mut after = rest.split_off(&end);
+            model.append(&mut after);
+            for l in [&list, &extracted] {
+                assert_eq!(l.check_invariants(), Ok(()));
+                check_spans(l);
+            }
+            assert!(list.iter().eq(model.iter()));
+            assert!(extracted.iter().eq(rest.iter()));
+
+            extracted.insert(-1, 0).unwrap();
+            extracted.append(1000, 0).unwrap();
+            list.append(1000, 0).unwrap();
+            assert_eq!(extracted.check_invariants(), Ok(()));
+            assert_eq!(list.check_invariants(), Ok(()));
+        }
+    }
+
+    #[mockalloc::test]
+    fn nth_after_and_before() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..500 {
+            list.insert(key * 2, key).unwrap();
+        }
+
+        assert_eq!(list.nth_after(&10, 0), Some((&12, &6)));
+        assert_eq!(list.nth_after(&11, 0), Some((&12, &6)));
+        assert_eq!(list.nth_after(&10, 100), Some((&212, &106)));
+        assert_eq!(list.nth_after(&-5, 0), Some((&0, &0)));
+        assert_eq!(list.nth_after(&996, 1), None);
+        assert_eq!(list.nth_after(&996, 0), Some((&998, &499)));
+        assert_eq!(list.nth_after(&0, usize::MAX), None);
+
+        assert_eq!(list.nth_before(&10, 0), Some((&8, &4)));
+        assert_eq!(list.nth_before(&11, 0), Some((&10, &5)));
+        assert_eq!(list.nth_before(&1000, 100), Some((&798, &399)));
+        assert_eq!(list.nth_before(&10, 4), Some((&0, &0)));
+        assert_eq!(list.nth_before(&10, 5), None);
+        assert_eq!(list.nth_before(&0, 0), None);
+    }
+
+    #[mockalloc::test]
+    fn bound_by() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for time in 0..100u32 {
+            for seq in 0..(time % 4) {
+                list.insert((time, seq), ()).unwrap();
+            }
+        }
+
+        let at = |time: u32| move |k: &(u32, u32)| k.0.cmp(&time);
+        assert_eq!(list.lower_bound_by(at(10)).map(|(k, _)| *k), Some((10, 0)));
+        assert_eq!(list.upper_bound_by(at(10)).map(|(k, _)| *k), Some((11, 0)));
+        assert_eq!(list.lower_bound_by(at(12)).map(|(k, _)| *k), Some((13, 0)));
+        assert_eq!(list.upper_bound_by(at(12)).map(|(k, _)| *k), Some((13, 0)));
+        assert_eq!(list.lower_bound_by(at(0)).map(|(k, _)| *k), Some((1, 0)));
+        assert_eq!(list.lower_bound_by(at(99)).map(|(k, _)| *k), Some((99, 0)));
+        assert_eq!(list.upper_bound_by(at(99)), None);
+        assert_eq!(list.lower_bound_by(at(100)), None);
+    }
+
+    #[mockalloc::test]
+    fn range_step() {
+        use rand::Rng;
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..1000 {
+            if rng.gen_bool(0.6) {
+                list.insert(key, key).unwrap();
+            }
+        }
+
+        for _ in 0..100 {
+            let start = rng.gen_range(0..1000);
+            let end = rng.gen_range(start..1000);
+            let step = rng.gen_range(1..50);
+            assert!(list
+                .range_step(start..=end, step)
+                .eq(list.range(start..=end).step_by(step)));
+        }
+        assert!(list.range_step(.., 1).eq(list.iter()));
+        assert!(list.range_step(.., 5000).eq(list.first()));
+        assert_eq!(list.range_step(2000.., 3).next(), None);
+    }
+
+    #[mockalloc::test]
+    fn group_by() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for time in (0..10_000).step_by(7) {
+            list.insert(time, time % 10).unwrap();
+        }
+
+        let mut days = 0;
+        for (day, entries) in list.group_by(|time| time / 1000) {
+            let expected = list.range(day * 1000..(day + 1) * 1000);
+            assert!(entries.eq(expected));
+            assert_eq!(day, days);
+            days += 1;
+        }
+        assert_eq!(days, 10);
+
+        // rangeを使わずに次のまとまりへ進んでもよい．
+        let groups: Vec<_> = list.group_by(|time| time / 2500).map(|(g, _)| g).collect();
+        assert_eq!(groups, [0, 1, 2, 3]);
+        let empty = SkipList::<i32, (), _>::new(|| false);
+        assert!(empty.group_by(|k| *k).next().is_none());
+    }
+
+    #[cfg(feature = "may-dangle")]
+    #[mockalloc::test]
+    fn may_dangle() {
+        // valueはlistより後に宣言したので先に捨てられるが，listのdropは借用を読まないので通る．
+        let mut list;
+        let value = String::from("value");
+        list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        list.insert(0, &value).unwrap();
+    }
+
+    #[mockalloc::test]
+    fn insert_with_level() {
+        let mut list = SkipList::new(|| false);
+        for key in 0..1000 {
+            list.insert(key * 2, ()).unwrap();
+        }
+        list.insert_with_level(501, (), 8).unwrap();
+        list.insert_with_level(701, (), 100).unwrap();
+        list.insert_with_level(901, (), 0).unwrap();
+        assert!(list.level_iter(7).map(|(k, _)| *k).eq([501, 701]));
+        assert_eq!(list.height(), 10);
+        assert!(list.level_iter(1).map(|(k, _)| *k).eq([501, 701]));
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.insert_with_level(501, (), 3).is_err());
+    }
+
+    #[mockalloc::test]
+    fn split_points() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        assert!(list.split_points(4).is_empty());
+        for item in 0..100 {
+            list.insert(item, item).unwrap();
+        }
+
+        assert_eq!(list.split_points(4), [&25, &50, &75]);
+        assert_eq!(list.split_points(3), [&33, &66]);
+        assert!(list.split_points(1).is_empty());
+        assert!(list.split_points(0).is_empty());
+        assert_eq!(list.split_points(1000).len(), 99);
+    }
+
+    #[mockalloc::test]
+    fn into_iter() {
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        for item in [3, 1, 4, 5, 9, 2, 6] {
+            list.insert(item, item.to_string()).unwrap();
+        }
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.next(), Some((1, "1".to_string())));
+        assert_eq!(iter.next(), Some((2, "2".to_string())));
+        // 残りのnodeはdropで解放される．
+        drop(iter);
+
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let list = SkipList::<i32, String, _>::new(gen);
+        assert_eq!(list.into_iter().next(), None);
+    }
+
+    #[mockalloc::test]
+    fn append_and_truncate() {
+        use rand::Rng;
+        let mut rng = SmallRng::from_entropy();
+        let gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(gen);
+        let mut model = std::collections::BTreeMap::new();
+
+        for key in 0..300 {
+            if rng.gen_bool(0.8) {
+                list.append(key, key).unwrap();
+                model.insert(key, key);
+            } else {
+                let key = rng.gen_range(0..key + 1);
+                let expected = if model.contains_key(&key) {
+                    Err(InsertError::Occupied(key, key))
+                } else {
+                    Ok(())
+                };
+                assert_eq!(list.append(key, key), expected, "{}", debug(&list));
+                model.insert(key, key);
+            }
+            if rng.gen_bool(0.1) {
+                let key = rng.gen_range(0..key + 1);
+                assert_eq!(list.remove(&key).ok(), model.remove_entry(&key));
+            }
+            if rng.gen_bool(0.05) {
+                let key = rng.gen_range(0..key + 1);
+                let expected = model.range(..key).count();
+                assert_eq!(list.truncate_before(&key), expected, "{}", debug(&list));
+                model = model.split_off(&key);
+            }
+            if rng.gen_bool(0.05) {
+                assert_eq!(list.pop_first(), model.pop_first());
+            }
+            if rng.gen_bool(0.05) {
+                assert_eq!(list.pop_last(), model.pop_last());
+            }
+            check_spans(&list);
+            assert_eq!(list.last(), model.last_key_value());
+        }
+
+        assert!(list.range(100..200).eq(model.range(100..200)));
+        assert_eq!(list.truncate_before(&1000), model.len());
+        check_spans(&list);
+        assert_eq!(list.count(), 0);
+        list.append(0, 0).unwrap();
+        check_spans(&list);
+    }
+
+    pub(crate) use crate::test_util::assert_structure as check_spans;
+
+    pub(crate) fn debug<K: Ord + Debug, V, R: Generator<bool>>(list: &SkipList<K, V, R>) -> String {
+        let mut out = String::new();
+        list.fmt_structure(&mut out).unwrap();
+        out
     }
 
-    struct Gen<T, R: rand::Rng, D: Distribution<T>> {
+    #[derive(Clone)]
+    pub(crate) struct Gen<T, R: rand::Rng, D: Distribution<T>> {
         rng: R,
         distr: D,
         marker: PhantomData<T>,
@@ -360,7 +2409,7 @@ mod test {
     where
         Standard: Distribution<T>,
     {
-        fn standard(rng: R) -> Self {
+        pub(crate) fn standard(rng: R) -> Self {
             Gen {
                 rng,
                 distr: Standard,
@@ -0,0 +1,238 @@
+use std::{
+    collections::{HashMap, TryReserveError},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use crate::{
+    node::{MaybeNode, Node},
+    Generator, SkipList,
+};
+
+// keyのhashからnodeを引く．hashの重なったnodeはcollidedに置き，keyを比べて探す．
+pub(crate) struct HashIndex<K: Ord, V> {
+    hash: fn(&K) -> u64,
+    nodes: HashMap<u64, Node<K, V>>,
+    // 64 bitのhashが重なることはまずないので，並べて順に見る．
+    collided: Vec<Node<K, V>>,
+}
+
+fn key_hash<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn same<K: Ord, V>(a: Node<K, V>, b: Node<K, V>) -> bool {
+    MaybeNode::from(a) == MaybeNode::from(b)
+}
+
+impl<K: Ord, V> HashIndex<K, V> {
+    fn add(&mut self, node: Node<K, V>) {
+        let hash = (self.hash)(node.key());
+        if let Some(other) = self.nodes.insert(hash, node) {
+            self.collided.push(other);
+        }
+    }
+
+    fn remove(&mut self, node: Node<K, V>) {
+        let hash = (self.hash)(node.key());
+        if !self.nodes.get(&hash).is_some_and(|n| same(*n, node)) {
+            self.collided.retain(|n| !same(*n, node));
+            return;
+        }
+        self.nodes.remove(&hash);
+        let hash_of = |n: &Node<K, V>| (self.hash)(n.key());
+        if let Some(pos) = self.collided.iter().position(|n| hash_of(n) == hash) {
+            self.nodes.insert(hash, self.collided.swap_remove(pos));
+        }
+    }
+
+    // 同じhashを使う空のindex．valueの型を変えたlistで引き直すのに使う．
+    pub(crate) fn emptied<W>(&self) -> HashIndex<K, W> {
+        HashIndex {
+            hash: self.hash,
+            nodes: HashMap::new(),
+            collided: Vec::new(),
+        }
+    }
+
+    pub(crate) fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.nodes.try_reserve(additional)
+    }
+
+    pub(crate) fn get(&self, key: &K) -> Option<Node<K, V>> {
+        let node = *self.nodes.get(&(self.hash)(key))?;
+        if node.key() == key {
+            return Some(node);
+        }
+        self.collided.iter().copied().find(|n| n.key() == key)
+    }
+}
+
+impl<K: Ord + Hash, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 以後，searchとsearch_mutとcontains_keyはlistを辿らずにhashで引く．rangeや順の走査は今までどおりlistを辿る．
+    pub fn enable_hash_index(&mut self) {
+        self.index = Some(HashIndex {
+            hash: key_hash::<K>,
+            nodes: HashMap::new(),
+            collided: Vec::new(),
+        });
+        self.rebuild_hash_index();
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn disable_hash_index(&mut self) {
+        self.index = None;
+    }
+
+    // nodeを作り直した後に，今のnodeだけで引き直す．
+    pub(crate) fn rebuild_hash_index(&mut self) {
+        let Some(index) = &mut self.index else {
+            return;
+        };
+        index.nodes.clear();
+        index.nodes.reserve(self.count);
+        index.collided.clear();
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            index.add(n);
+            node = n.nexts()[0].next;
+        }
+    }
+
+    pub(crate) fn index_add(&mut self, node: Node<K, V>) {
+        if let Some(index) = &mut self.index {
+            index.add(node);
+        }
+    }
+
+    pub(crate) fn index_remove(&mut self, node: Node<K, V>) {
+        if let Some(index) = &mut self.index {
+            index.remove(node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HashIndex;
+    use crate::node::Node;
+    use crate::test::Gen;
+    use crate::{Metrics, Operation, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Comparisons(Arc<AtomicUsize>);
+
+    impl Metrics for Comparisons {
+        fn operation(&self, op: Operation, comparisons: usize) {
+            if op == Operation::Search {
+                self.0.fetch_add(comparisons, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[mockalloc::test]
+    fn hash_index() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut model = BTreeMap::new();
+        for _ in 0..200 {
+            let key = rng.gen_range(0..400);
+            if list.insert(key, key).is_ok() {
+                model.insert(key, key);
+            }
+        }
+        list.enable_hash_index();
+        let comparisons = Arc::new(AtomicUsize::new(0));
+        list.set_metrics(Comparisons(comparisons.clone()));
+
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..400);
+            match rng.gen_range(0..6) {
+                0 => {
+                    assert_eq!(list.insert(key, key).is_ok(), !model.contains_key(&key));
+                    model.entry(key).or_insert(key);
+                }
+                1 => assert_eq!(list.remove(&key).ok(), model.remove_entry(&key)),
+                2 => {
+                    if let Some(value) = list.search_mut(&key) {
+                        *value += 1;
+                        *model.get_mut(&key).unwrap() += 1;
+                    }
+                }
+                3 => assert_eq!(list.pop_first(), model.pop_first()),
+                4 => {
+                    if list.swap_values(&key, &(key + 1)) {
+                        let a = model[&key];
+                        let b = model.insert(key + 1, a).unwrap();
+                        model.insert(key, b);
+                    }
+                }
+                _ => {
+                    let start = rng.gen_range(0..20);
+                    list.truncate_before(&start);
+                    model = model.split_off(&start);
+                }
+            }
+            assert_eq!(list.search(&key), model.get(&key));
+        }
+        assert!((0..400).all(|key| list.contains_key(&key) == model.contains_key(&key)));
+        assert_eq!(comparisons.load(Ordering::Relaxed), 0);
+
+        // towerを作り直すcompactや昇格の後も，引けるnodeを指している．
+        list.set_self_adjusting(true);
+        for key in model.keys() {
+            for _ in 0..10 {
+                list.search_mut(key).unwrap();
+            }
+        }
+        list.compact();
+        assert!(model.iter().all(|(k, v)| list.search(k) == Some(v)));
+
+        list.disable_hash_index();
+        assert!(model.iter().all(|(k, v)| list.search(k) == Some(v)));
+        assert!(comparisons.load(Ordering::Relaxed) > 0);
+    }
+
+    #[mockalloc::test]
+    fn map_values() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..200 {
+            list.insert(key, key).unwrap();
+        }
+        list.enable_hash_index();
+        let mut list = list.map_values(|_, value| value.to_string());
+        let comparisons = Arc::new(AtomicUsize::new(0));
+        list.set_metrics(Comparisons(comparisons.clone()));
+        assert!((0..200).all(|key| list.search(&key) == Some(&key.to_string())));
+        assert_eq!(list.search(&200), None);
+        assert_eq!(comparisons.load(Ordering::Relaxed), 0);
+    }
+
+    #[mockalloc::test]
+    fn collisions() {
+        let mut index = HashIndex {
+            hash: |key: &i32| (*key % 3) as u64,
+            nodes: HashMap::new(),
+            collided: Vec::new(),
+        };
+        let nodes: Vec<_> = (0..9).map(|key| Node::new(key, (), 1)).collect();
+        for node in &nodes {
+            index.add(*node);
+        }
+        assert!((0..9).all(|key| index.get(&key).is_some_and(|n| *n.key() == key)));
+        for node in &nodes[..5] {
+            index.remove(*node);
+        }
+        assert!((0..5).all(|key| index.get(&key).is_none()));
+        assert!((5..9).all(|key| index.get(&key).is_some_and(|n| *n.key() == key)));
+        for node in nodes {
+            node.dispose();
+        }
+    }
+}
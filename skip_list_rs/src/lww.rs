@@ -0,0 +1,228 @@
+use crate::{Generator, SkipList};
+
+// Lamport時刻とreplicaの組．時刻が同じならreplicaの順で決めるので，どのreplicaでも同じ書き込みが勝つ．
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Stamp<R> {
+    pub time: u64,
+    pub replica: R,
+}
+
+// valueがNoneなら削除の墓標．
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Slot<V, R> {
+    value: Option<V>,
+    stamp: Stamp<R>,
+}
+
+// keyごとにstampの最も新しい書き込みを残すCRDT．mergeは各keyでstampの大きいほうを選ぶだけなので，
+// 順序によらず，何度繰り返しても同じ結果になる．削除は墓標として残し，全replicaに行き渡ってからgcで消す．
+pub struct LwwSkipMap<K: Ord, V, R: Ord, G: Generator<bool>> {
+    list: SkipList<K, Slot<V, R>, G>,
+    replica: R,
+    clock: u64,
+    live: usize,
+}
+
+impl<K: Ord, V, R: Ord + Clone, G: Generator<bool>> LwwSkipMap<K, V, R, G> {
+    pub fn new(replica: R, gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            replica,
+            clock: 0,
+            live: 0,
+        }
+    }
+
+    // 墓標を除いたentryの数．
+    pub fn count(&self) -> usize {
+        self.live
+    }
+
+    pub fn replica(&self) -> &R {
+        &self.replica
+    }
+
+    // これまでに見た最も新しい時刻．
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.list.search(key)?.value.as_ref()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn stamp(&self, key: &K) -> Option<&Stamp<R>> {
+        Some(&self.list.search(key)?.stamp)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Stamp<R> {
+        self.write(key, Some(value))
+    }
+
+    // 墓標を置くので，keyがなくても記録する．
+    pub fn remove(&mut self, key: K) -> Stamp<R> {
+        self.write(key, None)
+    }
+
+    fn write(&mut self, key: K, value: Option<V>) -> Stamp<R> {
+        self.clock += 1;
+        let stamp = Stamp {
+            time: self.clock,
+            replica: self.replica.clone(),
+        };
+        self.apply(key, value, stamp.clone());
+        stamp
+    }
+
+    // stampが今のものより新しければ書き込む．勝ったらtrueを返す．
+    pub fn apply(&mut self, key: K, value: Option<V>, stamp: Stamp<R>) -> bool {
+        self.clock = self.clock.max(stamp.time);
+        let live = value.is_some() as usize;
+        if let Some(slot) = self.list.search_mut(&key) {
+            if slot.stamp >= stamp {
+                return false;
+            }
+            self.live = self.live - slot.value.is_some() as usize + live;
+            *slot = Slot { value, stamp };
+            return true;
+        }
+        self.live += live;
+        if self.list.insert(key, Slot { value, stamp }).is_err() {
+            unreachable!("the key was not found");
+        }
+        true
+    }
+
+    pub fn merge<H: Generator<bool>>(&mut self, remote: &LwwSkipMap<K, V, R, H>)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        for (key, slot) in remote.list.iter() {
+            self.apply(key.clone(), slot.value.clone(), slot.stamp.clone());
+        }
+        self.clock = self.clock.max(remote.clock);
+    }
+
+    // 時刻がstable以下の墓標を消す．どのreplicaもstableまでの書き込みをmergeし終えていること．
+    // まだ見ていないreplicaからその前の書き込みが届くと，消えたkeyが生き返る．
+    pub fn gc(&mut self, stable: u64) -> usize {
+        self.list.retain_range(.., |_, slot| {
+            slot.value.is_some() || slot.stamp.time > stable
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.list
+            .iter()
+            .filter_map(|(key, slot)| Some((key, slot.value.as_ref()?)))
+    }
+
+    // 墓標のkeyと，それを置いた削除のstamp．
+    pub fn tombstones(&self) -> impl Iterator<Item = (&K, &Stamp<R>)> {
+        self.list
+            .iter()
+            .filter(|(_, slot)| slot.value.is_none())
+            .map(|(key, slot)| (key, &slot.stamp))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LwwSkipMap, Stamp};
+    use crate::test::Gen;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    type Map = LwwSkipMap<u32, u32, u8, Gen<bool, SmallRng, rand::distributions::Standard>>;
+
+    fn replica(id: u8) -> Map {
+        LwwSkipMap::new(id, Gen::standard(SmallRng::from_entropy()))
+    }
+
+    fn stamp(time: u64, replica: u8) -> Stamp<u8> {
+        Stamp { time, replica }
+    }
+
+    fn entries(map: &Map) -> Vec<(u32, u32)> {
+        map.iter().map(|(k, v)| (*k, *v)).collect()
+    }
+
+    #[mockalloc::test]
+    fn last_writer_wins() {
+        let mut a = replica(1);
+        let mut b = replica(2);
+        assert_eq!(a.insert(5, 50), stamp(1, 1));
+        assert_eq!(b.insert(5, 51), stamp(1, 2));
+        a.merge(&b);
+        b.merge(&a);
+        // 同じ時刻ならreplicaの大きいほうが勝つ．
+        assert_eq!(a.get(&5), Some(&51));
+        assert_eq!(b.get(&5), Some(&51));
+
+        assert_eq!(a.remove(5), stamp(2, 1));
+        assert_eq!(a.count(), 0);
+        assert!(!a.contains_key(&5));
+        b.merge(&a);
+        assert_eq!(b.get(&5), None);
+        assert!(b.tombstones().eq([(&5, &stamp(2, 1))]));
+
+        // 古い書き込みは墓標に負ける．
+        assert!(!b.apply(5, Some(0), stamp(1, 9)));
+        assert_eq!(b.gc(1), 0);
+        assert_eq!(b.gc(2), 1);
+        assert!(b.tombstones().next().is_none());
+        assert_eq!(b.clock(), 2);
+        assert_eq!(b.insert(6, 60).time, 3);
+    }
+
+    #[mockalloc::test]
+    fn converges() {
+        let mut rng = SmallRng::from_entropy();
+        let mut replicas: Vec<_> = (0..3).map(replica).collect();
+        for _ in 0..600 {
+            let map = &mut replicas[rng.gen_range(0..3)];
+            let key = rng.gen_range(0..50);
+            match rng.gen_range(0..3) {
+                0 => {
+                    map.remove(key);
+                }
+                _ => {
+                    map.insert(key, rng.gen());
+                }
+            }
+            // たまに2つのreplicaだけを合わせる．
+            if rng.gen_ratio(1, 10) {
+                let (from, to) = (rng.gen_range(0..3), rng.gen_range(0..3));
+                if from != to {
+                    let remote = std::mem::replace(&mut replicas[from], replica(9));
+                    replicas[to].merge(&remote);
+                    replicas[from] = remote;
+                }
+            }
+        }
+
+        // 違う順で合わせても，同じ中身になる．
+        let [a, b, c] = <[Map; 3]>::try_from(replicas).ok().unwrap();
+        let mut left = replica(1);
+        for map in [&a, &b, &c] {
+            left.merge(map);
+        }
+        let mut right = replica(2);
+        for map in [&c, &a, &b, &a] {
+            right.merge(map);
+        }
+        assert_eq!(entries(&left), entries(&right));
+        assert_eq!(left.count(), entries(&left).len());
+        assert!(left.tombstones().eq(right.tombstones()));
+
+        let stable = left.clock();
+        assert_eq!(left.gc(stable), right.gc(stable));
+        assert_eq!(entries(&left), entries(&right));
+        assert_eq!(left.tombstones().count(), 0);
+    }
+}
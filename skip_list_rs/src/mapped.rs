@@ -0,0 +1,325 @@
+use std::{
+    io::{self, Write},
+    marker::PhantomData,
+    ops::{Bound, RangeBounds, RangeFull},
+};
+
+use crate::{
+    snapshot::{invalid, write_entry, write_u64},
+    Codec, Generator, SkipList,
+};
+
+// 書式は MAGIC，entryの数，高さ，headのlink，nodeの列．
+// nodeは level，linkの列，keyの長さ，key，valueの長さ，valueの順に並ぶ．
+// linkは指すnodeのファイル先頭からのoffsetで，0はnull．数とoffsetはすべてu64のlittle endian．
+// nodeはkeyの昇順に並ぶので，linkは必ず後ろを指す．
+const MAGIC: [u8; 4] = *b"SKM1";
+const HEAD: usize = 20;
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 各nodeのtowerをそのまま書き出す．offsetを決めるために，keyとvalueを2度encodeする．
+    pub fn write_mapped(
+        &self,
+        mut out: impl Write,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+    ) -> io::Result<()> {
+        let mut buf = Vec::new();
+        let mut encoded_len = |key: &K, value: &V| {
+            buf.clear();
+            keys.encode(key, &mut buf);
+            values.encode(value, &mut buf);
+            buf.len()
+        };
+
+        let mut offsets = Vec::with_capacity(self.count);
+        let mut offset = HEAD + self.nodes.len() * 8;
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            offsets.push(offset);
+            offset += (n.level() + 3) * 8 + encoded_len(n.key(), n.value());
+            node = n.nexts()[0].next;
+        }
+        // posの位置にあるnodeからspanだけ先のnodeのoffset．
+        let target = |pos: usize, span: usize| match span {
+            0 => 0,
+            span => offsets[pos + span - 1],
+        };
+
+        out.write_all(&MAGIC)?;
+        write_u64(&mut out, self.count)?;
+        write_u64(&mut out, self.nodes.len())?;
+        for link in &self.nodes {
+            let next = if link.next.is_null() { 0 } else { link.span };
+            write_u64(&mut out, target(0, next))?;
+        }
+
+        let mut buf = Vec::new();
+        let mut node = self.nodes[0].next;
+        let mut pos = 1;
+        while let Some(n) = node.take() {
+            write_u64(&mut out, n.level())?;
+            for link in n.nexts() {
+                let next = if link.next.is_null() { 0 } else { link.span };
+                write_u64(&mut out, target(pos, next))?;
+            }
+            write_entry(&mut out, &mut buf, (n.key(), n.value()), keys, values)?;
+            node = n.nexts()[0].next;
+            pos += 1;
+        }
+        out.flush()
+    }
+}
+
+// write_mappedで書いたbyte列の上で，そのまま探索する．mmapした領域などを渡す．
+// 壊れた入力はio::ErrorKind::InvalidDataになる．keyは比較のたびにdecodeし，valueはbyte列のまま返す．
+pub struct MappedSkipList<'a, K, C> {
+    bytes: &'a [u8],
+    codec: C,
+    count: usize,
+    height: usize,
+    marker: PhantomData<fn() -> K>,
+}
+
+impl<'a, K: Ord, C: Codec<K>> MappedSkipList<'a, K, C> {
+    pub fn new(bytes: &'a [u8], codec: C) -> io::Result<Self> {
+        if bytes.get(..4) != Some(MAGIC.as_slice()) {
+            return Err(invalid("not a mapped skip list"));
+        }
+        let mut list = Self {
+            bytes,
+            codec,
+            count: 0,
+            height: 0,
+            marker: PhantomData,
+        };
+        list.count = list.word(4)?;
+        list.height = list.word(12)?;
+        list.slice(HEAD, list.height.saturating_mul(8))?;
+        Ok(list)
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn search(&self, key: &K) -> io::Result<Option<&'a [u8]>> {
+        let Some(node) = self.lower_bound(|k| k < key)? else {
+            return Ok(None);
+        };
+        let (found, value) = self.entry(node)?;
+        Ok((&self.codec.decode(found)? == key).then_some(value))
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> MappedRange<'_, 'a, K, C, R> {
+        let node = match range.start_bound() {
+            Bound::Included(start) => self.lower_bound(|k| k < start),
+            Bound::Excluded(start) => self.lower_bound(|k| k <= start),
+            Bound::Unbounded => self.next(0, 0),
+        };
+        MappedRange {
+            list: self,
+            node,
+            range,
+        }
+    }
+
+    pub fn iter(&self) -> MappedRange<'_, 'a, K, C, RangeFull> {
+        self.range(..)
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> io::Result<&'a [u8]> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or_else(|| invalid("offset out of bounds"))
+    }
+
+    fn word(&self, offset: usize) -> io::Result<usize> {
+        let bytes = self.slice(offset, 8)?.try_into().unwrap();
+        usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| invalid("offset overflows usize"))
+    }
+
+    // nodeのlevelのlinkの指すnode．nodeが0ならhead．
+    fn next(&self, node: usize, level: usize) -> io::Result<Option<usize>> {
+        let (links, height) = match node {
+            0 => (HEAD, self.height),
+            node => (offset(node, 1)?, self.word(node)?),
+        };
+        if level >= height || height > self.height {
+            return Err(invalid("link level out of range"));
+        }
+        match self.word(offset(links, level)?)? {
+            0 => Ok(None),
+            // 後ろしか指さないので，壊れていても辿るのは必ず終わる．
+            next if next > node.max(HEAD) => Ok(Some(next)),
+            _ => Err(invalid("link points backwards")),
+        }
+    }
+
+    fn entry(&self, node: usize) -> io::Result<(&'a [u8], &'a [u8])> {
+        let level = self.word(node)?;
+        if level > self.height {
+            return Err(invalid("node level out of range"));
+        }
+        let key_at = offset(node, level + 1)?;
+        let key_len = self.word(key_at)?;
+        let key = self.slice(offset(key_at, 1)?, key_len)?;
+        let value_at = offset(key_at, 1)?
+            .checked_add(key_len)
+            .ok_or_else(|| invalid("offset overflows usize"))?;
+        let value = self.slice(offset(value_at, 1)?, self.word(value_at)?)?;
+        Ok((key, value))
+    }
+
+    // beforeを満たさない最初のnode．
+    fn lower_bound(&self, mut before: impl FnMut(&K) -> bool) -> io::Result<Option<usize>> {
+        let mut node = 0;
+        for level in (0..self.height).rev() {
+            while let Some(next) = self.next(node, level)? {
+                if !before(&self.codec.decode(self.entry(next)?.0)?) {
+                    break;
+                }
+                node = next;
+            }
+        }
+        self.next(node, 0)
+    }
+}
+
+// baseからwords語だけ先のoffset．壊れたファイルの値で溢れうるので検査する．
+fn offset(base: usize, words: usize) -> io::Result<usize> {
+    words
+        .checked_mul(8)
+        .and_then(|len| base.checked_add(len))
+        .ok_or_else(|| invalid("offset overflows usize"))
+}
+
+pub struct MappedRange<'m, 'a, K, C, R> {
+    list: &'m MappedSkipList<'a, K, C>,
+    node: io::Result<Option<usize>>,
+    range: R,
+}
+
+impl<'a, K: Ord, C: Codec<K>, R: RangeBounds<K>> Iterator for MappedRange<'_, 'a, K, C, R> {
+    type Item = io::Result<(&'a [u8], &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // 誤りは一度だけ返し，そこで止まる．
+        let node = match std::mem::replace(&mut self.node, Ok(None)) {
+            Ok(node) => node?,
+            Err(e) => return Some(Err(e)),
+        };
+        let step = || {
+            let (key, value) = self.list.entry(node)?;
+            let decoded = self.list.codec.decode(key)?;
+            let within = match self.range.end_bound() {
+                Bound::Included(end) => &decoded <= end,
+                Bound::Excluded(end) => &decoded < end,
+                Bound::Unbounded => true,
+            };
+            Ok(within.then_some((key, value)))
+        };
+        match step() {
+            Ok(Some(entry)) => {
+                self.node = self.list.next(node, 0);
+                Some(Ok(entry))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HEAD;
+    use crate::test::Gen;
+    use crate::{Codec, MappedSkipList, Plain, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::{self, ErrorKind};
+
+    fn collect<'a>(
+        range: impl Iterator<Item = io::Result<(&'a [u8], &'a [u8])>>,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        range
+            .map(|e| e.map(|(k, v)| (k.to_vec(), v.to_vec())))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[mockalloc::test]
+    fn search_and_range() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..300 {
+            let key: u32 = rng.gen_range(0..1000);
+            list.insert(key, format!("value {key}")).ok();
+        }
+        let mut bytes = Vec::new();
+        list.write_mapped(&mut bytes, &Plain, &Plain).unwrap();
+
+        let mapped = MappedSkipList::new(bytes.as_slice(), Plain).unwrap();
+        assert_eq!(mapped.count(), list.count());
+        for key in 0..1000 {
+            let expected = list.search(&key).map(String::as_bytes);
+            assert_eq!(mapped.search(&key).unwrap(), expected);
+        }
+
+        let encode = |(key, value): (&u32, &String)| {
+            let mut buf = Vec::new();
+            Plain.encode(key, &mut buf);
+            (buf, value.as_bytes().to_vec())
+        };
+        for _ in 0..50 {
+            let start = rng.gen_range(0..1000);
+            let end = rng.gen_range(start..1000);
+            assert_eq!(
+                collect(mapped.range(start..end)),
+                list.range(start..end).map(encode).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(
+            collect(mapped.iter()),
+            list.iter().map(encode).collect::<Vec<_>>()
+        );
+    }
+
+    #[mockalloc::test]
+    fn corrupted() {
+        let mut list = SkipList::new(|| false);
+        for key in [1u8, 2, 3] {
+            list.insert(key, key).unwrap();
+        }
+        let mut bytes = Vec::new();
+        list.write_mapped(&mut bytes, &Plain, &Plain).unwrap();
+
+        let truncated = MappedSkipList::new(&bytes[..bytes.len() - 1], Plain).unwrap();
+        assert_eq!(truncated.search(&2u8).unwrap(), Some([2].as_slice()));
+        let kind = truncated.search(&3).unwrap_err().kind();
+        assert_eq!(kind, ErrorKind::InvalidData);
+        let results: Vec<_> = truncated.iter().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+
+        // headのlinkを自分より前に向ける．
+        let mut backwards = bytes.clone();
+        backwards[HEAD..HEAD + 8].copy_from_slice(&4u64.to_le_bytes());
+        let mapped = MappedSkipList::new(backwards.as_slice(), Plain).unwrap();
+        assert!(mapped.search(&1).is_err());
+
+        // 先頭のnodeのlevelを壊す．
+        let first = u64::from_le_bytes(bytes[HEAD..HEAD + 8].try_into().unwrap()) as usize;
+        for level in [u64::MAX, u64::MAX / 8, 1 << 32] {
+            let mut corrupt = bytes.clone();
+            corrupt[first..first + 8].copy_from_slice(&level.to_le_bytes());
+            let mapped = MappedSkipList::new(corrupt.as_slice(), Plain).unwrap();
+            let kind = mapped.search(&1u8).unwrap_err().kind();
+            assert_eq!(kind, ErrorKind::InvalidData);
+            assert!(mapped.iter().next().unwrap().is_err());
+        }
+
+        assert!(MappedSkipList::<u8, _>::new(&bytes[..3], Plain).is_err());
+    }
+}
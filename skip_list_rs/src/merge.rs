@@ -0,0 +1,313 @@
+use std::{cmp::Ordering, marker::PhantomData};
+
+use crate::{node::MaybeNode, Generator, SkipList};
+
+// fromから前方に，keyがbeforeを満たさない最初のnodeを探す．
+// towerを上りながら進み，行き過ぎる手前で下りるので，進む距離をdとしてO(log d)で着く．
+pub(crate) fn seek<K: Ord, V>(
+    from: MaybeNode<K, V>,
+    mut before: impl FnMut(&K) -> bool,
+) -> MaybeNode<K, V> {
+    let Some(mut node) = from.take() else {
+        return from;
+    };
+    if !before(node.key()) {
+        return from;
+    }
+
+    let mut level = 0;
+    loop {
+        let links = node.nexts();
+        if links
+            .get(level + 1)
+            .and_then(|link| link.next.take())
+            .is_some_and(|next| before(next.key()))
+        {
+            level += 1;
+            continue;
+        }
+        match links[level].next.take() {
+            Some(next) if before(next.key()) => node = next,
+            _ => break,
+        }
+    }
+    loop {
+        while let Some(next) = node.nexts()[level].next.take() {
+            if !before(next.key()) {
+                break;
+            }
+            node = next;
+        }
+        if level == 0 {
+            return node.nexts()[0].next;
+        }
+        level -= 1;
+    }
+}
+
+fn order<K: Ord, V>(left: MaybeNode<K, V>, right: MaybeNode<K, V>) -> Option<Ordering> {
+    match (left.take(), right.take()) {
+        (None, None) => None,
+        (Some(_), None) => Some(Ordering::Less),
+        (None, Some(_)) => Some(Ordering::Greater),
+        (Some(l), Some(r)) => Some(l.key().cmp(r.key())),
+    }
+}
+
+// 両方にあるkeyは左のentryを返す．
+pub struct Union<'a, K: Ord, V> {
+    left: MaybeNode<K, V>,
+    right: MaybeNode<K, V>,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for Union<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let side = match order(self.left, self.right)? {
+            Ordering::Less => &mut self.left,
+            Ordering::Greater => &mut self.right,
+            Ordering::Equal => {
+                self.right = self.right.take()?.nexts()[0].next;
+                &mut self.left
+            }
+        };
+        let node = side.take()?;
+        *side = node.nexts()[0].next;
+        Some((node.key(), node.value()))
+    }
+}
+
+// 小さいほうを相手のkeyまで飛ばす．
+pub struct Join<'a, K: Ord, V, W> {
+    left: MaybeNode<K, V>,
+    right: MaybeNode<K, W>,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a, W: 'a> Iterator for Join<'a, K, V, W> {
+    type Item = (&'a K, &'a V, &'a W);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (left, right) = (self.left.take()?, self.right.take()?);
+            match left.key().cmp(right.key()) {
+                Ordering::Less => self.left = seek(self.left, |k| k < right.key()),
+                Ordering::Greater => self.right = seek(self.right, |k| k < left.key()),
+                Ordering::Equal => {
+                    self.left = left.nexts()[0].next;
+                    self.right = right.nexts()[0].next;
+                    return Some((left.key(), left.value(), right.value()));
+                }
+            }
+        }
+    }
+}
+
+// 両方にあるkeyの左のentry．
+pub struct Intersection<'a, K: Ord, V> {
+    join: Join<'a, K, V, V>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for Intersection<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value, _) = self.join.next()?;
+        Some((key, value))
+    }
+}
+
+// 右を左のkeyまで飛ばす．
+pub struct Difference<'a, K: Ord, V> {
+    left: MaybeNode<K, V>,
+    right: MaybeNode<K, V>,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for Difference<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let left = self.left.take()?;
+            let Some(right) = self.right.take() else {
+                self.left = left.nexts()[0].next;
+                return Some((left.key(), left.value()));
+            };
+            match left.key().cmp(right.key()) {
+                Ordering::Less => {
+                    self.left = left.nexts()[0].next;
+                    return Some((left.key(), left.value()));
+                }
+                Ordering::Greater => self.right = seek(self.right, |k| k < left.key()),
+                Ordering::Equal => {
+                    self.left = left.nexts()[0].next;
+                    self.right = right.nexts()[0].next;
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn union<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipList<K, V, H>,
+    ) -> Union<'a, K, V> {
+        Union {
+            left: self.nodes[0].next,
+            right: other.nodes[0].next,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn intersection<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipList<K, V, H>,
+    ) -> Intersection<'a, K, V> {
+        Intersection {
+            join: self.join(other),
+        }
+    }
+
+    // 両方にあるkeyと，それぞれのvalue．
+    pub fn join<'a, W, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipList<K, W, H>,
+    ) -> Join<'a, K, V, W> {
+        Join {
+            left: self.nodes[0].next,
+            right: other.nodes[0].next,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn difference<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipList<K, V, H>,
+    ) -> Difference<'a, K, V> {
+        Difference {
+            left: self.nodes[0].next,
+            right: other.nodes[0].next,
+            marker: PhantomData,
+        }
+    }
+
+    // selfのkeyを順にotherの中で飛ばし探すので，selfが疎なら大きなotherを全部は辿らない．
+    pub fn is_subset<H: Generator<bool>>(&self, other: &SkipList<K, V, H>) -> bool {
+        if self.count > other.count {
+            return false;
+        }
+        let mut right = other.nodes[0].next;
+        for (key, _) in self.iter() {
+            right = seek(right, |k| k < key);
+            match right.take() {
+                Some(node) if node.key() == key => right = node.nexts()[0].next,
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    pub fn is_superset<H: Generator<bool>>(&self, other: &SkipList<K, V, H>) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint<H: Generator<bool>>(&self, other: &SkipList<K, V, H>) -> bool {
+        self.intersection(other).next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    #[mockalloc::test]
+    fn set_operations() {
+        let mut rng = SmallRng::from_entropy();
+        for density in [0.01, 0.1, 0.5, 0.9] {
+            let mut left = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            let mut right = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            let mut l = BTreeSet::new();
+            let mut r = BTreeSet::new();
+            for key in 0..2000 {
+                if rng.gen_bool(density) {
+                    left.insert(key, 'l').unwrap();
+                    l.insert(key);
+                }
+                if rng.gen_bool(0.5) {
+                    right.insert(key, 'r').unwrap();
+                    r.insert(key);
+                }
+            }
+
+            let keys = |(k, _)| k;
+            assert!(left.union(&right).map(keys).eq(l.union(&r)));
+            assert!(left.intersection(&right).map(keys).eq(l.intersection(&r)));
+            assert!(right.intersection(&left).map(keys).eq(l.intersection(&r)));
+            assert!(left.difference(&right).map(keys).eq(l.difference(&r)));
+            assert!(right.difference(&left).map(keys).eq(r.difference(&l)));
+
+            assert!(left.union(&right).all(|(k, v)| *v == 'l' || !l.contains(k)));
+            assert!(left.intersection(&right).all(|(_, v)| *v == 'l'));
+
+            assert_eq!(left.is_subset(&right), l.is_subset(&r));
+            assert_eq!(left.is_superset(&right), l.is_superset(&r));
+            assert_eq!(left.is_disjoint(&right), l.is_disjoint(&r));
+        }
+    }
+
+    #[mockalloc::test]
+    fn join() {
+        let mut names = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut scores = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for id in 0..1000 {
+            names.insert(id, format!("user {id}")).unwrap();
+            if id % 97 == 0 {
+                scores.insert(id, id as f64 / 2.0).unwrap();
+            }
+        }
+        scores.insert(5000, 0.0).unwrap();
+
+        let joined: Vec<_> = names.join(&scores).collect();
+        assert_eq!(joined.len(), 11);
+        for (id, name, score) in joined {
+            assert_eq!(*name, format!("user {id}"));
+            assert_eq!(*score, *id as f64 / 2.0);
+        }
+        assert!(scores
+            .join(&names)
+            .map(|(id, _, _)| *id)
+            .eq((0..1000).step_by(97)));
+    }
+
+    #[mockalloc::test]
+    fn predicates() {
+        let list = |keys: &[i32]| {
+            let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            for key in keys {
+                list.insert(*key, ()).unwrap();
+            }
+            list
+        };
+        let all = list(&(0..1000).collect::<Vec<_>>());
+        let sparse = list(&[3, 400, 999]);
+        let other = list(&[3, 401]);
+        let empty = list(&[]);
+
+        assert!(sparse.is_subset(&all));
+        assert!(all.is_superset(&sparse));
+        assert!(!all.is_subset(&sparse));
+        assert!(!other.is_subset(&sparse));
+        assert!(!sparse.is_disjoint(&other));
+        assert!(list(&[1000, 1001]).is_disjoint(&all));
+        assert!(empty.is_subset(&sparse));
+        assert!(empty.is_disjoint(&all));
+    }
+}
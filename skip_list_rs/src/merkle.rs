@@ -0,0 +1,272 @@
+use std::{
+    hash::{Hash, Hasher},
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{AggregateSkipList, Generator, InsertError, Monoid, RemoveError};
+
+// 範囲の中のentryのhashの和と数．和は並びによらないので，towerの形が違うreplica同士でも比べられる．
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RangeDigest {
+    pub hash: u64,
+    pub count: usize,
+}
+
+impl<V> Monoid<(V, u64)> for RangeDigest {
+    fn empty() -> Self {
+        Self::default()
+    }
+
+    fn of((_, hash): &(V, u64)) -> Self {
+        Self {
+            hash: *hash,
+            count: 1,
+        }
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Self {
+            hash: self.hash.wrapping_add(other.hash),
+            count: self.count + other.count,
+        }
+    }
+}
+
+pub type DigestRange<K> = (Bound<K>, Bound<K>);
+
+// FNV-1aに，和をとっても偏らないよう最後にsplitmix64の攪拌をかける．整数はlittle endianで，
+// usizeとisizeは64 bitに広げて書くので，DefaultHasherと違ってRustのversionやtargetによらない．
+// ただしKとVのHash実装が書く内容は揃っていること．
+struct StableHasher(u64);
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        let mut h = self.0;
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+        h ^ (h >> 31)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_i64(n as i64);
+    }
+}
+
+fn entry_hash<K: Hash, V: Hash>(key: &K, value: &V) -> u64 {
+    let mut hasher = StableHasher(0xcbf29ce484222325);
+    (key, value).hash(&mut hasher);
+    hasher.finish()
+}
+
+// 各entryのhashをvalueと並べて持ち，AggregateSkipListの各linkにその飛び越す範囲のdigestを持たせる．
+// range_digestはtowerを上って下りるだけなのでO(log n)で求まる．
+pub struct MerkleSkipList<K: Ord, V, G: Generator<bool>> {
+    inner: AggregateSkipList<K, (V, u64), RangeDigest, G>,
+}
+
+impl<K: Ord + Hash, V: Hash, G: Generator<bool>> MerkleSkipList<K, V, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            inner: AggregateSkipList::new(gen),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        let hash = entry_hash(&key, &value);
+        self.inner
+            .insert(key, (value, hash))
+            .map_err(|InsertError::Occupied(key, (value, _))| InsertError::Occupied(key, value))
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        let (value, _) = self.inner.search(key)?;
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    pub fn update<R>(&mut self, key: &K, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        self.inner.update(key, |(value, hash)| {
+            let result = f(value);
+            *hash = entry_hash(key, value);
+            result
+        })
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let (key, (value, _)) = self.inner.remove(key)?;
+        Ok((key, value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.inner.iter().map(|(key, (value, _))| (key, value))
+    }
+
+    pub fn range_digest<R: RangeBounds<K>>(&self, range: R) -> RangeDigest {
+        self.inner.range_aggregate(range)
+    }
+
+    // remoteに範囲のdigestを尋ね，食い違う範囲をこちらのkeyの中央で2つに割って絞り込む．
+    // こちらのentryがleaf個以下になった範囲を，keyの順に返す．食い違いがd個なら，尋ねるのはO(d log n)回．
+    // remoteは相手のrange_digestを呼ぶだけでよく，通信はその中で行う．
+    pub fn diverging_ranges(
+        &self,
+        mut remote: impl FnMut(&DigestRange<K>) -> RangeDigest,
+        leaf: usize,
+    ) -> Vec<DigestRange<K>>
+    where
+        K: Clone,
+    {
+        let list = &self.inner.list;
+        let mut diverging = Vec::new();
+        let mut pending = vec![(Bound::Unbounded, Bound::Unbounded)];
+        while let Some(range) = pending.pop() {
+            let local = self.range_digest(range.clone());
+            if local == remote(&range) {
+                continue;
+            }
+            if local.count <= leaf.max(1) {
+                diverging.push(range);
+                continue;
+            }
+
+            let before = list.rank_by(|k| match &range.0 {
+                Bound::Included(start) => k < start,
+                Bound::Excluded(start) => k <= start,
+                Bound::Unbounded => false,
+            });
+            let Some(middle) = list.node_at(before + local.count / 2 + 1) else {
+                unreachable!("the range holds count entries");
+            };
+            let middle = middle.key();
+            // 後で割るほうを先に積み，前の範囲から順に調べる．
+            pending.push((Bound::Included(middle.clone()), range.1));
+            pending.push((range.0, Bound::Excluded(middle.clone())));
+        }
+        diverging
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{entry_hash, DigestRange, MerkleSkipList, RangeDigest};
+    use crate::test::Gen;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+    use std::ops::RangeBounds;
+
+    #[mockalloc::test]
+    fn range_digest() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = MerkleSkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut model = BTreeMap::new();
+        for _ in 0..500 {
+            let key = rng.gen_range(0..100);
+            match rng.gen_range(0..3) {
+                0 => {
+                    if list.insert(key, key).is_ok() {
+                        model.insert(key, key);
+                    }
+                }
+                1 => assert_eq!(list.remove(&key).ok(), model.remove_entry(&key)),
+                _ => {
+                    list.update(&key, |v| *v += 1);
+                    if let Some(v) = model.get_mut(&key) {
+                        *v += 1;
+                    }
+                }
+            }
+
+            let a = rng.gen_range(0..100);
+            let b = rng.gen_range(a..=100);
+            let expected =
+                model
+                    .range(a..b)
+                    .fold(RangeDigest::default(), |d, (k, v)| RangeDigest {
+                        hash: d.hash.wrapping_add(entry_hash(k, v)),
+                        count: d.count + 1,
+                    });
+            assert_eq!(list.range_digest(a..b), expected);
+        }
+        assert!(list.iter().eq(model.iter()));
+    }
+
+    #[mockalloc::test]
+    fn diverging_ranges() {
+        let mut left = MerkleSkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut right = MerkleSkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..4096 {
+            left.insert(key, key).unwrap();
+            right.insert(key, key).unwrap();
+        }
+        let mut asked = 0;
+        let mut ask = |range: &DigestRange<i32>| {
+            asked += 1;
+            right.range_digest(*range)
+        };
+        assert!(left.diverging_ranges(&mut ask, 8).is_empty());
+        assert_eq!(asked, 1);
+
+        right.update(&7, |v| *v += 1);
+        right.remove(&1000).unwrap();
+        left.remove(&1001).unwrap();
+        right.update(&4095, |v| *v = 0);
+        right.insert(5000, 0).unwrap();
+
+        asked = 0;
+        let ranges = left.diverging_ranges(
+            |range| {
+                asked += 1;
+                right.range_digest(*range)
+            },
+            8,
+        );
+        for key in [7, 1000, 1001, 4095, 5000] {
+            assert!(ranges.iter().any(|range| range.contains(&key)), "{key}");
+        }
+        let entries: usize = ranges.iter().map(|r| left.range_digest(*r).count).sum();
+        assert!(entries <= 40, "{entries}");
+        assert!(asked < 200, "{asked}");
+    }
+
+    // 別のbuildや32 bitのtargetでも同じ値になる．
+    #[test]
+    fn stable_entry_hash() {
+        assert_eq!(entry_hash(&1usize, &-1isize), entry_hash(&1u64, &-1i64));
+        assert_eq!(entry_hash(&7u32, &"value"), 0xf8139a9b1857b423);
+        assert_ne!(entry_hash(&7u32, &"value"), entry_hash(&7u32, &"valuf"));
+    }
+}
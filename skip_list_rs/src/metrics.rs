@@ -0,0 +1,106 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Insert,
+    Append,
+    Search,
+    Remove,
+    Truncate,
+}
+
+// listが操作のたびに呼ぶ．&selfで呼ぶので，数えるならatomicなどを使う．
+// comparisonsはその操作でkeyを比較した回数．
+pub trait Metrics: Send + Sync {
+    fn operation(&self, _op: Operation, _comparisons: usize) {}
+    // nodeを確保したときに，その大きさで呼ぶ．
+    fn allocated(&self, _bytes: usize) {}
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{Metrics, Operation, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder {
+        ops: Mutex<Vec<(Operation, usize)>>,
+        bytes: AtomicUsize,
+    }
+
+    impl Metrics for Arc<Recorder> {
+        fn operation(&self, op: Operation, comparisons: usize) {
+            self.ops.lock().unwrap().push((op, comparisons));
+        }
+
+        fn allocated(&self, bytes: usize) {
+            self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    #[mockalloc::test]
+    fn records() {
+        let recorder = Arc::new(Recorder::default());
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        list.set_metrics(recorder.clone());
+
+        for key in 0..10 {
+            list.append(key, key).unwrap();
+        }
+        list.insert(20, 20).unwrap();
+        assert!(list.insert(5, 5).is_err());
+        assert_eq!(list.search(&3), Some(&3));
+        list.remove(&3).unwrap();
+        list.pop_first();
+        list.truncate_before(&5);
+
+        let ops = std::mem::take(&mut *recorder.ops.lock().unwrap());
+        let kinds: Vec<_> = ops.iter().map(|(op, _)| *op).collect();
+        let mut expected = vec![Operation::Append; 10];
+        expected.extend([
+            Operation::Insert,
+            Operation::Insert,
+            Operation::Search,
+            Operation::Remove,
+            Operation::Remove,
+            Operation::Truncate,
+        ]);
+        assert_eq!(kinds, expected);
+        assert!(ops[..10].iter().all(|&(_, n)| n <= 1));
+        assert!(ops[10..13].iter().all(|&(_, n)| n > 0));
+
+        let allocated = recorder.bytes.load(Ordering::Relaxed);
+        assert!(allocated > 0);
+        assert!(list.take_metrics().is_some());
+        list.insert(30, 30).unwrap();
+        assert_eq!(recorder.bytes.load(Ordering::Relaxed), allocated);
+    }
+
+    #[mockalloc::test]
+    fn ascending_inserts() {
+        let recorder = Arc::new(Recorder::default());
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        list.set_metrics(recorder.clone());
+        for key in 0..1000 {
+            list.insert(key, key).unwrap();
+        }
+        // 末尾より大きいkeyは，最後のkeyと比べるだけでつなぐ．
+        let ops = std::mem::take(&mut *recorder.ops.lock().unwrap());
+        assert!(ops.iter().all(|&(op, n)| op == Operation::Insert && n <= 1));
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.insert(999, 0).is_err());
+        assert!(list.iter().map(|(k, _)| *k).eq(0..1000));
+
+        // 末尾からは比較せずに取り除く．
+        recorder.ops.lock().unwrap().clear();
+        for key in (500..1000).rev() {
+            assert_eq!(list.pop_last(), Some((key, key)));
+        }
+        let ops = std::mem::take(&mut *recorder.ops.lock().unwrap());
+        assert!(ops.iter().all(|&(op, n)| op == Operation::Remove && n == 0));
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.iter().map(|(k, _)| *k).eq(0..500));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::{vec_deque, VecDeque};
+
+use crate::{iter::Iter, Generator, RemoveError, SkipList};
+
+// 同じkeyの値は挿入順にまとめて1つのnodeに持つ．
+pub struct SkipMultiMap<K: Ord, V, G: Generator<bool>> {
+    list: SkipList<K, VecDeque<V>, G>,
+    count: usize,
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipMultiMap<K, V, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            count: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.count += 1;
+        if let Some(values) = self.list.search_mut(&key) {
+            values.push_back(value);
+            return;
+        }
+
+        let result = self.list.insert(key, VecDeque::from([value]));
+        debug_assert!(result.is_ok());
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.list.contains_key(key)
+    }
+
+    pub fn get_all(&self, key: &K) -> impl Iterator<Item = &V> {
+        self.list.search(key).into_iter().flatten()
+    }
+
+    // 最も古く挿入された値を取り除く．
+    pub fn remove_one(&mut self, key: &K) -> Option<V> {
+        let values = self.list.search_mut(key)?;
+        let value = values.pop_front();
+        if values.is_empty() {
+            let _ = self.list.remove(key);
+        }
+        self.count -= 1;
+        value
+    }
+
+    pub fn remove_all(&mut self, key: &K) -> vec_deque::IntoIter<V> {
+        let values = match self.list.remove(key) {
+            Ok((_, values)) => values,
+            Err(RemoveError::NotFound) => VecDeque::new(),
+        };
+        self.count -= values.len();
+        values.into_iter()
+    }
+
+    pub fn iter(&self) -> MultiIter<'_, K, V> {
+        MultiIter {
+            iter: self.list.iter(),
+            current: None,
+        }
+    }
+}
+
+pub struct MultiIter<'a, K: Ord + 'a, V: 'a> {
+    iter: Iter<'a, K, VecDeque<V>>,
+    current: Option<(&'a K, vec_deque::Iter<'a, V>)>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for MultiIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, values)) = &mut self.current {
+                if let Some(value) = values.next() {
+                    return Some((key, value));
+                }
+            }
+
+            let (key, values) = self.iter.next()?;
+            self.current = Some((key, values.iter()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipMultiMap;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn duplicates() {
+        let mut map = SkipMultiMap::new(Gen::standard(SmallRng::from_entropy()));
+
+        map.insert(2, "b");
+        map.insert(1, "a");
+        map.insert(2, "c");
+        map.insert(3, "d");
+        map.insert(2, "e");
+
+        assert_eq!(map.count(), 5);
+        assert_eq!(map.key_count(), 3);
+        assert!(map.get_all(&2).copied().eq(["b", "c", "e"]));
+        assert!(map.get_all(&4).next().is_none());
+        assert!(map.iter().map(|(k, v)| (*k, *v)).eq([
+            (1, "a"),
+            (2, "b"),
+            (2, "c"),
+            (2, "e"),
+            (3, "d")
+        ]));
+
+        assert_eq!(map.remove_one(&2), Some("b"));
+        assert_eq!(map.remove_one(&1), Some("a"));
+        assert_eq!(map.remove_one(&1), None);
+        assert!(!map.contains_key(&1));
+        assert_eq!(map.count(), 3);
+
+        assert!(map.remove_all(&2).eq(["c", "e"]));
+        assert!(map.remove_all(&2).next().is_none());
+        assert_eq!(map.count(), 1);
+        assert_eq!(map.key_count(), 1);
+    }
+}
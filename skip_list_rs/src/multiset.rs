@@ -0,0 +1,121 @@
+use crate::{iter::Iter, Generator, RemoveError, SkipList};
+
+// 各要素の個数を値として持ち，0になったnodeは取り除く．
+pub struct SkipMultiSet<T: Ord, G: Generator<bool>> {
+    list: SkipList<T, usize, G>,
+    count: usize,
+}
+
+impl<T: Ord, G: Generator<bool>> SkipMultiSet<T, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            count: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.list.count()
+    }
+
+    // 挿入後の個数を返す．
+    pub fn insert(&mut self, value: T) -> usize {
+        self.count += 1;
+        if let Some(count) = self.list.search_mut(&value) {
+            *count += 1;
+            return *count;
+        }
+
+        let result = self.list.insert(value, 1);
+        debug_assert!(result.is_ok());
+        1
+    }
+
+    pub fn count_of(&self, value: &T) -> usize {
+        self.list.search(value).copied().unwrap_or(0)
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.list.contains_key(value)
+    }
+
+    // 1つ取り除き，残りの個数を返す．
+    pub fn remove(&mut self, value: &T) -> Option<usize> {
+        let count = self.list.search_mut(value)?;
+        *count -= 1;
+        let remaining = *count;
+        if remaining == 0 {
+            let _ = self.list.remove(value);
+        }
+        self.count -= 1;
+        Some(remaining)
+    }
+
+    // すべて取り除き，取り除いた個数を返す．
+    pub fn remove_all(&mut self, value: &T) -> usize {
+        let removed = match self.list.remove(value) {
+            Ok((_, count)) => count,
+            Err(RemoveError::NotFound) => 0,
+        };
+        self.count -= removed;
+        removed
+    }
+
+    pub fn iter(&self) -> MultiSetIter<'_, T> {
+        MultiSetIter {
+            iter: self.list.iter(),
+        }
+    }
+}
+
+pub struct MultiSetIter<'a, T: Ord + 'a> {
+    iter: Iter<'a, T, usize>,
+}
+
+impl<'a, T: Ord + 'a> Iterator for MultiSetIter<'a, T> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(value, count)| (value, *count))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipMultiSet;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn counts() {
+        let mut set = SkipMultiSet::new(Gen::standard(SmallRng::from_entropy()));
+
+        assert_eq!(set.insert("b"), 1);
+        assert_eq!(set.insert("a"), 1);
+        assert_eq!(set.insert("b"), 2);
+        assert_eq!(set.insert("c"), 1);
+        assert_eq!(set.insert("b"), 3);
+
+        assert_eq!(set.count(), 5);
+        assert_eq!(set.key_count(), 3);
+        assert_eq!(set.count_of(&"b"), 3);
+        assert_eq!(set.count_of(&"d"), 0);
+        assert!(set
+            .iter()
+            .eq([("a", 1), ("b", 3), ("c", 1)].iter().map(|(v, c)| (v, *c))));
+
+        assert_eq!(set.remove(&"b"), Some(2));
+        assert_eq!(set.remove(&"a"), Some(0));
+        assert_eq!(set.remove(&"a"), None);
+        assert!(!set.contains(&"a"));
+        assert_eq!(set.remove_all(&"b"), 2);
+        assert_eq!(set.remove_all(&"b"), 0);
+        assert_eq!(set.count(), 1);
+        assert_eq!(set.key_count(), 1);
+    }
+}
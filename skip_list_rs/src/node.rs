@@ -1,168 +1,278 @@
-use std::{marker::PhantomData, ptr::NonNull};
+use std::{alloc::Layout, ptr::NonNull};
 
+// nodeへのpointerは常にallocの返したpointerから作り，headerのfieldやnextsへはそこからprojectionで辿る．
 pub struct MaybeNode<K: Ord, V> {
-    ptr: *mut u8,
-    marker: PhantomData<(K, V)>,
+    ptr: Option<NonNull<Header<K, V>>>,
 }
 
 impl<K: Ord, V> Clone for MaybeNode<K, V> {
     fn clone(&self) -> Self {
-        Self {
-            ptr: self.ptr.clone(),
-            marker: PhantomData,
-        }
+        *self
     }
 }
 
 impl<K: Ord, V> Copy for MaybeNode<K, V> {}
 
+impl<K: Ord, V> PartialEq for MaybeNode<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<K: Ord, V> Eq for MaybeNode<K, V> {}
+
 impl<K: Ord, V> MaybeNode<K, V> {
     pub fn null() -> Self {
-        Self {
-            ptr: std::ptr::null_mut(),
-            marker: PhantomData,
-        }
+        Self { ptr: None }
+    }
+
+    pub fn is_null(self) -> bool {
+        self.ptr.is_none()
     }
 
     pub fn take(self) -> Option<Node<K, V>> {
-        if self.ptr.is_null() {
-            return None;
-        }
+        Some(Node { ptr: self.ptr? })
+    }
+}
+// nextまでにlevel 0で何歩進むか(span)を併せて持つ．nextがnullのときspanは0．
+pub struct Link<K: Ord, V> {
+    pub next: MaybeNode<K, V>,
+    pub span: usize,
+}
 
-        Some(Node {
-            ptr: unsafe { NonNull::new_unchecked(self.ptr) },
-            marker: PhantomData,
-        })
+impl<K: Ord, V> Clone for Link<K, V> {
+    fn clone(&self) -> Self {
+        *self
     }
 }
-// key: K + value: V + level: usize + nexts: [MaybeNode<K, V>]
+
+impl<K: Ord, V> Copy for Link<K, V> {}
+
+impl<K: Ord, V> Link<K, V> {
+    pub fn null() -> Self {
+        Self {
+            next: MaybeNode::null(),
+            span: 0,
+        }
+    }
+}
+
+// Header<K, V> + nexts: [Link<K, V>]
+// key，value，levelの配置はrepr(C)の構造体に任せ，nextsはその後ろにLayout::extendで並べる．
+#[repr(C)]
+struct Header<K, V> {
+    key: K,
+    value: V,
+    level: usize,
+}
+
 pub struct Node<K: Ord, V> {
-    ptr: NonNull<u8>,
-    marker: PhantomData<(K, V)>,
+    ptr: NonNull<Header<K, V>>,
 }
 
 impl<K: Ord, V> Clone for Node<K, V> {
     fn clone(&self) -> Self {
-        Self {
-            ptr: self.ptr.clone(),
-            marker: PhantomData,
-        }
+        *self
     }
 }
 
 impl<K: Ord, V> Copy for Node<K, V> {}
 
 impl<K: Ord, V> Node<K, V> {
-    fn offset_of_value() -> usize {
-        std::mem::size_of::<K>()
-    }
-    fn offset_of_level() -> usize {
-        Self::offset_of_value() + std::mem::size_of::<V>()
-    }
+    // nextsのoffsetはlevelに依存しないので，level 0のlayoutから求める．
     fn offset_of_nexts() -> usize {
-        Self::offset_of_level() + std::mem::size_of::<usize>()
+        Self::calc_layout_and_offset(0).1
     }
 
-    fn calc_layout_and_offset(level: usize) -> (std::alloc::Layout, usize, usize, usize, usize) {
-        let key_layout = std::alloc::Layout::new::<K>();
-        let value_layout = std::alloc::Layout::new::<V>();
-        let level_layout = std::alloc::Layout::new::<usize>();
-        let nexts_layout = std::alloc::Layout::array::<Self>(level).unwrap();
-        let (layout, value_offset) = key_layout.extend(value_layout).unwrap();
-        let (layout, level_offset) = layout.extend(level_layout).unwrap();
-        let (layout, nexts_offset) = layout.extend(nexts_layout).unwrap();
-        (layout, 0, value_offset, level_offset, nexts_offset)
+    // headerは常にlevelを含むので，KとVがともにZSTでもlayoutの大きさは0にならない．
+    // std::alloc::allocは大きさ0のlayoutを受け付けないため，これに頼っている．
+    fn calc_layout_and_offset(level: usize) -> (Layout, usize) {
+        let header_layout = Layout::new::<Header<K, V>>();
+        const { assert!(std::mem::size_of::<Header<K, V>>() > 0) };
+        let nexts_layout = Layout::array::<Link<K, V>>(level).unwrap();
+        let (layout, nexts_offset) = header_layout.extend(nexts_layout).unwrap();
+        (layout.pad_to_align(), nexts_offset)
     }
 
     pub fn new(key: K, value: V, level: usize) -> Self {
-        let (layout, key_offset, value_offset, level_offset, nexts_offset) =
-            Self::calc_layout_and_offset(level);
+        match Self::try_new(key, value, level) {
+            Ok(node) => node,
+            Err(_) => std::alloc::handle_alloc_error(Self::calc_layout_and_offset(level).0),
+        }
+    }
 
-        let ptr = unsafe { std::alloc::alloc(layout) };
+    // 確保に失敗したときはkeyとvalueを返す．
+    pub fn try_new(key: K, value: V, level: usize) -> Result<Self, (K, V)> {
+        let (layout, _) = Self::calc_layout_and_offset(level);
 
+        let Some(ptr) = NonNull::new(unsafe { std::alloc::alloc(layout) }) else {
+            return Err((key, value));
+        };
+
+        let node = Self { ptr: ptr.cast() };
         unsafe {
-            ptr.add(key_offset).cast::<K>().write(key);
-            ptr.add(value_offset).cast::<V>().write(value);
-            ptr.add(level_offset).cast::<usize>().write(level);
-            let ptr = ptr.add(nexts_offset).cast::<MaybeNode<K, V>>();
+            node.ptr.write(Header { key, value, level });
+            let nexts = node.nexts_ptr();
             for idx in 0..level {
-                ptr.add(idx).write(MaybeNode::null())
+                nexts.add(idx).write(Link::null())
             }
         }
 
-        Self {
-            ptr: unsafe { NonNull::new_unchecked(ptr) },
-            marker: PhantomData,
-        }
+        Ok(node)
+    }
+
+    fn nexts_ptr(self) -> NonNull<Link<K, V>> {
+        unsafe { self.ptr.byte_add(Self::offset_of_nexts()).cast() }
     }
 
     pub fn value_ptr(self) -> *mut V {
-        unsafe { self.ptr.as_ptr().add(Self::offset_of_value()).cast::<V>() }
+        unsafe { &raw mut (*self.ptr.as_ptr()).value }
     }
 
     pub fn value<'a>(self) -> &'a V {
-        unsafe { self.value_ptr().as_ref().unwrap() }
+        unsafe { &*self.value_ptr() }
     }
     pub fn value_mut<'a>(self) -> &'a mut V {
-        unsafe { self.value_ptr().as_mut().unwrap() }
+        unsafe { &mut *self.value_ptr() }
     }
 
     pub fn key_ptr(self) -> *mut K {
-        self.ptr.as_ptr().cast::<K>()
+        unsafe { &raw mut (*self.ptr.as_ptr()).key }
     }
 
     pub fn key<'a>(self) -> &'a K {
-        unsafe { self.key_ptr().as_ref().unwrap() }
-    }
-    pub fn key_mut<'a>(self) -> &'a mut K {
-        unsafe { self.key_ptr().as_mut().unwrap() }
+        unsafe { &*self.key_ptr() }
     }
 
     pub fn level(self) -> usize {
-        unsafe {
-            self.ptr
-                .as_ptr()
-                .add(Self::offset_of_level())
-                .cast::<usize>()
-                .read()
-        }
+        unsafe { (&raw const (*self.ptr.as_ptr()).level).read() }
     }
 
-    pub fn nexts<'a>(self) -> &'a [MaybeNode<K, V>] {
-        unsafe {
-            let ptr = self.ptr.as_ptr().add(Self::offset_of_nexts()).cast();
-            let len = self.level();
-            std::slice::from_raw_parts(ptr, len)
-        }
+    pub fn nexts<'a>(self) -> &'a [Link<K, V>] {
+        unsafe { NonNull::slice_from_raw_parts(self.nexts_ptr(), self.level()).as_ref() }
     }
 
-    pub fn nexts_mut<'a>(self) -> &'a mut [MaybeNode<K, V>] {
-        unsafe {
-            let ptr = self.ptr.as_ptr().add(Self::offset_of_nexts()).cast();
-            let len = self.level();
-            std::slice::from_raw_parts_mut(ptr, len)
-        }
+    pub fn nexts_mut<'a>(self) -> &'a mut [Link<K, V>] {
+        unsafe { NonNull::slice_from_raw_parts(self.nexts_ptr(), self.level()).as_mut() }
+    }
+
+    pub fn allocated_size(self) -> usize {
+        Self::size_of_level(self.level())
+    }
+
+    pub fn size_of_level(level: usize) -> usize {
+        Self::calc_layout_and_offset(level).0.size()
     }
 
     pub fn dispose(self) -> (K, V) {
-        let ptr: *mut K = self.key_mut();
-        let key = unsafe { ptr.read() };
-        let ptr: *mut V = self.value_mut();
-        let val = unsafe { ptr.read() };
-        let level = self.level();
+        let Header { key, value, level } = unsafe { self.ptr.read() };
 
-        let (layout, _, _, _, _) = Self::calc_layout_and_offset(level);
-        unsafe { std::alloc::dealloc(self.ptr.as_ptr(), layout) };
+        let (layout, _) = Self::calc_layout_and_offset(level);
+        unsafe { std::alloc::dealloc(self.ptr.cast().as_ptr(), layout) };
 
-        (key, val)
+        (key, value)
     }
 }
 
-impl<K: Ord, V> Into<MaybeNode<K, V>> for Node<K, V> {
-    fn into(self) -> MaybeNode<K, V> {
+// まだlistにつながっていないnodeを，panicで巻き戻されたときに解放する．
+pub struct Unlinked<K: Ord, V>(pub Node<K, V>);
+
+impl<K: Ord, V> Unlinked<K, V> {
+    pub fn release(self) -> Node<K, V> {
+        let node = self.0;
+        std::mem::forget(self);
+        node
+    }
+}
+
+impl<K: Ord, V> Drop for Unlinked<K, V> {
+    fn drop(&mut self) {
+        self.0.dispose();
+    }
+}
+
+// level 0の鎖をheadから辿ってlen個のnodeを解放する．
+// 要素のdropがpanicしても，巻き戻しの中で残りを解放し続ける．
+pub struct Chain<K: Ord, V> {
+    pub head: MaybeNode<K, V>,
+    pub len: usize,
+}
+
+impl<K: Ord, V> Drop for Chain<K, V> {
+    fn drop(&mut self) {
+        while self.len > 0 {
+            let Some(node) = self.head.take() else {
+                break;
+            };
+            self.head = node.nexts()[0].next;
+            self.len -= 1;
+
+            let rest = Chain {
+                head: self.head,
+                len: self.len,
+            };
+            node.dispose();
+            std::mem::forget(rest);
+        }
+    }
+}
+
+impl<K: Ord, V> From<Node<K, V>> for MaybeNode<K, V> {
+    fn from(node: Node<K, V>) -> Self {
         MaybeNode {
-            ptr: self.ptr.as_ptr(),
-            marker: PhantomData,
+            ptr: Some(node.ptr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Node;
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[repr(align(64))]
+    struct Align64(u8);
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[repr(align(32))]
+    struct Align32(u16);
+
+    fn round_trip<K: Ord + std::fmt::Debug, V: PartialEq + std::fmt::Debug>(
+        make: impl Fn() -> (K, V),
+    ) {
+        for level in [1, 2, 7] {
+            let (key, value) = make();
+            let node = Node::new(key, value, level);
+
+            assert_eq!(node.key_ptr() as usize % std::mem::align_of::<K>(), 0);
+            assert_eq!(node.value_ptr() as usize % std::mem::align_of::<V>(), 0);
+            assert_eq!(node.level(), level);
+            assert!(node.nexts().iter().all(|link| link.next.is_null()));
+
+            let (key, value) = make();
+            assert_eq!(node.key(), &key);
+            assert_eq!(node.value(), &value);
+            assert_eq!(node.dispose(), (key, value));
         }
     }
+
+    #[test]
+    fn alloc_failure() {
+        // 確保できない大きさのtowerを要求する．
+        let level = 1 << (usize::BITS - 8);
+        assert!(Node::try_new(1u8, 2u8, level).is_err_and(|e| e == (1, 2)));
+    }
+
+    #[mockalloc::test]
+    fn exotic_alignments() {
+        round_trip(|| (1u8, Align64(2)));
+        round_trip(|| (Align64(1), 2u8));
+        round_trip(|| (Align32(1), Align64(2)));
+        round_trip(|| ((), Align32(3)));
+        round_trip(|| ((), ()));
+        round_trip(|| (Align32(3), ()));
+        round_trip(|| (1u8, ()));
+        round_trip(|| (1u64, [7u8; 3]));
+        round_trip(|| (String::from("key"), vec![1u128]));
+    }
 }
@@ -0,0 +1,459 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::{
+    node::{Link, MaybeNode, Node},
+    Change, Diff, Generator, SkipList,
+};
+
+// AddedとModifiedは，keyがあればvalueを置き換え，なければ挿入する．Removedはkeyがなければ何もしない．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Patch<K, V> {
+    Added(K, V),
+    Removed(K),
+    Modified(K, V),
+}
+
+impl<K: Clone, V: Clone> From<Diff<'_, K, V>> for Patch<K, V> {
+    fn from(diff: Diff<'_, K, V>) -> Self {
+        match diff {
+            Diff::Added(key, value) => Patch::Added(key.clone(), value.clone()),
+            Diff::Removed(key, _) => Patch::Removed(key.clone()),
+            Diff::Modified(key, _, value) => Patch::Modified(key.clone(), value.clone()),
+        }
+    }
+}
+
+// 各levelの直前のnode(nullはhead)とその位置．
+pub(crate) type Preds<K, V> = Vec<(MaybeNode<K, V>, usize)>;
+
+pub(crate) fn links<K: Ord, V>(head: &[Link<K, V>], pred: MaybeNode<K, V>) -> &[Link<K, V>] {
+    match pred.take() {
+        Some(node) => node.nexts(),
+        None => head,
+    }
+}
+
+fn links_mut<K: Ord, V>(head: &mut [Link<K, V>], pred: MaybeNode<K, V>) -> &mut [Link<K, V>] {
+    match pred.take() {
+        Some(node) => node.nexts_mut(),
+        None => head,
+    }
+}
+
+// predsの直後のnodeを残し，predsをその後ろへ進める．
+pub(crate) fn step<K: Ord, V>(preds: &mut Preds<K, V>, node: Node<K, V>) {
+    let pos = preds[0].1 + 1;
+    for pred in &mut preds[..node.level()] {
+        *pred = (node.into(), pos);
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // changesはkeyの昇順に並んでいること．
+    // 各levelの直前のnodeを持ち回り，次のkeyへはheadからではなくそこから進める．
+    pub fn apply_patch(&mut self, changes: impl IntoIterator<Item = Patch<K, V>>) {
+        let mut preds = vec![(MaybeNode::null(), 0); self.nodes.len()];
+
+        for change in changes {
+            let (Patch::Added(key, _) | Patch::Removed(key) | Patch::Modified(key, _)) = &change;
+            if let Some(pred) = preds[0].0.take() {
+                assert!(pred.key() < key, "changes must be sorted by key");
+            }
+            let found = self.advance(&mut preds, key, None);
+            match (change, found) {
+                (Patch::Added(_, value) | Patch::Modified(_, value), Some(node)) => {
+                    self.rehash(node, |v| *v = value);
+                    self.notify(Change::Updated(node.key()));
+                }
+                (Patch::Added(key, value) | Patch::Modified(key, value), None) => {
+                    let node = self.alloc(key, value);
+                    self.splice(&mut preds, node);
+                    self.hash_in(node);
+                    self.notify(Change::Inserted(node.key()));
+                }
+                (Patch::Removed(_), Some(node)) => {
+                    self.unsplice(&preds, node);
+                    self.hash_out(node);
+                    self.notify(Change::Removed(node.key()));
+                    node.dispose();
+                }
+                (Patch::Removed(_), None) => {}
+            }
+        }
+        self.debug_check(true);
+    }
+
+    // otherのentryを1つの掃引で取り込む．両方にあるkeyは，f(key, selfのvalue, otherのvalue)に置き換える．
+    pub fn merge_with<H: Generator<bool>>(
+        &mut self,
+        other: SkipList<K, V, H>,
+        mut f: impl FnMut(&K, &V, V) -> V,
+    ) {
+        let mut preds = vec![(MaybeNode::null(), 0); self.nodes.len()];
+
+        for (key, theirs) in other {
+            match self.advance(&mut preds, &key, None) {
+                Some(node) => {
+                    let merged = f(node.key(), node.value(), theirs);
+                    self.rehash(node, |v| *v = merged);
+                    self.notify(Change::Updated(node.key()));
+                }
+                None => {
+                    let node = self.alloc(key, theirs);
+                    self.splice(&mut preds, node);
+                    self.hash_in(node);
+                    self.notify(Change::Inserted(node.key()));
+                }
+            }
+        }
+        self.debug_check(true);
+    }
+
+    // keysは昇順に並んでいること．取り除いた数を返す．
+    pub fn remove_all<'k>(&mut self, keys: impl IntoIterator<Item = &'k K>) -> usize
+    where
+        K: 'k,
+    {
+        let mut preds = vec![(MaybeNode::null(), 0); self.nodes.len()];
+        let mut removed = 0;
+
+        for key in keys {
+            if let Some(pred) = preds[0].0.take() {
+                assert!(pred.key() < key, "keys must be sorted");
+            }
+            if let Some(node) = self.advance(&mut preds, key, None) {
+                self.unsplice(&preds, node);
+                self.hash_out(node);
+                self.notify(Change::Removed(node.key()));
+                node.dispose();
+                removed += 1;
+            }
+        }
+        self.debug_check(false);
+        removed
+    }
+
+    // boundsの中のentryのうち，predがfalseを返したものを取り除く．範囲の外は辿らない．
+    // predでのvalueの書き換えはsubscriberに知らせない．取り除いた数を返す．
+    pub fn retain_range(
+        &mut self,
+        bounds: impl RangeBounds<K>,
+        mut pred: impl FnMut(&K, &mut V) -> bool,
+    ) -> usize {
+        let mut preds = vec![(MaybeNode::null(), 0); self.nodes.len()];
+        if let Bound::Included(start) | Bound::Excluded(start) = bounds.start_bound() {
+            if let Some(node) = self.advance(&mut preds, start, None) {
+                if matches!(bounds.start_bound(), Bound::Excluded(_)) {
+                    step(&mut preds, node);
+                }
+            }
+        }
+
+        let mut removed = 0;
+        while let Some(node) = links(&self.nodes, preds[0].0)[0].next.take() {
+            let within = match bounds.end_bound() {
+                Bound::Included(end) => node.key() <= end,
+                Bound::Excluded(end) => node.key() < end,
+                Bound::Unbounded => true,
+            };
+            if !within {
+                break;
+            }
+            if self.rehash(node, |v| pred(node.key(), v)) {
+                step(&mut preds, node);
+                continue;
+            }
+            self.unsplice(&preds, node);
+            self.hash_out(node);
+            self.notify(Change::Removed(node.key()));
+            node.dispose();
+            removed += 1;
+        }
+        self.debug_check(false);
+        removed
+    }
+
+    // nextがkeyより前にあるlevelは下から連続しているので，下から上ってその高さを求め，そこから下りる．
+    // keyまでの距離をdとしてO(log d)で進み，keyのnodeがあれば返す．comparisonsがあれば比較した回数を足す．
+    pub(crate) fn advance(
+        &self,
+        preds: &mut Preds<K, V>,
+        key: &K,
+        comparisons: Option<&mut usize>,
+    ) -> Option<Node<K, V>> {
+        let mut compared = 0;
+        let mut before = |next: MaybeNode<K, V>| {
+            next.take().is_some_and(|next| {
+                compared += 1;
+                next.key() < key
+            })
+        };
+        let mut height = 0;
+        while height < preds.len() && before(links(&self.nodes, preds[height].0)[height].next) {
+            height += 1;
+        }
+        if let Some(top) = height.checked_sub(1) {
+            let (mut pred, mut rank) = preds[top];
+            for level in (0..height).rev() {
+                // 下のlevelの直前のnodeのほうが先にあることがある．
+                if preds[level].1 > rank {
+                    (pred, rank) = preds[level];
+                }
+                loop {
+                    let link = links(&self.nodes, pred)[level];
+                    if !before(link.next) {
+                        break;
+                    }
+                    rank += link.span;
+                    pred = link.next;
+                }
+                preds[level] = (pred, rank);
+            }
+        }
+
+        let next = links(&self.nodes, preds[0].0)[0].next.take();
+        let found = next.filter(|next| {
+            compared += 1;
+            next.key() == key
+        });
+        if let Some(comparisons) = comparisons {
+            *comparisons += compared;
+        }
+        found
+    }
+
+    // predsの直後にnodeをつなぎ，以後のkeyのためにpredsをnodeへ進める．
+    pub(crate) fn splice(&mut self, preds: &mut Preds<K, V>, node: Node<K, V>) {
+        let pos = preds[0].1 + 1;
+        self.count += 1;
+
+        for (level, pred) in preds.iter_mut().enumerate() {
+            let (from, rank) = *pred;
+            let link = &mut links_mut(&mut self.nodes, from)[level];
+            if level >= node.level() {
+                if link.next.is_null() {
+                    self.tails[level].span += 1;
+                } else {
+                    link.span += 1;
+                }
+                continue;
+            }
+
+            if link.next.is_null() {
+                self.tails[level] = Link {
+                    next: node.into(),
+                    span: self.count - pos,
+                };
+            }
+            let node_link = &mut node.nexts_mut()[level];
+            node_link.next = link.next;
+            node_link.span = if link.next.is_null() {
+                0
+            } else {
+                rank + link.span + 1 - pos
+            };
+            link.next = node.into();
+            link.span = pos - rank;
+            *pred = (node.into(), pos);
+        }
+
+        for _ in self.nodes.len()..node.level() {
+            self.nodes.push(Link {
+                next: node.into(),
+                span: pos,
+            });
+            self.tails.push(Link {
+                next: node.into(),
+                span: self.count - pos,
+            });
+            preds.push((node.into(), pos));
+        }
+    }
+
+    // predsの直後のremovedを外す．predsはそのまま次のkeyの直前として使える．
+    fn unsplice(&mut self, preds: &Preds<K, V>, removed: Node<K, V>) {
+        self.count -= 1;
+
+        for (level, &(from, rank)) in preds.iter().enumerate() {
+            let link = &mut links_mut(&mut self.nodes, from)[level];
+            if level >= removed.level() {
+                if link.next.is_null() {
+                    self.tails[level].span -= 1;
+                } else {
+                    link.span -= 1;
+                }
+                continue;
+            }
+
+            let removed_link = removed.nexts()[level];
+            if removed_link.next.is_null() {
+                self.tails[level] = Link {
+                    next: from,
+                    span: self.count - rank,
+                };
+            }
+            link.next = removed_link.next;
+            link.span = if link.next.is_null() {
+                0
+            } else {
+                link.span + removed_link.span - 1
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::{Patch, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[mockalloc::test]
+    fn catch_up() {
+        let mut rng = SmallRng::from_entropy();
+        for _ in 0..20 {
+            let mut old = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            let mut replica = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            let mut new = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            for key in 0..200 {
+                if rng.gen_bool(0.5) {
+                    old.insert(key, key).unwrap();
+                    replica.insert(key, key).unwrap();
+                }
+                if rng.gen_bool(0.5) {
+                    new.insert(key, rng.gen_range(key..key + 2)).unwrap();
+                }
+            }
+
+            replica.apply_patch(old.diff(&new).map(Patch::from));
+            check_spans(&replica);
+            assert_eq!(replica.check_invariants(), Ok(()));
+            assert!(replica.iter().eq(new.iter()));
+            assert_eq!(replica.count(), new.count());
+        }
+    }
+
+    #[mockalloc::test]
+    fn upsert() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in [1, 3, 5] {
+            list.insert(key, 0).unwrap();
+        }
+
+        list.apply_patch([
+            Patch::Added(0, 1),
+            Patch::Added(1, 1),
+            Patch::Removed(2),
+            Patch::Removed(3),
+            Patch::Modified(4, 1),
+            Patch::Modified(5, 1),
+        ]);
+        check_spans(&list);
+        assert!(list
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq([(0, 1), (1, 1), (4, 1), (5, 1)]));
+    }
+
+    #[mockalloc::test]
+    fn retain_range() {
+        use std::ops::Bound;
+        let mut rng = SmallRng::from_entropy();
+        for _ in 0..20 {
+            let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            let mut model = BTreeMap::new();
+            for key in 0..300 {
+                if rng.gen_bool(0.7) {
+                    list.insert(key, key).unwrap();
+                    model.insert(key, key);
+                }
+            }
+            let start = rng.gen_range(0..300);
+            let end = rng.gen_range(start..300);
+            let bounds = (Bound::Excluded(start), Bound::Included(end));
+
+            let mut visited = Vec::new();
+            let removed = list.retain_range(bounds, |k, v| {
+                visited.push(*k);
+                *v += 1;
+                k % 3 != 0
+            });
+            let before = model.len();
+            model.retain(|k, v| {
+                if !(start + 1..=end).contains(k) {
+                    return true;
+                }
+                *v += 1;
+                k % 3 != 0
+            });
+            assert_eq!(removed, before - model.len());
+            assert!(visited.iter().all(|k| *k > start && *k <= end));
+            check_spans(&list);
+            assert_eq!(list.check_invariants(), Ok(()));
+            assert!(list.iter().eq(model.iter()));
+        }
+
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..10 {
+            list.insert(key, ()).unwrap();
+        }
+        assert_eq!(list.retain_range(.., |k, _| k % 2 == 0), 5);
+        assert_eq!(list.retain_range(4.., |_, _| false), 3);
+        assert!(list.iter().map(|(k, _)| *k).eq([0, 2]));
+    }
+
+    #[mockalloc::test]
+    fn merge_with() {
+        let mut rng = SmallRng::from_entropy();
+        let mut mine = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut theirs = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut expected = BTreeMap::new();
+        for key in 0..300 {
+            if rng.gen_bool(0.5) {
+                mine.insert(key, 1).unwrap();
+                expected.insert(key, 1);
+            }
+            if rng.gen_bool(0.5) {
+                theirs.insert(key, 10).unwrap();
+                *expected.entry(key).or_default() += 10;
+            }
+        }
+
+        mine.merge_with(theirs, |_, mine, theirs| mine + theirs);
+        check_spans(&mine);
+        assert_eq!(mine.check_invariants(), Ok(()));
+        assert!(mine.iter().eq(expected.iter()));
+    }
+
+    #[mockalloc::test]
+    fn remove_all() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut other = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let mut expected = BTreeMap::new();
+        for key in 0..300 {
+            list.insert(key, key).unwrap();
+            if rng.gen_bool(0.3) {
+                other.insert(key, ()).unwrap();
+            } else {
+                expected.insert(key, key);
+            }
+        }
+        other.insert(1000, ()).unwrap();
+
+        let removed = list.remove_all(other.iter().map(|(k, _)| k));
+        assert_eq!(removed, other.count() - 1);
+        check_spans(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert!(list.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    #[should_panic(expected = "changes must be sorted by key")]
+    fn unsorted() {
+        let mut list = SkipList::new(|| false);
+        list.apply_patch([Patch::Added(2, ()), Patch::Added(1, ())]);
+    }
+}
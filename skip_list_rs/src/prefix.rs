@@ -0,0 +1,134 @@
+use std::cmp::Ordering;
+
+use crate::{iter::Range, Generator, SkipList};
+
+// Kの先頭のいくつかのfieldからなる部分key．先頭のfieldが等しいkeyは，Kの順序で連続して並ぶこと．
+// #[derive(SkipKey)]が，先頭のfieldの型のtupleに実装する．
+pub trait KeyPrefix<K> {
+    // keyの先頭のfieldをselfと比べる．
+    fn cmp_key(&self, key: &K) -> Ordering;
+}
+
+// tupleのkeyに，先頭の要素からなる短いtupleをprefixとして使えるようにする．
+macro_rules! tuple_prefix {
+    ($(($($p:ident $i:tt),+) of ($($k:ident),+);)*) => {$(
+        impl<$($k: Ord),+> KeyPrefix<($($k,)+)> for ($($p,)+) {
+            fn cmp_key(&self, key: &($($k,)+)) -> Ordering {
+                $(
+                    match key.$i.cmp(&self.$i) {
+                        Ordering::Equal => {}
+                        ord => return ord,
+                    }
+                )+
+                Ordering::Equal
+            }
+        }
+    )*};
+}
+
+tuple_prefix! {
+    (A 0) of (A, B);
+    (A 0) of (A, B, C);
+    (A 0, B 1) of (A, B, C);
+    (A 0) of (A, B, C, D);
+    (A 0, B 1) of (A, B, C, D);
+    (A 0, B 1, C 2) of (A, B, C, D);
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 残りのfieldの最小値や最大値を作らずに，先頭のfieldがprefixと等しいentryを返す．
+    pub fn range_prefix(&self, prefix: impl KeyPrefix<K>) -> Range<'_, K, V> {
+        self.range_by(
+            |k| prefix.cmp_key(k) == Ordering::Less,
+            |k| prefix.cmp_key(k) != Ordering::Greater,
+        )
+    }
+}
+
+// keyのbyte列の辞書順がKのOrdと一致すること．String，str，Vec<u8>，[u8]などはそうなっている．
+impl<K: Ord + AsRef<[u8]>, V, G: Generator<bool>> SkipList<K, V, G> {
+    // prefixで始まるkeyは連続して並ぶので，その前と後ろを比較で探す．
+    // 上端のkeyを作らないので，prefixが0xffで終わる場合や空の場合も特別扱いしない．
+    pub fn prefix_range(&self, prefix: impl AsRef<[u8]>) -> Range<'_, K, V> {
+        let prefix = prefix.as_ref();
+        self.range_by(
+            |k| k.as_ref() < prefix,
+            |k| k.as_ref() < prefix || k.as_ref().starts_with(prefix),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{iter::Range, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn strings() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in ["fo", "foo", "foo/a", "foo/b", "fooz", "fop", "g", ""] {
+            list.insert(key.to_string(), ()).unwrap();
+        }
+        let keys = |prefix: &str| {
+            list.prefix_range(prefix)
+                .map(|(k, _)| k.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys("foo"), ["foo", "foo/a", "foo/b", "fooz"]);
+        assert_eq!(keys("foo/"), ["foo/a", "foo/b"]);
+        assert_eq!(keys("fox"), [] as [&str; 0]);
+        assert_eq!(keys("h"), [] as [&str; 0]);
+        assert_eq!(keys("").len(), 8);
+    }
+
+    #[mockalloc::test]
+    fn maximal_bytes() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in [
+            vec![0xfe],
+            vec![0xff],
+            vec![0xff, 0xff],
+            vec![0xff, 0xff, 0],
+            vec![],
+        ] {
+            list.insert(key, ()).unwrap();
+        }
+        let keys: Vec<_> = list
+            .prefix_range([0xff, 0xff])
+            .map(|(k, _)| k.clone())
+            .collect();
+        assert_eq!(keys, [vec![0xff, 0xff], vec![0xff, 0xff, 0]]);
+        assert_eq!(list.prefix_range([0xff]).count(), 3);
+    }
+
+    #[mockalloc::test]
+    fn tuples() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for tenant in ["a", "b", "c"] {
+            for user in [i32::MIN, 0, i32::MAX] {
+                for id in 0..3u8 {
+                    list.insert((tenant, user, id), ()).unwrap();
+                }
+            }
+        }
+        let keys = |range: Range<'_, _, _>| range.map(|(k, _)| *k).collect::<Vec<_>>();
+        let b = keys(list.range_prefix(("b",)));
+        assert_eq!(b.len(), 9);
+        assert!(b.iter().all(|(tenant, _, _)| *tenant == "b"));
+        assert_eq!(
+            keys(list.range_prefix(("c", i32::MAX))),
+            [("c", i32::MAX, 0), ("c", i32::MAX, 1), ("c", i32::MAX, 2)]
+        );
+        assert!(keys(list.range_prefix(("d",))).is_empty());
+        assert!(keys(list.range_prefix(("a", 1))).is_empty());
+
+        let mut pairs = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in [(1, 'a'), (2, 'a'), (2, 'z'), (3, 'a')] {
+            pairs.insert(key, ()).unwrap();
+        }
+        let two: Vec<_> = pairs.range_prefix((2,)).map(|(k, _)| *k).collect();
+        assert_eq!(two, [(2, 'a'), (2, 'z')]);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::SkipList;
+
+// keyの数を絞り，keyとtowerの高さを任意に選んで，insert/search/removeの後も不変条件が保たれることを確かめる．
+// towerの高さはGeneratorの返すboolを任意にすることで，すべての形を調べる．
+const N: usize = 3;
+
+fn any_list(keys: &[u8; N]) -> SkipList<u8, u8, impl FnMut() -> bool> {
+    let mut list = SkipList::new(kani::any::<bool>);
+    for key in keys {
+        let _ = list.insert(*key, key.wrapping_mul(3));
+    }
+    list
+}
+
+#[kani::proof]
+#[kani::unwind(8)]
+fn insert() {
+    let keys: [u8; N] = kani::any();
+    let list = any_list(&keys);
+    assert!(list.check_invariants().is_ok());
+    assert!(list.count() <= N);
+    for key in keys {
+        assert!(list.contains_key(&key));
+    }
+}
+
+#[kani::proof]
+#[kani::unwind(8)]
+fn search() {
+    let keys: [u8; N] = kani::any();
+    let list = any_list(&keys);
+    let probe: u8 = kani::any();
+    match list.search(&probe) {
+        Some(value) => {
+            assert!(keys.contains(&probe));
+            assert!(*value == probe.wrapping_mul(3));
+        }
+        None => assert!(!keys.contains(&probe)),
+    }
+}
+
+#[kani::proof]
+#[kani::unwind(8)]
+fn remove() {
+    let keys: [u8; N] = kani::any();
+    let mut list = any_list(&keys);
+    let count = list.count();
+    let probe: u8 = kani::any();
+    let removed = list.remove(&probe);
+    assert!(removed.is_ok() == keys.contains(&probe));
+    assert!(list.count() + removed.is_ok() as usize == count);
+    assert!(!list.contains_key(&probe));
+    assert!(list.check_invariants().is_ok());
+}
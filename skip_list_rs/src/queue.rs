@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use crate::{node::Node, Generator, SkipList};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct QueueHandle(pub(crate) u64);
+
+// 同じpriorityはpushされた順に取り出されるよう，通し番号をkeyに含める．
+// handleからはnodeを直接引き，そのkeyで取り除く．
+pub struct SkipPriorityQueue<K: Ord, V, G: Generator<bool>> {
+    list: SkipList<(K, u64), V, G>,
+    handles: HashMap<u64, Node<(K, u64), V>>,
+    seq: u64,
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipPriorityQueue<K, V, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            handles: HashMap::new(),
+            seq: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn push(&mut self, priority: K, value: V) -> QueueHandle {
+        let seq = self.seq;
+        self.seq += 1;
+        self.link(priority, seq, value);
+        QueueHandle(seq)
+    }
+
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        let ((priority, _), value) = self.list.first()?;
+        Some((priority, value))
+    }
+
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        let ((priority, seq), value) = self.list.pop_first()?;
+        self.handles.remove(&seq);
+        Some((priority, value))
+    }
+
+    pub fn priority(&self, handle: QueueHandle) -> Option<&K> {
+        let node = self.handles.get(&handle.0)?;
+        Some(&node.key().0)
+    }
+
+    pub fn get(&self, handle: QueueHandle) -> Option<&V> {
+        let node = self.handles.get(&handle.0)?;
+        Some(node.value())
+    }
+
+    // 新しいpriorityで入れ直し，古いpriorityを返す．handleはそのまま使える．
+    pub fn change_priority(&mut self, handle: QueueHandle, priority: K) -> Option<K> {
+        let ((old, seq), value) = self.unlink(handle)?;
+        self.link(priority, seq, value);
+        Some(old)
+    }
+
+    pub fn remove(&mut self, handle: QueueHandle) -> Option<(K, V)> {
+        let ((priority, _), value) = self.unlink(handle)?;
+        Some((priority, value))
+    }
+
+    fn link(&mut self, priority: K, seq: u64, value: V) {
+        let inserted = self
+            .list
+            .insert_by(|next, _, key| next.cmp(key), (priority, seq), value);
+        let Ok((node, _)) = inserted else {
+            unreachable!("sequence numbers are unique");
+        };
+        self.handles.insert(seq, node);
+    }
+
+    fn unlink(&mut self, handle: QueueHandle) -> Option<((K, u64), V)> {
+        let node = self.handles.remove(&handle.0)?;
+        let key = node.key();
+        let removed = self.list.remove_by(|next, _| next.cmp(key)).ok()?;
+        Some(removed.dispose())
+    }
+}
+
+// handlesはlistが所有するnodeだけを指すので，listと同じくSend/Syncにできる．
+unsafe impl<K: Ord + Send, V: Send, G: Generator<bool> + Send> Send for SkipPriorityQueue<K, V, G> {}
+unsafe impl<K: Ord + Sync, V: Sync, G: Generator<bool> + Sync> Sync for SkipPriorityQueue<K, V, G> {}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipPriorityQueue;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn push_pop() {
+        let mut queue = SkipPriorityQueue::new(Gen::standard(SmallRng::from_entropy()));
+
+        queue.push(3, "c");
+        queue.push(1, "a");
+        queue.push(2, "b1");
+        queue.push(2, "b2");
+
+        assert_eq!(queue.count(), 4);
+        assert_eq!(queue.peek(), Some((&1, &"a")));
+        assert_eq!(queue.pop(), Some((1, "a")));
+        assert_eq!(queue.pop(), Some((2, "b1")));
+        assert_eq!(queue.pop(), Some((2, "b2")));
+        assert_eq!(queue.pop(), Some((3, "c")));
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.peek(), None);
+    }
+
+    #[mockalloc::test]
+    fn handles() {
+        let mut queue = SkipPriorityQueue::new(Gen::standard(SmallRng::from_entropy()));
+
+        let a = queue.push(10, "a");
+        let b = queue.push(20, "b");
+        let c = queue.push(30, "c");
+
+        assert_eq!(queue.change_priority(c, 5), Some(30));
+        assert_eq!(queue.priority(c), Some(&5));
+        assert_eq!(queue.get(c), Some(&"c"));
+        assert_eq!(queue.peek(), Some((&5, &"c")));
+
+        assert_eq!(queue.remove(a), Some((10, "a")));
+        assert_eq!(queue.remove(a), None);
+        assert_eq!(queue.change_priority(a, 0), None);
+
+        assert_eq!(queue.pop(), Some((5, "c")));
+        assert_eq!(queue.priority(c), None);
+        assert_eq!(queue.pop(), Some((20, "b")));
+        assert_eq!(queue.remove(b), None);
+        assert_eq!(queue.count(), 0);
+    }
+
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<SkipPriorityQueue<i32, String, fn() -> bool>>();
+    }
+}
@@ -0,0 +1,154 @@
+// 安全の条件は，docではなくRawNodeの上にまとめて書く．
+#![allow(clippy::missing_safety_doc)]
+
+use crate::{node::Node, Change, Generator, Operation, SkipList};
+
+// listの中のnodeを指すだけのhandle．借用を持たないので，listを書き換えるとhandleの指すnodeは解放されうる．
+// 参照を返すmethodはunsafeで，呼ぶ側が次のことを保証する．
+// - nodeが取り除かれておらず，listもdropされていない．
+// - 返した参照を使う間，同じentryへの他の参照とaliasしない(value_mutは他の参照と重ならない)．
+pub struct RawNode<K: Ord, V> {
+    node: Node<K, V>,
+}
+
+impl<K: Ord, V> Clone for RawNode<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: Ord, V> Copy for RawNode<K, V> {}
+
+impl<K: Ord, V> PartialEq for RawNode<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node.key_ptr() == other.node.key_ptr()
+    }
+}
+
+impl<K: Ord, V> Eq for RawNode<K, V> {}
+
+impl<K: Ord, V> RawNode<K, V> {
+    pub unsafe fn key<'a>(self) -> &'a K {
+        self.node.key()
+    }
+
+    pub unsafe fn value<'a>(self) -> &'a V {
+        self.node.value()
+    }
+
+    pub unsafe fn value_mut<'a>(self) -> &'a mut V {
+        self.node.value_mut()
+    }
+
+    // towerの高さ．levelは0からlevel() - 1まで辿れる．
+    pub unsafe fn level(self) -> usize {
+        self.node.level()
+    }
+
+    // levelでの次のnodeと，そこまでlevel 0で何歩進むか．levelがtowerの高さ以上ならpanicする．
+    pub unsafe fn next(self, level: usize) -> Option<(RawNode<K, V>, usize)> {
+        let link = self.node.nexts()[level];
+        let node = link.next.take()?;
+        Some((RawNode { node }, link.span))
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn raw_first(&self) -> Option<RawNode<K, V>> {
+        let node = self.nodes[0].next.take()?;
+        Some(RawNode { node })
+    }
+
+    // key以上の最初のnode．
+    pub fn raw_lower_bound(&self, key: &K) -> Option<RawNode<K, V>> {
+        let node = self.find_first(|k| k < key).take()?;
+        Some(RawNode { node })
+    }
+
+    // headのtowerでのlevelの次のnodeと，そこまでの歩数．
+    pub fn raw_head(&self, level: usize) -> Option<(RawNode<K, V>, usize)> {
+        let link = self.nodes.get(level)?;
+        let node = link.next.take()?;
+        Some((RawNode { node }, link.span))
+    }
+
+    // nodeをlistから外し，中身を返す．nodeはこのlistのもので，まだ外していないこと．
+    // 以後，同じnodeを指すhandleは使えない．
+    pub unsafe fn raw_detach(&mut self, node: RawNode<K, V>) -> (K, V) {
+        let key = node.node.key();
+        let mut comparisons = 0;
+        let removed = self.remove_by(|next, _| {
+            comparisons += 1;
+            next.cmp(key)
+        });
+        self.report(Operation::Remove, comparisons);
+        let Ok(removed) = removed else {
+            panic!("the detached node is not in the list")
+        };
+        debug_assert!(RawNode { node: removed } == node);
+        self.hash_out(removed);
+        self.notify(Change::Removed(removed.key()));
+        removed.dispose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn raw() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..100 {
+            list.insert(key, key * 10).unwrap();
+        }
+
+        // level 0を辿り，奇数のnodeを集めて外す．
+        let mut odd = Vec::new();
+        let mut node = list.raw_first();
+        while let Some(n) = node {
+            unsafe {
+                if n.key() % 2 == 1 {
+                    odd.push(n);
+                    *n.value_mut() += 1;
+                }
+                node = n.next(0).map(|(next, span)| {
+                    assert_eq!(span, 1);
+                    next
+                });
+            }
+        }
+        assert_eq!(odd.len(), 50);
+        assert_eq!(list.search(&7), Some(&71));
+        for n in odd {
+            let (key, value) = unsafe { list.raw_detach(n) };
+            assert_eq!(value, key * 10 + 1);
+        }
+        assert!(list.iter().map(|(k, _)| *k).eq((0..100).step_by(2)));
+        check_spans(&list);
+
+        // 上のlevelのspanを足すと位置になる．
+        // 取り除いた後は，最上段が空になっていることがある．
+        let (top, (mut node, mut pos)) = (0..list.height())
+            .rev()
+            .find_map(|level| Some((level, list.raw_head(level)?)))
+            .unwrap();
+        unsafe {
+            loop {
+                assert_eq!(*node.key(), (pos as i32 - 1) * 2);
+                assert!(node.level() > top);
+                let Some((next, span)) = node.next(top) else {
+                    break;
+                };
+                node = next;
+                pos += span;
+            }
+        }
+        assert_eq!(unsafe { *list.raw_lower_bound(&31).unwrap().key() }, 32);
+        assert!(list.raw_lower_bound(&99).is_none());
+        assert!(list.raw_head(list.height()).is_none());
+    }
+}
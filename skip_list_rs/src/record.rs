@@ -0,0 +1,224 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+};
+
+use crate::{
+    snapshot::{invalid, read_chunk, read_entry, write_chunk, write_entry},
+    Codec, Generator, InsertError, RemoveError, SkipList,
+};
+
+// 先頭にMAGIC，続けて操作ごとに種類の1 byteとkey(とvalue)を並べる．
+// 挿入にはその間に呼んだGeneratorの結果を，呼んだ回数の1 byteと，1回1 bitで詰めたbyte列で添える．
+const MAGIC: [u8; 4] = *b"SKR1";
+const INSERT: u8 = 1;
+const APPEND: u8 = 2;
+const REMOVE: u8 = 3;
+
+// Generatorの結果を控える．
+pub struct Tap<G> {
+    gen: G,
+    bits: Vec<bool>,
+}
+
+impl<G: Generator<bool>> Generator<bool> for Tap<G> {
+    fn gen(&mut self) -> bool {
+        let bit = self.gen.gen();
+        self.bits.push(bit);
+        bit
+    }
+}
+
+// 控えた結果を順に返す．尽きたらfalseを返す．
+pub struct Replayed {
+    bits: VecDeque<bool>,
+}
+
+impl Generator<bool> for Replayed {
+    fn gen(&mut self) -> bool {
+        self.bits.pop_front().unwrap_or(false)
+    }
+}
+
+// 形を変える操作とGeneratorの結果を記録する．失敗した挿入もGeneratorを呼ぶので記録する．
+// 記録をreplayに渡すと，同じ形のlistができるので，壊れたlistを再現して報告に添えられる．
+pub struct RecordingSkipList<K: Ord, V, G: Generator<bool>, C: Codec<K> + Codec<V>> {
+    list: SkipList<K, V, Tap<G>>,
+    trace: Vec<u8>,
+    codec: C,
+    buf: Vec<u8>,
+}
+
+impl<K: Ord, V, G: Generator<bool>, C: Codec<K> + Codec<V>> RecordingSkipList<K, V, G, C> {
+    pub fn new(gen: G, codec: C) -> Self {
+        Self {
+            list: SkipList::new(Tap {
+                gen,
+                bits: Vec::new(),
+            }),
+            trace: MAGIC.to_vec(),
+            codec,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn list(&self) -> &SkipList<K, V, Tap<G>> {
+        &self.list
+    }
+
+    pub fn trace(&self) -> &[u8] {
+        &self.trace
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        self.list.search(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        self.record_entry(INSERT, &key, &value);
+        let result = self.list.insert(key, value);
+        self.record_bits();
+        result
+    }
+
+    pub fn append(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        self.record_entry(APPEND, &key, &value);
+        let result = self.list.append(key, value);
+        self.record_bits();
+        result
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        self.record_remove(key);
+        self.list.remove(key)
+    }
+
+    // 取り出したkeyの削除として記録する．
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let popped = self.list.pop_first()?;
+        self.record_remove(&popped.0);
+        Some(popped)
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let popped = self.list.pop_last()?;
+        self.record_remove(&popped.0);
+        Some(popped)
+    }
+
+    // Vec<u8>への書き込みは失敗しない．
+    fn record_entry(&mut self, op: u8, key: &K, value: &V) {
+        self.trace.push(op);
+        write_entry(
+            &mut self.trace,
+            &mut self.buf,
+            (key, value),
+            &self.codec,
+            &self.codec,
+        )
+        .unwrap();
+    }
+
+    fn record_remove(&mut self, key: &K) {
+        self.trace.push(REMOVE);
+        self.buf.clear();
+        self.codec.encode(key, &mut self.buf);
+        write_chunk(&mut self.trace, &self.buf).unwrap();
+    }
+
+    fn record_bits(&mut self) {
+        let bits = std::mem::take(&mut self.list.gen.bits);
+        self.trace.push(bits.len() as u8);
+        for byte in bits.chunks(8) {
+            let packed = byte
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, bit)| acc | (u8::from(*bit) << i));
+            self.trace.push(packed);
+        }
+    }
+}
+
+// 記録した操作を同じGeneratorの結果でやり直す．操作の成否は記録したときと同じになる．
+pub fn replay<K: Ord, V>(
+    mut trace: impl Read,
+    codec: &(impl Codec<K> + Codec<V>),
+) -> io::Result<SkipList<K, V, Replayed>> {
+    let mut magic = [0; 4];
+    trace.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(invalid("not a skip list trace"));
+    }
+    let mut list = SkipList::new(Replayed {
+        bits: VecDeque::new(),
+    });
+    let mut buf = Vec::new();
+    let mut op = [0];
+    while trace.read(&mut op)? == 1 {
+        match op[0] {
+            INSERT | APPEND => {
+                let (key, value) = read_entry(&mut trace, &mut buf, codec, codec)?;
+                let mut len = [0];
+                trace.read_exact(&mut len)?;
+                let mut packed = vec![0; (len[0] as usize).div_ceil(8)];
+                trace.read_exact(&mut packed)?;
+                list.gen.bits = (0..len[0] as usize)
+                    .map(|i| packed[i / 8] >> (i % 8) & 1 == 1)
+                    .collect();
+                let _ = match op[0] {
+                    INSERT => list.insert(key, value),
+                    _ => list.append(key, value),
+                };
+            }
+            REMOVE => {
+                read_chunk(&mut trace, &mut buf)?;
+                let key = Codec::<K>::decode(codec, &buf)?;
+                let _ = list.remove(&key);
+            }
+            _ => return Err(invalid("unknown operation")),
+        }
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{replay, RecordingSkipList};
+    use crate::test::Gen;
+    use crate::test_util::render;
+    use crate::Plain;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::ErrorKind;
+
+    #[mockalloc::test]
+    fn record_and_replay() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = RecordingSkipList::new(Gen::standard(SmallRng::from_entropy()), Plain);
+        for _ in 0..2000 {
+            let key: u32 = rng.gen_range(0..500);
+            match rng.gen_range(0..10) {
+                0..=4 => drop(list.insert(key, key as u64 * 3)),
+                5 => drop(list.append(key, 0)),
+                6..=7 => drop(list.remove(&key)),
+                8 => drop(list.pop_first()),
+                _ => drop(list.pop_last()),
+            }
+        }
+
+        let replayed = replay::<u32, u64>(list.trace(), &Plain).unwrap();
+        assert_eq!(render(&replayed), render(list.list()));
+        assert!(replayed.iter().eq(list.list().iter()));
+        assert_eq!(replayed.check_invariants(), Ok(()));
+
+        let trace = list.trace();
+        let cut = replay::<u32, u64>(&trace[..trace.len() - 1], &Plain);
+        assert_eq!(cut.err().unwrap().kind(), ErrorKind::UnexpectedEof);
+        let bad = replay::<u32, u64>(&b"nope"[..], &Plain);
+        assert_eq!(bad.err().unwrap().kind(), ErrorKind::InvalidData);
+    }
+}
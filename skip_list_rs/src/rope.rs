@@ -0,0 +1,458 @@
+use std::{cmp::Ordering, fmt, ops::Range};
+
+use crate::{
+    node::{Link, MaybeNode},
+    Generator, SkipList,
+};
+
+// 1つのchunkに入れるbyte数の上限．挿入で超えたら後ろを別のchunkに分ける．
+const CHUNK: usize = 512;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Width {
+    bytes: usize,
+    chars: usize,
+}
+
+impl Width {
+    fn of(text: &str) -> Self {
+        Self {
+            bytes: text.len(),
+            chars: text.chars().count(),
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            bytes: self.bytes + other.bytes,
+            chars: self.chars + other.chars,
+        }
+    }
+}
+
+struct Chunk {
+    text: String,
+    // 各levelのlinkが飛び越す範囲(始点を除き終点を含む)の幅．
+    widths: Vec<Width>,
+}
+
+type ChunkNode = MaybeNode<(), Chunk>;
+
+// 文字列をCHUNK byte以下のchunkに分けて持つ．chunkはSkipVecと同じく位置で並べ，
+// 各linkにAggregateSkipListと同じく飛び越す幅を持たせて，byteでもcharでも位置をO(log n)で探す．
+pub struct SkipRope<G: Generator<bool>> {
+    list: SkipList<(), Chunk, G>,
+    heads: Vec<Width>,
+    len: Width,
+}
+
+struct Located {
+    // chunkの位置(1始まり)．空なら0．
+    pos: usize,
+    node: ChunkNode,
+    // chunkより前の幅．
+    before: Width,
+}
+
+impl<G: Generator<bool>> SkipRope<G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            heads: vec![Width::default()],
+            len: Width::default(),
+        }
+    }
+
+    pub fn len_bytes(&self) -> usize {
+        self.len.bytes
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.len.chars
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.list.iter().map(|(_, chunk)| chunk.text.as_str())
+    }
+
+    // char単位の位置をbyte単位に直す．末尾も指せる．
+    pub fn byte_of_char(&self, char_index: usize) -> usize {
+        assert!(
+            char_index <= self.len.chars,
+            "char index (is {char_index}) should be <= len (is {})",
+            self.len.chars
+        );
+        let at = self.locate(char_index, |w| w.chars);
+        let Some(node) = at.node.take() else {
+            return 0;
+        };
+        let text = &node.value().text;
+        let offset = text
+            .char_indices()
+            .nth(char_index - at.before.chars)
+            .map_or(text.len(), |(i, _)| i);
+        at.before.bytes + offset
+    }
+
+    pub fn insert(&mut self, byte_index: usize, text: &str) {
+        self.check_boundary(byte_index);
+        if text.is_empty() {
+            return;
+        }
+        let at = self.locate(byte_index, |w| w.bytes);
+        let Some(node) = at.node.take() else {
+            self.insert_chunks(0, text);
+            return;
+        };
+
+        let chunk = &mut node.value_mut().text;
+        chunk.insert_str(byte_index - at.before.bytes, text);
+        let rest = if chunk.len() > CHUNK {
+            chunk.split_off(floor_boundary(chunk, CHUNK))
+        } else {
+            String::new()
+        };
+        self.len = self.len.add(Width::of(text));
+        self.refresh(at.pos);
+        if !rest.is_empty() {
+            self.len.bytes -= rest.len();
+            self.len.chars -= rest.chars().count();
+            self.insert_chunks(at.pos, &rest);
+        }
+    }
+
+    pub fn insert_at_char(&mut self, char_index: usize, text: &str) {
+        self.insert(self.byte_of_char(char_index), text);
+    }
+
+    pub fn remove(&mut self, range: Range<usize>) {
+        assert!(
+            range.start <= range.end,
+            "range start is greater than range end"
+        );
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+
+        let mut remaining = range.end - range.start;
+        while remaining > 0 {
+            let at = self.locate(range.start, |w| w.bytes);
+            let Some(node) = at.node.take() else {
+                unreachable!("the range is within the rope");
+            };
+            let chunk = &mut node.value_mut().text;
+            let offset = range.start - at.before.bytes;
+            let end = chunk.len().min(offset + remaining);
+            let removed = Width::of(&chunk[offset..end]);
+            chunk.replace_range(offset..end, "");
+            remaining -= removed.bytes;
+            self.len.bytes -= removed.bytes;
+            self.len.chars -= removed.chars;
+
+            if chunk.is_empty() {
+                let preds = self.preds(at.pos);
+                let removed = self.list.remove_by(|_, pos| pos.cmp(&at.pos));
+                if let Ok(node) = removed {
+                    node.dispose();
+                }
+                for (level, pred) in preds.into_iter().enumerate() {
+                    self.recompute(pred, level);
+                }
+            } else {
+                self.refresh(at.pos);
+            }
+        }
+    }
+
+    pub fn remove_chars(&mut self, range: Range<usize>) {
+        let start = self.byte_of_char(range.start);
+        let end = self.byte_of_char(range.end);
+        self.remove(start..end);
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> String {
+        assert!(
+            range.start <= range.end,
+            "range start is greater than range end"
+        );
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+
+        let mut out = String::with_capacity(range.end - range.start);
+        let at = self.locate(range.start, |w| w.bytes);
+        let mut node = at.node;
+        let mut offset = range.start - at.before.bytes;
+        while out.len() < range.end - range.start {
+            let Some(n) = node.take() else {
+                unreachable!("the range is within the rope");
+            };
+            let text = &n.value().text;
+            let end = text.len().min(offset + range.end - range.start - out.len());
+            out.push_str(&text[offset..end]);
+            offset = 0;
+            node = n.nexts()[0].next;
+        }
+        out
+    }
+
+    pub fn slice_chars(&self, range: Range<usize>) -> String {
+        self.slice(self.byte_of_char(range.start)..self.byte_of_char(range.end))
+    }
+
+    fn check_boundary(&self, byte_index: usize) {
+        assert!(
+            byte_index <= self.len.bytes,
+            "byte index (is {byte_index}) should be <= len (is {})",
+            self.len.bytes
+        );
+        let at = self.locate(byte_index, |w| w.bytes);
+        if let Some(node) = at.node.take() {
+            assert!(
+                node.value()
+                    .text
+                    .is_char_boundary(byte_index - at.before.bytes),
+                "byte index {byte_index} is not a char boundary"
+            );
+        }
+    }
+
+    // indexを含むchunk．indexが末尾なら最後のchunk．
+    fn locate(&self, index: usize, measure: fn(&Width) -> usize) -> Located {
+        let mut from = MaybeNode::null();
+        let mut pos = 0;
+        let mut before = Width::default();
+        for level in (0..self.list.nodes.len()).rev() {
+            loop {
+                let link = self.links(from)[level];
+                let width = self.widths(from)[level];
+                if link.next.is_null() || measure(&before.add(width)) > index {
+                    break;
+                }
+                before = before.add(width);
+                pos += link.span;
+                from = link.next;
+            }
+        }
+
+        let next = self.links(from)[0].next;
+        if !next.is_null() {
+            return Located {
+                pos: pos + 1,
+                node: next,
+                before,
+            };
+        }
+        // 末尾を指しているので，最後のchunkの後ろにつける．
+        let Some(last) = from.take() else {
+            return Located {
+                pos: 0,
+                node: from,
+                before,
+            };
+        };
+        let width = Width::of(&last.value().text);
+        Located {
+            pos,
+            node: from,
+            before: Width {
+                bytes: before.bytes - width.bytes,
+                chars: before.chars - width.chars,
+            },
+        }
+    }
+
+    // textをCHUNK以下に分け，位置がafterのchunkの後ろに順に挿入する．
+    fn insert_chunks(&mut self, after: usize, mut text: &str) {
+        let mut index = after;
+        while !text.is_empty() {
+            let (piece, rest) = text.split_at(floor_boundary(text, CHUNK));
+            text = rest;
+            let chunk = Chunk {
+                text: piece.to_string(),
+                widths: Vec::new(),
+            };
+            // 位置がindex + 1未満のnodeの後ろに挿入する．
+            let inserted = self.list.insert_by(
+                |_, pos, _| {
+                    if pos <= index {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    }
+                },
+                (),
+                chunk,
+            );
+            let Ok((node, pos)) = inserted else {
+                unreachable!("positional insertion never collides");
+            };
+            node.value_mut().widths = vec![Width::default(); node.level()];
+            self.heads.resize(self.list.nodes.len(), Width::default());
+            self.len = self.len.add(Width::of(piece));
+            self.refresh(pos);
+            index = pos;
+        }
+    }
+
+    // 位置がposのchunkの幅が変わったので，そこを飛び越すlinkとchunk自身のlinkを下から計算し直す．
+    fn refresh(&mut self, pos: usize) {
+        let preds = self.preds(pos);
+        let node = self.links(preds[0])[0].next;
+        let level = node.take().map_or(0, |n| n.level());
+        for (l, pred) in preds.into_iter().enumerate() {
+            self.recompute(pred, l);
+            if l < level {
+                self.recompute(node, l);
+            }
+        }
+    }
+
+    // 各levelで位置がposより前にある最後のnode(nullはhead)．
+    fn preds(&self, pos: usize) -> Vec<ChunkNode> {
+        let mut from = MaybeNode::null();
+        let mut rank = 0;
+        let mut preds = vec![MaybeNode::null(); self.list.nodes.len()];
+        for level in (0..preds.len()).rev() {
+            loop {
+                let link = self.links(from)[level];
+                if link.next.is_null() || rank + link.span >= pos {
+                    break;
+                }
+                rank += link.span;
+                from = link.next;
+            }
+            preds[level] = from;
+        }
+        preds
+    }
+
+    // fromのlevelのlinkの幅を，level - 1のlinkから計算し直す．
+    fn recompute(&mut self, from: ChunkNode, level: usize) {
+        let target = self.links(from)[level].next;
+        let width = match target.take() {
+            None => Width::default(),
+            Some(node) if level == 0 => Width::of(&node.value().text),
+            Some(_) => {
+                let mut acc = Width::default();
+                let mut cur = from;
+                while cur != target {
+                    acc = acc.add(self.widths(cur)[level - 1]);
+                    cur = self.links(cur)[level - 1].next;
+                }
+                acc
+            }
+        };
+
+        match from.take() {
+            Some(node) => node.value_mut().widths[level] = width,
+            None => self.heads[level] = width,
+        }
+    }
+
+    fn links(&self, from: ChunkNode) -> &[Link<(), Chunk>] {
+        match from.take() {
+            Some(node) => node.nexts(),
+            None => &self.list.nodes,
+        }
+    }
+
+    fn widths(&self, from: ChunkNode) -> &[Width] {
+        match from.take() {
+            Some(node) => &node.value().widths,
+            None => &self.heads,
+        }
+    }
+}
+
+fn floor_boundary(text: &str, max: usize) -> usize {
+    if text.len() <= max {
+        return text.len();
+    }
+    let mut at = max;
+    while !text.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+impl<G: Generator<bool>> fmt::Display for SkipRope<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::SkipRope;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[mockalloc::test]
+    fn edit() {
+        let mut rope = SkipRope::new(Gen::standard(SmallRng::from_entropy()));
+        rope.insert(0, "hello world");
+        rope.insert(5, ",");
+        rope.insert(rope.len_bytes(), "!");
+        assert_eq!(rope.to_string(), "hello, world!");
+        rope.insert_at_char(0, "¡");
+        assert_eq!(rope.len_chars(), 14);
+        assert_eq!(rope.len_bytes(), 15);
+        assert_eq!(rope.byte_of_char(1), 2);
+        assert_eq!(rope.slice_chars(1..6), "hello");
+        rope.remove_chars(6..8);
+        assert_eq!(rope.to_string(), "¡helloworld!");
+        rope.remove(0..2);
+        assert_eq!(rope.slice(0..5), "hello");
+    }
+
+    #[mockalloc::test]
+    fn random_against_string() {
+        let mut rng = SmallRng::from_entropy();
+        let mut rope = SkipRope::new(Gen::standard(SmallRng::from_entropy()));
+        let mut model = String::new();
+        let alphabet: Vec<char> = "abcあいう😀 \n".chars().collect();
+
+        for _ in 0..300 {
+            let chars = model.chars().count();
+            if chars == 0 || rng.gen_bool(0.6) {
+                let len = rng.gen_range(0..700);
+                let text: String = (0..len)
+                    .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                    .collect();
+                let at = rng.gen_range(0..=chars);
+                let byte = model.char_indices().nth(at).map_or(model.len(), |(i, _)| i);
+                rope.insert_at_char(at, &text);
+                model.insert_str(byte, &text);
+            } else {
+                let start = rng.gen_range(0..chars);
+                let end = rng.gen_range(start..=chars.min(start + 1000));
+                rope.remove_chars(start..end);
+                let byte = |c| model.char_indices().nth(c).map_or(model.len(), |(i, _)| i);
+                let (start, end) = (byte(start), byte(end));
+                model.replace_range(start..end, "");
+            }
+
+            check_spans(&rope.list);
+            assert_eq!(rope.len_bytes(), model.len());
+            assert_eq!(rope.len_chars(), model.chars().count());
+            assert!(rope
+                .chunks()
+                .all(|c| !c.is_empty() && c.len() <= super::CHUNK));
+            if !model.is_empty() {
+                let start = rng.gen_range(0..model.chars().count());
+                let end = rng.gen_range(start..=model.chars().count());
+                let expected: String = model.chars().skip(start).take(end - start).collect();
+                assert_eq!(rope.slice_chars(start..end), expected);
+            }
+        }
+        assert_eq!(rope.to_string(), model);
+    }
+
+    #[test]
+    #[should_panic]
+    fn not_a_boundary() {
+        let mut rope = SkipRope::new(Gen::standard(SmallRng::from_entropy()));
+        rope.insert(0, "あ");
+        rope.insert(1, "a");
+    }
+}
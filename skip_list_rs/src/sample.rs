@@ -0,0 +1,59 @@
+use crate::{Generator, SkipList};
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 位置を一様に選び，spanを辿ってO(log n)で取り出す．genは全域で一様なusizeを返すこと．
+    pub fn sample(&self, gen: &mut impl Generator<usize>) -> Option<(&K, &V)> {
+        let count = self.count;
+        if count == 0 {
+            return None;
+        }
+        // countの倍数に収まらない端を捨てて，剰余の偏りをなくす．
+        let zone = usize::MAX - (usize::MAX - count + 1) % count;
+        let pos = loop {
+            let r = gen.gen();
+            if r <= zone {
+                break r % count + 1;
+            }
+        };
+        let node = self.node_at(pos)?;
+        Some((node.key(), node.value()))
+    }
+
+    // 復元抽出でn個選ぶ．
+    pub fn sample_n(&self, gen: &mut impl Generator<usize>, n: usize) -> Vec<(&K, &V)> {
+        if self.count == 0 {
+            return Vec::new();
+        }
+        (0..n).filter_map(|_| self.sample(gen)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn uniform() {
+        let mut gen = Gen::standard(SmallRng::from_entropy());
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        assert_eq!(list.sample(&mut gen), None);
+        assert!(list.sample_n(&mut gen, 3).is_empty());
+
+        for key in 0..10 {
+            list.insert(key, ()).unwrap();
+        }
+        let mut hits = [0; 10];
+        for (key, _) in list.sample_n(&mut gen, 10000) {
+            hits[*key] += 1;
+        }
+        assert!(hits.iter().all(|&n| (800..1200).contains(&n)), "{hits:?}");
+
+        // 捨てる端に当たったら引き直す．
+        let mut values = [usize::MAX, usize::MAX - 1, 12].into_iter();
+        let mut gen = move || values.next().unwrap();
+        assert_eq!(list.sample(&mut gen), Some((&2, &())));
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::{Add, Bound, RangeBounds},
+};
+
+use crate::{
+    iter::Range,
+    node::{MaybeNode, Node},
+    Generator, SkipList,
+};
+
+// (score, member)順に並べ，memberからは直接nodeを引く．
+pub struct ScoredSet<M: Ord + Hash + Clone, S: Ord, G: Generator<bool>> {
+    list: SkipList<(S, M), (), G>,
+    members: HashMap<M, Node<(S, M), ()>>,
+}
+
+impl<M: Ord + Hash + Clone, S: Ord, G: Generator<bool>> ScoredSet<M, S, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            members: HashMap::new(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    // 既にあるmemberはscoreを更新し，古いscoreを返す．
+    pub fn add(&mut self, member: M, score: S) -> Option<S> {
+        let old = self.unlink(&member).map(|(score, _)| score);
+        self.link(member, score);
+        old
+    }
+
+    // 無いmemberはdeltaをscoreとして追加する．
+    pub fn incr_score(&mut self, member: M, delta: S) -> &S
+    where
+        S: Add<Output = S>,
+    {
+        let score = match self.unlink(&member) {
+            Some((score, _)) => score + delta,
+            None => delta,
+        };
+        let node = self.link(member, score);
+        &node.key().0
+    }
+
+    pub fn remove(&mut self, member: &M) -> Option<S> {
+        let (score, _) = self.unlink(member)?;
+        Some(score)
+    }
+
+    pub fn score(&self, member: &M) -> Option<&S> {
+        let node = self.members.get(member)?;
+        Some(&node.key().0)
+    }
+
+    // 0始まりの順位．
+    pub fn rank(&self, member: &M) -> Option<usize> {
+        let node = self.members.get(member)?;
+        let pos = self.list.pos_of(node.key())?;
+        Some(pos - 1)
+    }
+
+    pub fn range_by_score<R: RangeBounds<S>>(&self, range: R) -> ScoredIter<'_, M, S> {
+        let start = |(s, _): &(S, M)| match range.start_bound() {
+            Bound::Included(b) => s < b,
+            Bound::Excluded(b) => s <= b,
+            Bound::Unbounded => false,
+        };
+        let end = |(s, _): &(S, M)| match range.end_bound() {
+            Bound::Included(b) => s <= b,
+            Bound::Excluded(b) => s < b,
+            Bound::Unbounded => true,
+        };
+
+        ScoredIter {
+            iter: self.list.range_by(start, end),
+        }
+    }
+
+    pub fn range_by_rank<R: RangeBounds<usize>>(&self, range: R) -> ScoredIter<'_, M, S> {
+        // 位置は1始まりなので，0始まりの順位rの位置はr + 1．
+        let start = match range.start_bound() {
+            Bound::Included(r) => r.saturating_add(1),
+            Bound::Excluded(r) => r.saturating_add(2),
+            Bound::Unbounded => 1,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(r) => r.saturating_add(2),
+            Bound::Excluded(r) => r.saturating_add(1),
+            Bound::Unbounded => usize::MAX,
+        };
+
+        let node_at = |pos| match self.list.node_at(pos) {
+            Some(node) => node.into(),
+            None => MaybeNode::null(),
+        };
+        ScoredIter {
+            iter: self.list.range_between(node_at(start), node_at(end)),
+        }
+    }
+
+    pub fn iter(&self) -> ScoredIter<'_, M, S> {
+        self.range_by_rank(..)
+    }
+
+    fn link(&mut self, member: M, score: S) -> Node<(S, M), ()> {
+        let inserted =
+            self.list
+                .insert_by(|next, _, key| next.cmp(key), (score, member.clone()), ());
+        let Ok((node, _)) = inserted else {
+            unreachable!("members are unique");
+        };
+        self.members.insert(member, node);
+        node
+    }
+
+    fn unlink(&mut self, member: &M) -> Option<(S, M)> {
+        let node = self.members.remove(member)?;
+        let key = node.key();
+        let removed = self.list.remove_by(|next, _| next.cmp(key)).ok()?;
+        let (key, _) = removed.dispose();
+        Some(key)
+    }
+}
+
+pub struct ScoredIter<'a, M: Ord + 'a, S: Ord + 'a> {
+    iter: Range<'a, (S, M), ()>,
+}
+
+impl<'a, M: Ord + 'a, S: Ord + 'a> Iterator for ScoredIter<'a, M, S> {
+    type Item = (&'a M, &'a S);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|((score, member), _)| (member, score))
+    }
+}
+
+// membersはlistが所有するnodeだけを指すので，SkipListと同じくSend/Syncにできる．
+unsafe impl<M: Ord + Hash + Clone + Send, S: Ord + Send, G: Generator<bool> + Send> Send
+    for ScoredSet<M, S, G>
+{
+}
+unsafe impl<M: Ord + Hash + Clone + Sync, S: Ord + Sync, G: Generator<bool> + Sync> Sync
+    for ScoredSet<M, S, G>
+{
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::ScoredSet;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::ops::Bound;
+
+    #[mockalloc::test]
+    fn scores_and_ranks() {
+        let mut set = ScoredSet::new(Gen::standard(SmallRng::from_entropy()));
+
+        assert_eq!(set.add("alice", 30), None);
+        assert_eq!(set.add("bob", 10), None);
+        assert_eq!(set.add("carol", 20), None);
+        assert_eq!(set.add("dave", 20), None);
+        assert_eq!(set.add("bob", 25), Some(10));
+
+        assert_eq!(set.count(), 4);
+        assert_eq!(set.score(&"bob"), Some(&25));
+        assert_eq!(set.rank(&"carol"), Some(0));
+        assert_eq!(set.rank(&"dave"), Some(1));
+        assert_eq!(set.rank(&"alice"), Some(3));
+        assert_eq!(set.rank(&"eve"), None);
+
+        assert_eq!(*set.incr_score("carol", 15), 35);
+        assert_eq!(*set.incr_score("eve", 5), 5);
+        assert!(set.iter().eq([
+            ("eve", 5),
+            ("dave", 20),
+            ("bob", 25),
+            ("alice", 30),
+            ("carol", 35)
+        ]
+        .iter()
+        .map(|(m, s)| (m, s))));
+
+        assert!(set
+            .range_by_score(20..=30)
+            .map(|(m, _)| *m)
+            .eq(["dave", "bob", "alice"]));
+        assert!(set.range_by_score(31..).map(|(m, _)| *m).eq(["carol"]));
+        assert!(set.range_by_score(40..).next().is_none());
+        assert!(set.range_by_rank(1..3).map(|(m, _)| *m).eq(["dave", "bob"]));
+        assert!(set
+            .range_by_rank(3..)
+            .map(|(m, _)| *m)
+            .eq(["alice", "carol"]));
+        assert!(set.range_by_rank(..=0).map(|(m, _)| *m).eq(["eve"]));
+        assert!(set.range_by_rank(5..).next().is_none());
+        assert_eq!(set.range_by_rank(..=usize::MAX).count(), 5);
+        assert_eq!(set.range_by_rank(3..usize::MAX).count(), 2);
+        assert!(set.range_by_rank(usize::MAX..).next().is_none());
+        let excluded = (Bound::Excluded(usize::MAX), Bound::Unbounded);
+        assert!(set.range_by_rank(excluded).next().is_none());
+
+        assert_eq!(set.remove(&"bob"), Some(25));
+        assert_eq!(set.remove(&"bob"), None);
+        assert_eq!(set.rank(&"alice"), Some(2));
+    }
+
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<ScoredSet<String, u32, fn() -> bool>>();
+    }
+}
@@ -0,0 +1,369 @@
+use std::ops::{BitAnd, BitOr, BitXor, RangeBounds, Sub};
+
+use crate::{
+    iter::{IntoIter, Iter, Range},
+    Generator, RemoveError, SkipList,
+};
+
+pub struct SkipSet<T: Ord, G: Generator<bool>> {
+    list: SkipList<T, (), G>,
+}
+
+impl<T: Ord, G: Generator<bool>> SkipSet<T, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    // 既にあれば，渡したvalueをそのまま返す．
+    pub fn insert(&mut self, value: T) -> Result<(), T> {
+        self.list.insert(value, ()).map_err(|e| e.into_inner().0)
+    }
+
+    // 昇順に並んだvaluesは末尾へつなぐだけで，探索しない．そうでないvalueはinsertと同じく探して入れ，重複は捨てる．
+    pub fn from_sorted(values: impl IntoIterator<Item = T>, gen: G) -> Self {
+        let mut set = Self::new(gen);
+        for value in values {
+            set.list.append(value, ()).ok();
+        }
+        set
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.list.contains_key(value)
+    }
+
+    pub fn remove(&mut self, value: &T) -> Result<T, RemoveError> {
+        self.list.remove(value).map(|(value, _)| value)
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.list.first().map(|(value, _)| value)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.list.last().map(|(value, _)| value)
+    }
+
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.list.pop_first().map(|(value, _)| value)
+    }
+
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.list.pop_last().map(|(value, _)| value)
+    }
+
+    pub fn iter(&self) -> SetIter<'_, T> {
+        SetIter {
+            iter: self.list.iter(),
+        }
+    }
+
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> SetRange<'_, T> {
+        SetRange {
+            iter: self.list.range(range),
+        }
+    }
+
+    pub fn union<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipSet<T, H>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.list.union(&other.list).map(|(value, _)| value)
+    }
+
+    pub fn intersection<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipSet<T, H>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.list.intersection(&other.list).map(|(value, _)| value)
+    }
+
+    pub fn difference<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipSet<T, H>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.list.difference(&other.list).map(|(value, _)| value)
+    }
+
+    pub fn symmetric_difference<'a, H: Generator<bool>>(
+        &'a self,
+        other: &'a SkipSet<T, H>,
+    ) -> impl Iterator<Item = &'a T> {
+        let mut left = self.difference(other).peekable();
+        let mut right = other.difference(self).peekable();
+        std::iter::from_fn(move || match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) if r < l => right.next(),
+            (Some(_), _) => left.next(),
+            (None, _) => right.next(),
+        })
+    }
+
+    // valuesは昇順に並んでいること．取り除いた数を返す．
+    pub fn remove_all<'v>(&mut self, values: impl IntoIterator<Item = &'v T>) -> usize
+    where
+        T: 'v,
+    {
+        self.list.remove_all(values)
+    }
+
+    pub fn is_subset<H: Generator<bool>>(&self, other: &SkipSet<T, H>) -> bool {
+        self.list.is_subset(&other.list)
+    }
+
+    pub fn is_superset<H: Generator<bool>>(&self, other: &SkipSet<T, H>) -> bool {
+        self.list.is_superset(&other.list)
+    }
+
+    pub fn is_disjoint<H: Generator<bool>>(&self, other: &SkipSet<T, H>) -> bool {
+        self.list.is_disjoint(&other.list)
+    }
+}
+
+impl<T: Ord + Clone, G: Generator<bool> + Clone> SkipSet<T, G> {
+    // 演算子はgenを受け取れないので，左のgenを複製して使う．同じ乱数列を引き直すので，
+    // 結果の高さは左のsetの高さと揃うが，結果の中では互いに独立なので探索の手間は変わらない．
+    // 別の乱数列を使うなら，from_sortedに集合演算のiteratorと新しいgenを渡す．
+    fn collect_sorted<'a>(&self, values: impl Iterator<Item = &'a T>) -> Self
+    where
+        T: 'a,
+    {
+        Self::from_sorted(values.cloned(), self.list.gen.clone())
+    }
+}
+
+// BTreeSetと同じく，参照どうしから新しいsetを作る．
+impl<T: Ord + Clone, G: Generator<bool> + Clone, H: Generator<bool>> BitOr<&SkipSet<T, H>>
+    for &SkipSet<T, G>
+{
+    type Output = SkipSet<T, G>;
+
+    fn bitor(self, rhs: &SkipSet<T, H>) -> SkipSet<T, G> {
+        self.collect_sorted(self.union(rhs))
+    }
+}
+
+impl<T: Ord + Clone, G: Generator<bool> + Clone, H: Generator<bool>> BitAnd<&SkipSet<T, H>>
+    for &SkipSet<T, G>
+{
+    type Output = SkipSet<T, G>;
+
+    fn bitand(self, rhs: &SkipSet<T, H>) -> SkipSet<T, G> {
+        self.collect_sorted(self.intersection(rhs))
+    }
+}
+
+impl<T: Ord + Clone, G: Generator<bool> + Clone, H: Generator<bool>> BitXor<&SkipSet<T, H>>
+    for &SkipSet<T, G>
+{
+    type Output = SkipSet<T, G>;
+
+    fn bitxor(self, rhs: &SkipSet<T, H>) -> SkipSet<T, G> {
+        self.collect_sorted(self.symmetric_difference(rhs))
+    }
+}
+
+impl<T: Ord + Clone, G: Generator<bool> + Clone, H: Generator<bool>> Sub<&SkipSet<T, H>>
+    for &SkipSet<T, G>
+{
+    type Output = SkipSet<T, G>;
+
+    fn sub(self, rhs: &SkipSet<T, H>) -> SkipSet<T, G> {
+        self.collect_sorted(self.difference(rhs))
+    }
+}
+
+impl<T: Ord, G: Generator<bool>> IntoIterator for SkipSet<T, G> {
+    type Item = T;
+
+    type IntoIter = SetIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SetIntoIter {
+            iter: self.list.into_iter(),
+        }
+    }
+}
+
+pub struct SetIter<'a, T: Ord + 'a> {
+    iter: Iter<'a, T, ()>,
+}
+
+impl<'a, T: Ord + 'a> Iterator for SetIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(value, _)| value)
+    }
+}
+
+pub struct SetRange<'a, T: Ord + 'a> {
+    iter: Range<'a, T, ()>,
+}
+
+impl<'a, T: Ord + 'a> Iterator for SetRange<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(value, _)| value)
+    }
+}
+
+impl<'a, T: Ord + 'a> DoubleEndedIterator for SetRange<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(value, _)| value)
+    }
+}
+
+pub struct SetIntoIter<T: Ord> {
+    iter: IntoIter<T, ()>,
+}
+
+impl<T: Ord> Iterator for SetIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(value, _)| value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::{RemoveError, SkipSet};
+    use rand::rngs::SmallRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn set(
+        items: impl IntoIterator<Item = i32>,
+    ) -> SkipSet<i32, Gen<bool, SmallRng, rand::distributions::Standard>> {
+        let mut set = SkipSet::new(Gen::standard(SmallRng::from_entropy()));
+        for item in items {
+            set.insert(item).unwrap();
+        }
+        set
+    }
+
+    #[mockalloc::test]
+    fn insert_contains_remove() {
+        let mut items: Vec<_> = (0..20).map(|e| e * 2).collect();
+        items.shuffle(&mut SmallRng::from_entropy());
+        let mut set = set(items);
+
+        assert_eq!(set.count(), 20);
+        assert_eq!(set.insert(4), Err(4));
+        assert!(set.contains(&4));
+        assert!(!set.contains(&5));
+        assert_eq!(set.remove(&5), Err(RemoveError::NotFound));
+        assert_eq!(set.remove(&4), Ok(4));
+        assert!(!set.contains(&4));
+        assert_eq!(set.count(), 19);
+    }
+
+    #[mockalloc::test]
+    fn first_last() {
+        let mut set = set([3, 1, 4, 5, 9, 2, 6]);
+
+        assert_eq!(set.first(), Some(&1));
+        assert_eq!(set.last(), Some(&9));
+        assert_eq!(set.pop_first(), Some(1));
+        assert_eq!(set.pop_last(), Some(9));
+        check_spans(&set.list);
+        assert_eq!(set.first(), Some(&2));
+        assert_eq!(set.last(), Some(&6));
+        assert!(set.iter().copied().eq([2, 3, 4, 5, 6]));
+
+        while set.pop_last().is_some() {}
+        assert_eq!(set.count(), 0);
+        assert_eq!(set.first(), None);
+        assert_eq!(set.last(), None);
+        assert_eq!(set.pop_first(), None);
+    }
+
+    #[mockalloc::test]
+    fn range() {
+        let mut items: Vec<_> = (0..20).map(|e| e * 2).collect();
+        items.shuffle(&mut SmallRng::from_entropy());
+        let set = set(items);
+
+        assert!(set.range(4..10).copied().eq([4, 6, 8]));
+        assert!(set.range(3..=10).copied().eq([4, 6, 8, 10]));
+        assert!(set.range(..3).copied().eq([0, 2]));
+        assert!(set.range(35..).copied().eq([36, 38]));
+        assert!(set.range(5..5).next().is_none());
+        assert!(set.range(100..).next().is_none());
+        assert!(set.range(..).copied().eq((0..20).map(|e| e * 2)));
+        assert!(set.range(3..=10).rev().copied().eq([10, 8, 6, 4]));
+    }
+
+    #[mockalloc::test]
+    fn set_operations() {
+        let left = set([1, 2, 3, 5, 8, 13]);
+        let right = set([0, 2, 4, 6, 8, 10, 12]);
+        assert!(left
+            .union(&right)
+            .copied()
+            .eq([0, 1, 2, 3, 4, 5, 6, 8, 10, 12, 13]));
+        assert!(left.intersection(&right).copied().eq([2, 8]));
+        assert!(left.difference(&right).copied().eq([1, 3, 5, 13]));
+        assert!(right.difference(&left).copied().eq([0, 4, 6, 10, 12]));
+
+        assert!(set([2, 8]).is_subset(&left));
+        assert!(right.is_superset(&set([4, 12])));
+        assert!(!left.is_disjoint(&right));
+        assert!(set([7, 9]).is_disjoint(&left));
+
+        let mut left = left;
+        assert_eq!(left.remove_all(right.iter()), 2);
+        assert!(left.iter().copied().eq([1, 3, 5, 13]));
+    }
+
+    #[mockalloc::test]
+    fn operators() {
+        let left = set([1, 2, 3, 5, 8, 13]);
+        let right = set([0, 2, 4, 6, 8, 10, 12]);
+        let union = &left | &right;
+        assert!(union
+            .iter()
+            .copied()
+            .eq([0, 1, 2, 3, 4, 5, 6, 8, 10, 12, 13]));
+        assert!((&left & &right).iter().copied().eq([2, 8]));
+        assert!((&left - &right).iter().copied().eq([1, 3, 5, 13]));
+        let xor = &left ^ &right;
+        assert!(xor.iter().copied().eq([0, 1, 3, 4, 5, 6, 10, 12, 13]));
+        assert!(left
+            .symmetric_difference(&right)
+            .copied()
+            .eq(xor.iter().copied()));
+        for set in [&union, &xor] {
+            check_spans(&set.list);
+            assert_eq!(set.list.check_invariants(), Ok(()));
+        }
+
+        let fresh = SkipSet::from_sorted(
+            left.union(&right).copied(),
+            Gen::standard(SmallRng::from_entropy()),
+        );
+        assert!(fresh.iter().eq(union.iter()));
+        let unsorted = SkipSet::from_sorted([3, 1, 3, 2], Gen::standard(SmallRng::from_entropy()));
+        assert!(unsorted.iter().copied().eq([1, 2, 3]));
+        check_spans(&unsorted.list);
+
+        let empty = set([]);
+        assert_eq!((&empty | &empty).count(), 0);
+        assert!((&left ^ &empty).iter().eq(left.iter()));
+        assert!((&empty - &left).iter().next().is_none());
+    }
+
+    #[mockalloc::test]
+    fn into_iter() {
+        let set = set([5, 3, 8, 1]);
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), [1, 3, 5, 8]);
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash},
+    iter::Peekable,
+};
+
+use crate::{iter::Iter, Generator, InsertError, RemoveError, SkipList};
+
+pub trait Router<K> {
+    fn shards(&self) -> usize;
+    fn route(&self, key: &K) -> usize;
+}
+
+// shard i には bounds[i - 1] <= key < bounds[i] を満たすkeyが入る．
+pub struct RangeRouter<K: Ord> {
+    bounds: Vec<K>,
+}
+
+impl<K: Ord> RangeRouter<K> {
+    pub fn new(bounds: Vec<K>) -> Self {
+        assert!(
+            bounds.windows(2).all(|w| w[0] < w[1]),
+            "bounds must be strictly increasing"
+        );
+        Self { bounds }
+    }
+}
+
+impl<K: Ord> Router<K> for RangeRouter<K> {
+    fn shards(&self) -> usize {
+        self.bounds.len() + 1
+    }
+
+    fn route(&self, key: &K) -> usize {
+        self.bounds.partition_point(|b| b <= key)
+    }
+}
+
+pub struct HashRouter<S: BuildHasher = RandomState> {
+    shards: usize,
+    hasher: S,
+}
+
+impl HashRouter {
+    pub fn new(shards: usize) -> Self {
+        Self::with_hasher(shards, RandomState::new())
+    }
+}
+
+impl<S: BuildHasher> HashRouter<S> {
+    pub fn with_hasher(shards: usize, hasher: S) -> Self {
+        assert!(shards > 0, "at least one shard is required");
+        Self { shards, hasher }
+    }
+}
+
+impl<K: Hash, S: BuildHasher> Router<K> for HashRouter<S> {
+    fn shards(&self) -> usize {
+        self.shards
+    }
+
+    fn route(&self, key: &K) -> usize {
+        (self.hasher.hash_one(key) % self.shards as u64) as usize
+    }
+}
+
+// keyの先頭のlenバイトだけをhashする．先頭が同じkeyは同じshardに入る．
+pub struct PrefixRouter<S: BuildHasher = RandomState> {
+    len: usize,
+    hash: HashRouter<S>,
+}
+
+impl PrefixRouter {
+    pub fn new(shards: usize, len: usize) -> Self {
+        Self::with_hasher(shards, len, RandomState::new())
+    }
+}
+
+impl<S: BuildHasher> PrefixRouter<S> {
+    pub fn with_hasher(shards: usize, len: usize, hasher: S) -> Self {
+        Self {
+            len,
+            hash: HashRouter::with_hasher(shards, hasher),
+        }
+    }
+}
+
+impl<K: AsRef<[u8]>, S: BuildHasher> Router<K> for PrefixRouter<S> {
+    fn shards(&self) -> usize {
+        self.hash.shards
+    }
+
+    fn route(&self, key: &K) -> usize {
+        let bytes = key.as_ref();
+        self.hash.route(&&bytes[..self.len.min(bytes.len())])
+    }
+}
+
+pub struct ShardedSkipList<K: Ord, V, G: Generator<bool>, R: Router<K>> {
+    router: R,
+    shards: Vec<SkipList<K, V, G>>,
+}
+
+impl<K: Ord, V, G: Generator<bool>, R: Router<K>> ShardedSkipList<K, V, G, R> {
+    pub fn new(router: R, mut make_gen: impl FnMut() -> G) -> Self {
+        let shards = (0..router.shards())
+            .map(|_| SkipList::new(make_gen()))
+            .collect();
+        Self { router, shards }
+    }
+
+    pub fn count(&self) -> usize {
+        self.shards.iter().map(|s| s.count()).sum()
+    }
+
+    pub fn shard_of(&self, key: &K) -> usize {
+        self.router.route(key)
+    }
+
+    pub fn shards(&self) -> &[SkipList<K, V, G>] {
+        &self.shards
+    }
+
+    // 各shardは独立しているので，呼び出し側で別スレッドに分配して書き込める．
+    pub fn writers(&mut self) -> Vec<ShardWriter<'_, K, V, G, R>> {
+        let router = &self.router;
+        self.shards
+            .iter_mut()
+            .enumerate()
+            .map(|(shard, list)| ShardWriter {
+                shard,
+                router,
+                list,
+            })
+            .collect()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        let shard = self.shard_of(&key);
+        self.shards[shard].insert(key, value)
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        self.shards[self.shard_of(key)].search(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let shard = self.shard_of(key);
+        self.shards[shard].remove(key)
+    }
+
+    pub fn iter(&self) -> ShardedIter<'_, K, V> {
+        ShardedIter {
+            iters: self.shards.iter().map(|s| s.iter().peekable()).collect(),
+        }
+    }
+}
+
+// 1つのshardへの書き込み口．別のshardへ振り分けられるkeyは入れられない．
+pub struct ShardWriter<'a, K: Ord, V, G: Generator<bool>, R: Router<K>> {
+    shard: usize,
+    router: &'a R,
+    list: &'a mut SkipList<K, V, G>,
+}
+
+impl<K: Ord, V, G: Generator<bool>, R: Router<K>> ShardWriter<'_, K, V, G, R> {
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), InsertError<K, V>> {
+        assert_eq!(
+            self.router.route(&key),
+            self.shard,
+            "key is routed to another shard"
+        );
+        self.list.insert(key, value)
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        self.list.search(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        self.list.remove(key)
+    }
+}
+
+// 各shardの先頭を比較し，最小のものから返す．
+pub struct ShardedIter<'a, K: Ord + 'a, V: 'a> {
+    iters: Vec<Peekable<Iter<'a, K, V>>>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> Iterator for ShardedIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut min: Option<(usize, &K)> = None;
+        for (idx, iter) in self.iters.iter_mut().enumerate() {
+            let Some((key, _)) = iter.peek() else {
+                continue;
+            };
+            if min.is_none_or(|(_, m)| *key < m) {
+                min = Some((idx, *key));
+            }
+        }
+
+        let (idx, _) = min?;
+        self.iters[idx].next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{HashRouter, InsertError, PrefixRouter, RangeRouter, ShardedSkipList};
+    use rand::rngs::SmallRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn range_routed() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = ShardedSkipList::new(RangeRouter::new(vec![10, 20]), || {
+            Gen::standard(SmallRng::from_entropy())
+        });
+
+        let mut items: Vec<_> = (0..30).collect();
+        items.shuffle(&mut rng);
+        for item in items {
+            list.insert(item, item).unwrap();
+        }
+
+        assert_eq!(list.count(), 30);
+        for (no, shard) in list.shards().iter().enumerate() {
+            assert_eq!(shard.count(), 10);
+            assert!(shard.iter().all(|(k, _)| *k / 10 == no));
+        }
+        assert!(list.iter().map(|(k, _)| *k).eq(0..30));
+
+        assert_eq!(list.remove(&15), Ok((15, 15)));
+        assert_eq!(list.search(&15), None);
+        assert_eq!(list.search(&16), Some(&16));
+        assert_eq!(list.count(), 29);
+    }
+
+    #[mockalloc::test]
+    fn hash_routed() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = ShardedSkipList::new(HashRouter::new(4), || {
+            Gen::standard(SmallRng::from_entropy())
+        });
+
+        let mut items: Vec<_> = (0..100).collect();
+        items.shuffle(&mut rng);
+        for item in items.iter().copied() {
+            list.insert(item, item * 2).unwrap();
+        }
+        assert_eq!(list.insert(7, 0), Err(InsertError::Occupied(7, 0)));
+
+        assert!(list.iter().map(|(k, _)| *k).eq(0..100));
+        for item in items {
+            assert_eq!(list.search(&item), Some(&(item * 2)));
+        }
+    }
+
+    #[test]
+    fn parallel_writes() {
+        let router = HashRouter::new(4);
+        let mut list = ShardedSkipList::new(router, || Gen::standard(SmallRng::from_entropy()));
+
+        let mut batches: Vec<Vec<u32>> = vec![Vec::new(); 4];
+        for key in 0..1000 {
+            batches[list.shard_of(&key)].push(key);
+        }
+
+        std::thread::scope(|scope| {
+            for (mut writer, batch) in list.writers().into_iter().zip(batches) {
+                scope.spawn(move || {
+                    for key in batch {
+                        writer.insert(key, key).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(list.count(), 1000);
+        assert!(list.iter().map(|(k, _)| *k).eq(0..1000));
+    }
+
+    #[test]
+    #[should_panic(expected = "key is routed to another shard")]
+    fn misrouted_write() {
+        let mut list = ShardedSkipList::new(RangeRouter::new(vec![10]), || {
+            Gen::standard(SmallRng::from_entropy())
+        });
+        let mut writers = list.writers();
+        writers[0].insert(15, 15).unwrap();
+    }
+
+    #[mockalloc::test]
+    fn prefix_routed() {
+        let mut list = ShardedSkipList::new(PrefixRouter::new(8, 2), || {
+            Gen::standard(SmallRng::from_entropy())
+        });
+        let keys: Vec<String> = (0..200).map(|i| format!("{:02}/{i}", i % 20)).collect();
+        for key in &keys {
+            list.insert(key.clone(), ()).unwrap();
+        }
+        // 先頭の2バイトが同じkeyは同じshardに入る．
+        for key in &keys {
+            assert_eq!(list.shard_of(key), list.shard_of(&key[..2].to_string()));
+            assert_eq!(list.search(key), Some(&()));
+        }
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert!(list.iter().map(|(k, _)| k).eq(sorted.iter()));
+        // 先頭のバイト数に満たないkeyは全体をhashする．
+        list.insert("a".to_string(), ()).unwrap();
+        assert_eq!(list.search(&"a".to_string()), Some(&()));
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    hint,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering::SeqCst},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+};
+
+use crate::{
+    lookup::HashIndex,
+    node::{Link, Node},
+    AllocError, Generator, SkipList,
+};
+
+// 読む側は今の版のArcを取るだけで，lockを取らない．書く側は今の版を写したlistを変えて差し替える．
+// nodeを版の間で共有できないので，writeは毎回全体をO(n)で写す．読むのが多く，まれに変わる設定のような用途向け．
+pub struct ArcSkipList<K: Ord, V, G: Generator<bool>> {
+    // Arc::into_rawしたもの．
+    current: AtomicPtr<SkipList<K, V, G>>,
+    // loadでpointerを読んでから参照を数えるまでの間にいる読み手の数．epochの偶奇ごとに分けて数える．
+    readers: [AtomicUsize; 2],
+    // 差し替えるたびに偶奇を入れ替える．書き手は入れ替える前の側の読み手だけを待つ．
+    epoch: AtomicUsize,
+    writer: Mutex<()>,
+    marker: PhantomData<Arc<SkipList<K, V, G>>>,
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 各nodeの高さをそのまま写すので，Generatorは呼ばない．metricsや購読，filterは引き継がない．
+    // content hashはそのまま写し，hash indexは写したnodeで引き直す．
+    pub(crate) fn duplicate(&self) -> Self
+    where
+        K: Clone,
+        V: Clone,
+        G: Clone,
+    {
+        match self.copy_with(|key, value, level| Ok(Node::new(key, value, level))) {
+            Ok(list) => list,
+            Err(AllocError) => unreachable!("Node::new does not fail"),
+        }
+    }
+
+    // nodeを確保できなければ，そこまで写した分を解放して失敗する．KやVのcloneの中の確保は見ない．
+    pub fn try_clone(&self) -> Result<Self, AllocError>
+    where
+        K: Clone,
+        V: Clone,
+        G: Clone,
+    {
+        self.copy_with(Node::try_new)
+    }
+
+    // 写したnodeはすぐlistにつなぐので，途中で返ればlistのDropが解放する．
+    fn copy_with(
+        &self,
+        mut alloc: impl FnMut(K, V, usize) -> Result<Node<K, V>, (K, V)>,
+    ) -> Result<Self, AllocError>
+    where
+        K: Clone,
+        V: Clone,
+        G: Clone,
+    {
+        let mut list = Self::new(self.gen.clone());
+        let height = self.nodes.len();
+        if list.nodes.try_reserve(height).is_err() || list.tails.try_reserve(height).is_err() {
+            return Err(AllocError);
+        }
+        list.digest = self.digest.clone();
+        list.index = self.index.as_ref().map(HashIndex::emptied);
+        if let Some(index) = &mut list.index {
+            index.try_reserve(self.count).map_err(|_| AllocError)?;
+        }
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            let copy =
+                alloc(n.key().clone(), n.value().clone(), n.level()).map_err(|_| AllocError)?;
+            list.allocated(copy);
+            list.push_back(copy);
+            node = n.nexts()[0].next;
+        }
+        list.rebuild_hash_index();
+        list.debug_check(true);
+        Ok(list)
+    }
+}
+
+// 余ったnodeを，panicで巻き戻されたときも含めて解放する．
+struct Spare<K: Ord, V>(Vec<Vec<Node<K, V>>>);
+
+impl<K: Ord, V> Drop for Spare<K, V> {
+    fn drop(&mut self) {
+        for node in self.0.iter_mut().flat_map(|nodes| nodes.drain(..)) {
+            node.dispose();
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone, G: Generator<bool> + Clone> Clone for SkipList<K, V, G> {
+    fn clone(&self) -> Self {
+        self.duplicate()
+    }
+
+    // 今のnodeを高さごとに取っておき，同じ高さのsourceのnodeに書き写して使い回す．
+    // 足りない分だけ新しく確保し，余ったnodeは解放する．cloneと同じく，metricsや購読，filterは外れ，
+    // content hashとhash indexはsourceに合わせる．
+    fn clone_from(&mut self, source: &Self) {
+        let mut spare = Spare(Vec::new());
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            node = n.nexts()[0].next;
+            if spare.0.len() < n.level() {
+                spare.0.resize_with(n.level(), Vec::new);
+            }
+            spare.0[n.level() - 1].push(n);
+        }
+        // nodeはspareが持つので，古いlistのDropには何も解放させない．
+        self.nodes[0] = Link::null();
+        self.count = 0;
+        *self = Self::new(source.gen.clone());
+
+        let mut node = source.nodes[0].next;
+        while let Some(n) = node.take() {
+            let reused = spare.0.get_mut(n.level() - 1).and_then(Vec::pop);
+            match reused {
+                Some(copy) => {
+                    copy.nexts_mut().fill(Link::null());
+                    // 先につなぐので，clone_fromがpanicしてもlistのDropが解放する．
+                    self.push_back(copy);
+                    unsafe { &mut *copy.key_ptr() }.clone_from(n.key());
+                    copy.value_mut().clone_from(n.value());
+                }
+                None => {
+                    let copy = Node::new(n.key().clone(), n.value().clone(), n.level());
+                    self.push_back(copy);
+                }
+            }
+            node = n.nexts()[0].next;
+        }
+        self.digest = source.digest.clone();
+        self.index = source.index.as_ref().map(HashIndex::emptied);
+        self.rebuild_hash_index();
+        self.debug_check(true);
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> ArcSkipList<K, V, G> {
+    pub fn new(list: SkipList<K, V, G>) -> Self {
+        Self {
+            current: AtomicPtr::new(Arc::into_raw(Arc::new(list)).cast_mut()),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            epoch: AtomicUsize::new(0),
+            writer: Mutex::new(()),
+            marker: PhantomData,
+        }
+    }
+
+    // 返した版は，後で差し替えられても変わらない．
+    pub fn load(&self) -> Arc<SkipList<K, V, G>> {
+        // 数えている間にepochが変われば，数え直す．書き手は1回の差し替えにつき1度しか変えない．
+        let slot = loop {
+            let slot = self.epoch.load(SeqCst);
+            self.readers[slot].fetch_add(1, SeqCst);
+            if self.epoch.load(SeqCst) == slot {
+                break slot;
+            }
+            self.readers[slot].fetch_sub(1, SeqCst);
+        };
+        let current = self.current.load(SeqCst);
+        // 差し替えた側はreadersが0になるまで古い版を手放さないので，currentはまだ生きている．
+        let list = unsafe {
+            Arc::increment_strong_count(current);
+            Arc::from_raw(current)
+        };
+        self.readers[slot].fetch_sub(1, SeqCst);
+        list
+    }
+
+    pub fn store(&self, list: SkipList<K, V, G>) -> Arc<SkipList<K, V, G>> {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        self.swap(Arc::new(list))
+    }
+
+    // 書き手は1つずつ順に通すので，fが見るのは常に直前の書き込みの結果．fがpanicすれば差し替えない．
+    pub fn write<R>(&self, f: impl FnOnce(&mut SkipList<K, V, G>) -> R) -> R
+    where
+        K: Clone,
+        V: Clone,
+        G: Clone,
+    {
+        // 守っているのは()だけなので，前のfがpanicしてpoisonされていても続けてよい．
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut list = self.load().duplicate();
+        let result = f(&mut list);
+        self.swap(Arc::new(list));
+        result
+    }
+
+    // 差し替えた後，その前にpointerを読んだ読み手が参照を数え終わるのを待ってから古い版を返す．
+    // epochを入れ替えた後の読み手は他方の側で数えるので，待つのは入れ替える前から数えていた読み手だけになる．
+    // 読み手が絶えず来ても，書き手はその時いた読み手が抜ければ進める．
+    fn swap(&self, list: Arc<SkipList<K, V, G>>) -> Arc<SkipList<K, V, G>> {
+        let old = self.current.swap(Arc::into_raw(list).cast_mut(), SeqCst);
+        let slot = self.epoch.fetch_xor(1, SeqCst);
+        let mut spins = 0;
+        while self.readers[slot].load(SeqCst) != 0 {
+            if spins < 64 {
+                spins += 1;
+                hint::spin_loop();
+            } else {
+                thread::yield_now();
+            }
+        }
+        unsafe { Arc::from_raw(old) }
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> Drop for ArcSkipList<K, V, G> {
+    fn drop(&mut self) {
+        drop(unsafe { Arc::from_raw(*self.current.get_mut()) });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ArcSkipList;
+    use crate::node::Node;
+    use crate::test::{check_spans, Gen};
+    use crate::test_util::{from_levels, render};
+    use crate::{AllocError, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[mockalloc::test]
+    fn copy_on_write() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..100 {
+            list.insert(key, key).unwrap();
+        }
+        let copy = list.duplicate();
+        assert_eq!(render(&copy), render(&list));
+        check_spans(&copy);
+
+        let shared = ArcSkipList::new(list);
+        let before = shared.load();
+        let removed = shared.write(|list| list.remove(&50).unwrap());
+        assert_eq!(removed, (50, 50));
+        assert_eq!(before.search(&50), Some(&50));
+        assert_eq!(shared.load().search(&50), None);
+        assert_eq!(shared.load().count(), 99);
+
+        let old = shared.store(copy);
+        assert_eq!(old.count(), 99);
+        assert_eq!(shared.load().count(), 100);
+    }
+
+    #[mockalloc::test]
+    fn try_clone() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..100 {
+            list.insert(key, key.to_string()).unwrap();
+        }
+        let copy = list.try_clone().unwrap();
+        assert_eq!(render(&copy), render(&list));
+        assert!(copy.iter().eq(list.iter()));
+
+        // 途中で確保に失敗しても，写した分はすべて解放される．
+        let failed = list.copy_with(|key, value, level| match key {
+            50 => Err((key, value)),
+            _ => Node::try_new(key, value, level),
+        });
+        assert_eq!(failed.err(), Some(AllocError));
+        assert_eq!(list.count(), 100);
+    }
+
+    #[mockalloc::test]
+    fn clone_from() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..200 {
+            let key = rng.gen_range(0..400);
+            list.insert(key, key.to_string()).ok();
+        }
+        let mut copy = list.clone();
+        assert_eq!(render(&copy), render(&list));
+
+        for count in [300, 50, 0, 120] {
+            let mut source = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+            for key in 0..count {
+                source.insert(key * 3, format!("v{key}")).unwrap();
+            }
+            copy.clone_from(&source);
+            assert_eq!(render(&copy), render(&source));
+            check_spans(&copy);
+            assert!(copy.iter().eq(source.iter()));
+        }
+
+        // content hashとhash indexは写した側でも使える．
+        list.enable_content_hash();
+        list.enable_hash_index();
+        let expected = list.content_hash();
+        let mut cloned = list.clone();
+        copy.clone_from(&list);
+        for copy in [&mut cloned, &mut copy, &mut list.try_clone().unwrap()] {
+            assert_eq!(copy.content_hash(), expected);
+            assert!(copy.index.as_ref().is_some_and(|index| list
+                .iter()
+                .all(|(k, v)| index.get(k).is_some_and(|n| n.value() == v))));
+        }
+        copy.insert(400, "x".into()).unwrap();
+        assert_ne!(copy.content_hash(), expected);
+        assert_eq!(copy.search(&400), Some(&"x".into()));
+    }
+
+    // record_allocsは入れ子にできないので，mockalloc::testを使わない．
+    #[test]
+    fn clone_from_reuses_nodes() {
+        let gen = || false;
+        let levels = |count: u32| {
+            (0..count).map(move |key| (key, key, 1 + (key + 1).trailing_zeros() as usize))
+        };
+        let source = from_levels(gen, levels(256).map(|(k, v, l)| (k + 1, v + 1, l)));
+        let mut copy = from_levels(gen, levels(256));
+        let info = mockalloc::record_allocs(|| copy.clone_from(&source));
+        // 確保するのはheadや取っておくnodeの配列だけで，256個のnodeは確保しない．
+        assert!(info.num_allocs() < 64, "{}", info.num_allocs());
+        assert!(copy.iter().eq(source.iter()));
+        check_spans(&copy);
+    }
+
+    #[test]
+    fn write_after_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        let shared = ArcSkipList::new(SkipList::new(Gen::standard(SmallRng::from_entropy())));
+        shared.write(|list| list.insert(0, 0).unwrap());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            shared.write(|list| {
+                list.insert(1, 1).unwrap();
+                panic!("write failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(shared.load().count(), 1);
+
+        shared.write(|list| list.insert(2, 2).unwrap());
+        assert!(shared.load().iter().map(|(k, _)| *k).eq([0, 2]));
+        let old = shared.store(SkipList::new(Gen::standard(SmallRng::from_entropy())));
+        assert_eq!(old.count(), 2);
+        assert_eq!(shared.load().count(), 0);
+    }
+
+    #[test]
+    fn concurrent_reads() {
+        let shared = ArcSkipList::new(SkipList::new(Gen::standard(SmallRng::from_entropy())));
+        let done = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) {
+                        // 書き手は1つずつ足すので，どの版も0から続く連番になる．
+                        let list = shared.load();
+                        assert!(list.iter().map(|(k, _)| *k).eq(0..list.count()));
+                    }
+                });
+            }
+            for key in 0..200 {
+                shared.write(|list| list.insert(key, ()).unwrap());
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(shared.load().count(), 200);
+    }
+
+    // 読み手が途切れずにloadし続けても，書き手は進める．
+    #[test]
+    fn writer_progress() {
+        let gen = || false;
+        let shared = ArcSkipList::new(SkipList::new(gen));
+        let done = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) {
+                        let list = shared.load();
+                        assert!(list.count() <= 1);
+                    }
+                });
+            }
+            for key in 0..1000 {
+                shared.store(from_levels(gen, [(key, key, 1)]));
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(shared.load().search(&999), Some(&999));
+    }
+}
@@ -0,0 +1,372 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    node::{Link, MaybeNode, Node},
+    Generator, SkipList,
+};
+
+// 書式は MAGIC，entryの数，entryの列．entryはkeyとvalueをそれぞれ長さつきで並べる．
+// 数と長さはすべてu64のlittle endian．entryはkeyの昇順に並ぶ．
+const MAGIC: [u8; 4] = *b"SKL1";
+// 形も残す書式は STRUCTURED，entryの数，headの高さ，entryの列．各entryの前にtowerの高さを1 byte置く．
+const STRUCTURED: [u8; 4] = *b"SKT1";
+
+pub trait Codec<T> {
+    fn encode(&self, value: &T, out: &mut Vec<u8>);
+    fn decode(&self, bytes: &[u8]) -> io::Result<T>;
+}
+
+// 整数はlittle endian，Vec<u8>とStringはそのままのbyte列．
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Plain;
+
+macro_rules! plain_int {
+    ($($t:ty)*) => {$(
+        impl Codec<$t> for Plain {
+            fn encode(&self, value: &$t, out: &mut Vec<u8>) {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+
+            fn decode(&self, bytes: &[u8]) -> io::Result<$t> {
+                let bytes = bytes.try_into().map_err(|_| invalid("integer of wrong width"))?;
+                Ok(<$t>::from_le_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+plain_int!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+impl Codec<Vec<u8>> for Plain {
+    fn encode(&self, value: &Vec<u8>, out: &mut Vec<u8>) {
+        out.extend_from_slice(value);
+    }
+
+    fn decode(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl Codec<String> for Plain {
+    fn encode(&self, value: &String, out: &mut Vec<u8>) {
+        out.extend_from_slice(value.as_bytes());
+    }
+
+    fn decode(&self, bytes: &[u8]) -> io::Result<String> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("string is not UTF-8"))
+    }
+}
+
+pub(crate) fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub(crate) fn write_u64(out: &mut impl Write, n: usize) -> io::Result<()> {
+    out.write_all(&(n as u64).to_le_bytes())
+}
+
+pub(crate) fn write_chunk(out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    write_u64(out, bytes.len())?;
+    out.write_all(bytes)
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| invalid("length overflows usize"))
+}
+
+pub(crate) fn read_chunk(input: &mut impl Read, buf: &mut Vec<u8>) -> io::Result<()> {
+    let len = read_u64(input)?;
+    buf.clear();
+    // 壊れた長さで大きく確保しないよう，読めた分だけ伸ばす．
+    let read = input.take(len as u64).read_to_end(buf)?;
+    if read != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+pub(crate) fn write_entry<K, V>(
+    out: &mut impl Write,
+    buf: &mut Vec<u8>,
+    (key, value): (&K, &V),
+    keys: &impl Codec<K>,
+    values: &impl Codec<V>,
+) -> io::Result<()> {
+    buf.clear();
+    keys.encode(key, buf);
+    write_chunk(out, buf)?;
+    buf.clear();
+    values.encode(value, buf);
+    write_chunk(out, buf)
+}
+
+pub(crate) fn read_entry<K, V>(
+    input: &mut impl Read,
+    buf: &mut Vec<u8>,
+    keys: &impl Codec<K>,
+    values: &impl Codec<V>,
+) -> io::Result<(K, V)> {
+    read_chunk(input, buf)?;
+    let key = keys.decode(buf)?;
+    read_chunk(input, buf)?;
+    Ok((key, values.decode(buf)?))
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 小さな書き込みを繰り返すので，outはBufWriterなどで包んでおくとよい．
+    pub fn write_snapshot(
+        &self,
+        mut out: impl Write,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+    ) -> io::Result<()> {
+        out.write_all(&MAGIC)?;
+        write_u64(&mut out, self.count)?;
+        let mut buf = Vec::new();
+        for entry in self.iter() {
+            write_entry(&mut out, &mut buf, entry, keys, values)?;
+        }
+        out.flush()
+    }
+
+    // entryは昇順に並んでいるので，appendで末尾につないでO(n)で組み立てる．
+    pub fn read_snapshot(
+        gen: G,
+        mut input: impl Read,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+    ) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a skip list snapshot"));
+        }
+
+        let count = read_u64(&mut input)?;
+        let mut list = Self::new(gen);
+        let mut buf = Vec::new();
+        for _ in 0..count {
+            let (key, value) = read_entry(&mut input, &mut buf, keys, values)?;
+            if list.last().is_some_and(|(last, _)| last >= &key) {
+                return Err(invalid("snapshot keys are not strictly ascending"));
+            }
+            if list.append(key, value).is_err() {
+                unreachable!("the key is greater than the last one");
+            }
+        }
+        Ok(list)
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // towerの高さも書くので，read_structured_snapshotは同じ形のlistを作る．
+    pub fn write_structured_snapshot(
+        &self,
+        mut out: impl Write,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+    ) -> io::Result<()> {
+        out.write_all(&STRUCTURED)?;
+        write_u64(&mut out, self.count)?;
+        write_u64(&mut out, self.nodes.len())?;
+        let mut buf = Vec::new();
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            out.write_all(&[n.level() as u8])?;
+            write_entry(&mut out, &mut buf, (n.key(), n.value()), keys, values)?;
+            node = n.nexts()[0].next;
+        }
+        out.flush()
+    }
+
+    // genを呼ばずに，書かれた高さのnodeを末尾につなぐ．
+    pub fn read_structured_snapshot(
+        gen: G,
+        mut input: impl Read,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+    ) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if magic != STRUCTURED {
+            return Err(invalid("not a structured skip list snapshot"));
+        }
+
+        let count = read_u64(&mut input)?;
+        let height = read_u64(&mut input)?;
+        if height == 0 || height > usize::BITS as usize {
+            return Err(invalid("head height is out of range"));
+        }
+        let mut list = Self::new(gen);
+        let mut buf = Vec::new();
+        for _ in 0..count {
+            let mut level = [0];
+            input.read_exact(&mut level)?;
+            let level = level[0] as usize;
+            if level == 0 || level > height {
+                return Err(invalid("tower height is out of range"));
+            }
+            let (key, value) = read_entry(&mut input, &mut buf, keys, values)?;
+            if list.last().is_some_and(|(last, _)| last >= &key) {
+                return Err(invalid("snapshot keys are not strictly ascending"));
+            }
+            let node = Node::new(key, value, level);
+            list.allocated(node);
+            list.push_back(node);
+            list.hash_in(node);
+        }
+        // 取り除いて空になった上のlevelも，headの高さとして残す．
+        while list.nodes.len() < height {
+            list.nodes.push(Link::null());
+            list.tails.push(Link {
+                next: MaybeNode::null(),
+                span: list.count,
+            });
+        }
+        list.debug_check(true);
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::test_util::render;
+    use crate::{Plain, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::ErrorKind;
+
+    #[mockalloc::test]
+    fn round_trip() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..300 {
+            let key: u32 = rng.gen_range(0..1000);
+            list.insert(key, format!("value {key}")).ok();
+        }
+
+        let mut bytes = Vec::new();
+        list.write_snapshot(&mut bytes, &Plain, &Plain).unwrap();
+        let read = SkipList::read_snapshot(
+            Gen::standard(SmallRng::from_entropy()),
+            bytes.as_slice(),
+            &Plain,
+            &Plain,
+        )
+        .unwrap();
+        assert_eq!(read.check_invariants(), Ok(()));
+        assert!(read.iter().eq(list.iter()));
+    }
+
+    #[mockalloc::test]
+    fn structured() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..300 {
+            let key: u32 = rng.gen_range(0..1000);
+            list.insert(key, key as u64).ok();
+        }
+        for key in 0..900 {
+            list.remove(&key).ok();
+        }
+
+        let mut bytes = Vec::new();
+        list.write_structured_snapshot(&mut bytes, &Plain, &Plain)
+            .unwrap();
+        let read =
+            SkipList::read_structured_snapshot(|| false, bytes.as_slice(), &Plain, &Plain).unwrap();
+        assert_eq!(render(&read), render(&list));
+        check_spans(&read);
+        assert!(read.iter().eq(list.iter()));
+
+        let mut again = Vec::new();
+        read.write_structured_snapshot(&mut again, &Plain, &Plain)
+            .unwrap();
+        assert_eq!(again, bytes);
+
+        let read = |bytes: &[u8]| {
+            SkipList::<u32, u64, _>::read_structured_snapshot(|| false, bytes, &Plain, &Plain)
+                .err()
+                .map(|e| e.kind())
+        };
+        let mut flat = bytes.clone();
+        flat[20] = 0;
+        assert_eq!(read(&flat), Some(ErrorKind::InvalidData));
+        assert_eq!(
+            read(&bytes[..bytes.len() - 1]),
+            Some(ErrorKind::UnexpectedEof)
+        );
+    }
+
+    // snapshot，形を残すsnapshot，exportのstreamは，どれも他の読み手に渡すと拒まれる．
+    #[mockalloc::test]
+    fn distinct_formats() {
+        let mut list = SkipList::new(|| false);
+        for key in [1u8, 2] {
+            list.insert(key, key).unwrap();
+        }
+        let mut plain = Vec::new();
+        list.write_snapshot(&mut plain, &Plain, &Plain).unwrap();
+        let mut structured = Vec::new();
+        list.write_structured_snapshot(&mut structured, &Plain, &Plain)
+            .unwrap();
+        let mut stream = Vec::new();
+        list.export(&mut stream, &Plain, &Plain).unwrap();
+
+        type List = SkipList<u8, u8, fn() -> bool>;
+        type Reader = fn(&[u8]) -> std::io::Result<List>;
+        fn never() -> bool {
+            false
+        }
+        let readers: [Reader; 3] = [
+            |bytes| List::read_snapshot(never, bytes, &Plain, &Plain),
+            |bytes| List::read_structured_snapshot(never, bytes, &Plain, &Plain),
+            |bytes| List::import(never, bytes, &Plain, &Plain),
+        ];
+        for (i, read) in readers.iter().enumerate() {
+            for (j, bytes) in [&plain, &structured, &stream].into_iter().enumerate() {
+                let result = read(bytes);
+                if i == j {
+                    assert!(result.unwrap().iter().eq(list.iter()));
+                } else {
+                    assert_eq!(result.err().map(|e| e.kind()), Some(ErrorKind::InvalidData));
+                }
+            }
+        }
+    }
+
+    #[mockalloc::test]
+    pub(crate) fn invalid() {
+        let mut list = SkipList::new(|| false);
+        for key in [1u8, 2] {
+            list.insert(key, key).unwrap();
+        }
+        let mut bytes = Vec::new();
+        list.write_snapshot(&mut bytes, &Plain, &Plain).unwrap();
+
+        let read =
+            |bytes: &[u8]| SkipList::<u8, u8, _>::read_snapshot(|| false, bytes, &Plain, &Plain);
+        assert_eq!(
+            read(&bytes[..bytes.len() - 1]).err().map(|e| e.kind()),
+            Some(ErrorKind::UnexpectedEof)
+        );
+
+        // 2つのkeyを入れ替える．key 1はmagic，数，長さの後の20 byte目にある．
+        let mut swapped = bytes.clone();
+        swapped.swap(20, 38);
+        assert_eq!(
+            read(&swapped).err().map(|e| e.kind()),
+            Some(ErrorKind::InvalidData)
+        );
+
+        let mut magic = bytes;
+        magic[0] = b'X';
+        assert_eq!(
+            read(&magic).err().map(|e| e.kind()),
+            Some(ErrorKind::InvalidData)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::{node::Node, Generator, InsertError, SkipList};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EntryId(u64);
+
+type EntryNode<K, V> = Node<K, (V, u64)>;
+
+// insertで返すEntryIdから，探索せずにnodeを直接引く．nodeは取り除くまで動かないので，
+// 他のkeyを挿入や削除してもEntryIdはそのまま使える．取り除いたentryのEntryIdはNoneを返す．
+// removeはnodeのkeyで前後をつなぎ直すので，O(log n)かかる．
+pub struct StableSkipList<K: Ord, V, G: Generator<bool>> {
+    list: SkipList<K, (V, u64), G>,
+    handles: HashMap<u64, EntryNode<K, V>>,
+    seq: u64,
+}
+
+impl<K: Ord, V, G: Generator<bool>> StableSkipList<K, V, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+            handles: HashMap::new(),
+            seq: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Result<EntryId, InsertError<K, V>> {
+        let seq = self.seq;
+        let (node, _) = self
+            .list
+            .insert_by(|next, _, key| next.cmp(key), key, (value, seq))
+            .map_err(|(key, (value, _))| InsertError::Occupied(key, value))?;
+        self.seq += 1;
+        self.handles.insert(seq, node);
+        Ok(EntryId(seq))
+    }
+
+    pub fn get(&self, id: EntryId) -> Option<(&K, &V)> {
+        let node = self.handles.get(&id.0)?;
+        Some((node.key(), &node.value().0))
+    }
+
+    pub fn get_mut(&mut self, id: EntryId) -> Option<(&K, &mut V)> {
+        let node = self.handles.get(&id.0)?;
+        Some((node.key(), &mut node.value_mut().0))
+    }
+
+    pub fn remove(&mut self, id: EntryId) -> Option<(K, V)> {
+        let node = self.handles.remove(&id.0)?;
+        let key = node.key();
+        let removed = self.list.remove_by(|next, _| next.cmp(key)).ok()?;
+        let (key, (value, _)) = removed.dispose();
+        Some((key, value))
+    }
+
+    pub fn id_of(&self, key: &K) -> Option<EntryId> {
+        let (_, seq) = self.list.search(key)?;
+        Some(EntryId(*seq))
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        let (value, _) = self.list.search(key)?;
+        Some(value)
+    }
+
+    pub fn remove_key(&mut self, key: &K) -> Option<(K, V)> {
+        let (key, (value, seq)) = self.list.remove(key).ok()?;
+        self.handles.remove(&seq);
+        Some((key, value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntryId, &K, &V)> {
+        self.list
+            .iter()
+            .map(|(key, (value, seq))| (EntryId(*seq), key, value))
+    }
+}
+
+// handlesはlistが所有するnodeだけを指すので，SkipListと同じくSend/Syncにできる．
+unsafe impl<K: Ord + Send, V: Send, G: Generator<bool> + Send> Send for StableSkipList<K, V, G> {}
+unsafe impl<K: Ord + Sync, V: Sync, G: Generator<bool> + Sync> Sync for StableSkipList<K, V, G> {}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::StableSkipList;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[mockalloc::test]
+    fn stable_ids() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = StableSkipList::new(Gen::standard(SmallRng::from_entropy()));
+        let ids: Vec<_> = (0..100).map(|k| list.insert(k * 2, k).unwrap()).collect();
+        assert!(list.insert(10, 0).is_err());
+
+        // 他のkeyを出し入れしても，idは同じentryを指す．
+        for _ in 0..500 {
+            let key = rng.gen_range(0..100) * 2 + 1;
+            if list.insert(key, -1).is_err() {
+                list.remove_key(&key).unwrap();
+            }
+        }
+        for (k, id) in ids.iter().enumerate() {
+            assert_eq!(list.get(*id), Some((&(k as i32 * 2), &(k as i32))));
+        }
+        *list.get_mut(ids[3]).unwrap().1 = 300;
+        assert_eq!(list.search(&6), Some(&300));
+        assert_eq!(list.id_of(&6), Some(ids[3]));
+
+        assert_eq!(list.remove(ids[3]), Some((6, 300)));
+        assert_eq!(list.remove(ids[3]), None);
+        assert_eq!(list.get(ids[3]), None);
+        assert_eq!(list.remove_key(&8), Some((8, 4)));
+        assert_eq!(list.get(ids[4]), None);
+        assert_eq!(list.id_of(&8), None);
+        assert!(list
+            .iter()
+            .filter(|(_, k, _)| *k % 2 == 0)
+            .all(|(id, k, _)| list.get(id).unwrap().0 == k));
+        assert_eq!(list.count(), list.iter().count());
+    }
+
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<StableSkipList<i32, String, fn() -> bool>>();
+    }
+}
@@ -0,0 +1,155 @@
+use std::mem::size_of;
+
+use crate::{node::Link, Generator, SkipList};
+
+// per_level[l]はlevel lに現れるnodeの数，towers[h - 1]は高さhのnodeの数．
+// 探索路の長さは，headからそのnodeに着くまでに辿るlinkの数．
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub height: usize,
+    pub count: usize,
+    pub per_level: Vec<usize>,
+    pub towers: Vec<usize>,
+    pub average_search_path: f64,
+    pub max_search_path: usize,
+}
+
+// nodeの確保した大きさの内訳と，headとtailsのVecが確保している大きさ．
+// overheadはtowerの高さとpadding．keyやvalueがさらに持つheapの領域は数えない．
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub keys: usize,
+    pub values: usize,
+    pub towers: usize,
+    pub overhead: usize,
+    pub head: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.keys + self.values + self.towers + self.overhead + self.head
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // level 0を一度辿って集計する．
+    // nodeの最上段のlinkは，それより高いtowerを持つ直前のnodeから張られているので，
+    // 探索路の長さはそのnodeの長さに1を足したものになる．
+    pub fn stats(&self) -> Stats {
+        let height = self.height();
+        let mut per_level = vec![0; height];
+        let mut towers = vec![0; height];
+        // 各levelで最後に見たnodeの探索路の長さ．
+        let mut paths = vec![0; height];
+        let mut total = 0;
+        let mut max = 0;
+
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            let level = n.level();
+            let path = paths[level - 1] + 1;
+            towers[level - 1] += 1;
+            for (count, last) in per_level[..level].iter_mut().zip(&mut paths[..level]) {
+                *count += 1;
+                *last = path;
+            }
+            total += path;
+            max = max.max(path);
+            node = n.nexts()[0].next;
+        }
+
+        Stats {
+            height,
+            count: self.count,
+            per_level,
+            towers,
+            average_search_path: match self.count {
+                0 => 0.0,
+                count => total as f64 / count as f64,
+            },
+            max_search_path: max,
+        }
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let link = size_of::<Link<K, V>>();
+        let mut usage = MemoryUsage {
+            keys: self.count * size_of::<K>(),
+            values: self.count * size_of::<V>(),
+            towers: 0,
+            overhead: 0,
+            head: (self.nodes.capacity() + self.tails.capacity()) * link,
+        };
+
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            let tower = n.level() * link;
+            usage.towers += tower;
+            usage.overhead += n.allocated_size() - tower - size_of::<K>() - size_of::<V>();
+            node = n.nexts()[0].next;
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[mockalloc::test]
+    fn flat() {
+        let mut list = SkipList::new(|| false);
+        for key in 0..4 {
+            list.insert(key, ()).unwrap();
+        }
+        let stats = list.stats();
+        assert_eq!(stats.height, 1);
+        assert_eq!(stats.per_level, [4]);
+        assert_eq!(stats.towers, [4]);
+        assert_eq!(stats.average_search_path, 2.5);
+        assert_eq!(stats.max_search_path, 4);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[mockalloc::test]
+    fn memory_usage() {
+        let mut list = SkipList::new(|| false);
+        for key in 0..10u64 {
+            list.insert(key, key as u32).unwrap();
+        }
+        // 1つのnodeはkey 8，value 4，padding 4，level 8，link 16で40 byte．
+        let usage = list.memory_usage();
+        assert_eq!(usage.keys, 80);
+        assert_eq!(usage.values, 40);
+        assert_eq!(usage.towers, 160);
+        assert_eq!(usage.overhead, 120);
+        assert_eq!(usage.total(), 400 + usage.head);
+        assert!(usage.head >= 2 * 16);
+    }
+
+    #[mockalloc::test]
+    fn random() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        assert_eq!(list.stats().max_search_path, 0);
+        assert_eq!(list.height(), 1);
+        for key in 0..1000 {
+            list.insert(key, ()).unwrap();
+        }
+        // levelはcountのbit長までしか伸びない．
+        assert!(list.height() <= 10);
+
+        let stats = list.stats();
+        assert_eq!(stats.height, list.height());
+        assert_eq!(stats.count, 1000);
+        assert_eq!(stats.per_level[0], 1000);
+        assert!(stats.per_level.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(stats.towers.iter().sum::<usize>(), 1000);
+        for (level, count) in stats.per_level.iter().enumerate() {
+            assert_eq!(stats.towers[level..].iter().sum::<usize>(), *count);
+        }
+        assert!(stats.average_search_path <= stats.max_search_path as f64);
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    snapshot::{invalid, read_entry, write_entry},
+    Codec, Generator, SkipList,
+};
+
+// snapshotと違い数を先に書かず，entryごとに続きがあるかの1 byteを置き，ENDで終える．
+// 読み手はENDで止まるので，socketのように閉じられない入力からも読める．
+const MAGIC: [u8; 4] = *b"SKS1";
+const MORE: u8 = 1;
+const END: u8 = 0;
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // entryを1つずつencodeして書くので，手元に持つのは1 entry分だけ．
+    pub fn export(
+        &self,
+        mut out: impl Write,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+    ) -> io::Result<()> {
+        out.write_all(&MAGIC)?;
+        let mut buf = Vec::new();
+        for entry in self.iter() {
+            out.write_all(&[MORE])?;
+            write_entry(&mut out, &mut buf, entry, keys, values)?;
+        }
+        out.write_all(&[END])?;
+        out.flush()
+    }
+
+    // 昇順ならappendでつなぐだけで済み，そうでなければ挿入する．keyの重複はInvalidData．
+    // ENDより後ろは読まない．
+    pub fn import(
+        gen: G,
+        mut input: impl Read,
+        keys: &impl Codec<K>,
+        values: &impl Codec<V>,
+    ) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a skip list stream"));
+        }
+
+        let mut list = Self::new(gen);
+        let mut buf = Vec::new();
+        loop {
+            let mut tag = [0];
+            input.read_exact(&mut tag)?;
+            match tag[0] {
+                MORE => {}
+                END => return Ok(list),
+                _ => return Err(invalid("unknown stream tag")),
+            }
+            let (key, value) = read_entry(&mut input, &mut buf, keys, values)?;
+            list.append(key, value)
+                .map_err(|_| invalid("duplicate key in stream"))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{Plain, SkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::{self, ErrorKind, Read};
+
+    // 1 byteずつしか返さない入力．
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            let Some(out) = buf.first_mut() else {
+                return Ok(0);
+            };
+            *out = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    #[mockalloc::test]
+    fn round_trip() {
+        let mut rng = SmallRng::from_entropy();
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..300 {
+            let key: u64 = rng.gen_range(0..1000);
+            list.insert(key, vec![key as u8; key as usize % 7]).ok();
+        }
+
+        let mut bytes = Vec::new();
+        list.export(&mut bytes, &Plain, &Plain).unwrap();
+        // ENDの後ろに続くものは読まない．
+        bytes.extend_from_slice(b"trailing");
+        let mut input = Trickle(&bytes);
+        let read = SkipList::import(
+            Gen::standard(SmallRng::from_entropy()),
+            &mut input,
+            &Plain,
+            &Plain,
+        )
+        .unwrap();
+        assert_eq!(read.check_invariants(), Ok(()));
+        assert!(read.iter().eq(list.iter()));
+        assert_eq!(input.0, b"trailing");
+    }
+
+    #[mockalloc::test]
+    fn unordered() {
+        let mut bytes = b"SKS1".to_vec();
+        for key in [3u8, 1, 2] {
+            bytes.push(1);
+            bytes.extend_from_slice(&1u64.to_le_bytes());
+            bytes.push(key);
+            bytes.extend_from_slice(&0u64.to_le_bytes());
+        }
+        bytes.push(0);
+
+        let import =
+            |bytes: &[u8]| SkipList::<u8, Vec<u8>, _>::import(|| false, bytes, &Plain, &Plain);
+        let list = import(&bytes).unwrap();
+        assert!(list.iter().map(|(k, _)| *k).eq([1, 2, 3]));
+
+        let kind = import(&bytes[..bytes.len() - 1]).err().map(|e| e.kind());
+        assert_eq!(kind, Some(ErrorKind::UnexpectedEof));
+
+        let mut duplicate = bytes.clone();
+        // 2つ目のentryのkeyを1つ目と同じにする．
+        duplicate[31] = 3;
+        let kind = import(&duplicate).err().map(|e| e.kind());
+        assert_eq!(kind, Some(ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fmt::{self, Debug, Write},
+    iter::{repeat_n, repeat_with},
+};
+
+use crate::{node::Node, Generator, SkipList};
+
+impl<K: Ord + Debug, V, G: Generator<bool>> SkipList<K, V, G> {
+    // 列をnodeに，行をlevelにとり，各linkの指すkeyを並べる．最下段はnode自身のkey．
+    pub fn fmt_structure(&self, out: &mut impl Write) -> fmt::Result {
+        let mut forwards = self.nodes.as_slice();
+        let mut lines: Vec<_> = repeat_with(String::new).take(forwards.len()).collect();
+        let mut baseline = String::new();
+        let mut current_node: Option<Node<K, V>> = None;
+        let align = 10;
+        loop {
+            match current_node {
+                Some(n) => write!(baseline, "{:<align$}", format!("{:?}", n.key()))?,
+                None => baseline.extend(repeat_n(' ', align)),
+            }
+
+            for (no, node) in forwards.iter().enumerate() {
+                write!(
+                    lines[no],
+                    "{:<align$}",
+                    format!("{:?}", node.next.take().map(|e| e.key()))
+                )?;
+            }
+
+            for line in &mut lines[forwards.len()..] {
+                line.extend(repeat_n(' ', align));
+            }
+
+            let Some(next) = forwards[0].next.take() else {
+                break;
+            };
+            current_node = Some(next);
+            forwards = next.nexts();
+        }
+
+        writeln!(out, "┌{:─<x$}┐", "", x = baseline.len())?;
+        for line in lines.iter().rev() {
+            writeln!(out, "│{}│", line)?;
+        }
+        writeln!(out, "├{:─<x$}┤", "", x = baseline.len())?;
+        writeln!(out, "│{}│", baseline)?;
+        writeln!(out, "└{:─<x$}┘", "", x = baseline.len())
+    }
+
+    pub fn print_structure(&self) {
+        let mut out = String::new();
+        self.fmt_structure(&mut out).unwrap();
+        print!("{out}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SkipList;
+
+    #[mockalloc::test]
+    fn fmt_structure() {
+        let mut list = SkipList::new(|| false);
+        for key in [2, 1] {
+            list.insert(key, ()).unwrap();
+        }
+        let mut out = String::new();
+        list.fmt_structure(&mut out).unwrap();
+        let expected = [
+            "┌──────────────────────────────┐",
+            "│Some(1)   Some(2)   None      │",
+            "├──────────────────────────────┤",
+            "│          1         2         │",
+            "└──────────────────────────────┘",
+        ];
+        assert!(out.lines().eq(expected));
+    }
+}
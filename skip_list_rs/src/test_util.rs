@@ -0,0 +1,146 @@
+use std::{
+    fmt::{Debug, Write},
+    vec,
+};
+
+use crate::{
+    node::{MaybeNode, Node},
+    Generator, SkipList,
+};
+
+// 与えた高さの列を順に返すGenerator．高さlはl - 1回のtrueと1回のfalseになる．列が尽きたら高さ1を返す．
+// listはtowerが高さの上限(挿入前のentryの数のbit長)に届くとGeneratorを呼ばないので，上限未満の高さだけを与える．
+// 上限以上を与えると，残りは次のnodeの高さとして読まれる．最初の2つのentryはGeneratorを呼ばずに高さ1になる．
+// 決まった形のlistを作るだけならfrom_levelsを使う．
+pub struct Scripted {
+    levels: vec::IntoIter<usize>,
+    target: Option<usize>,
+    climbed: usize,
+}
+
+impl Scripted {
+    pub fn new(levels: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            levels: levels.into_iter().collect::<Vec<_>>().into_iter(),
+            target: None,
+            climbed: 0,
+        }
+    }
+}
+
+impl Generator<bool> for Scripted {
+    fn gen(&mut self) -> bool {
+        let target = *self
+            .target
+            .get_or_insert_with(|| self.levels.next().unwrap_or(1));
+        if self.climbed + 1 < target {
+            self.climbed += 1;
+            return true;
+        }
+        self.target = None;
+        self.climbed = 0;
+        false
+    }
+}
+
+// 各entryを与えた高さのnodeにして並べる．entriesはkeyの昇順に並び，高さは1以上であること．
+pub fn from_levels<K: Ord, V, G: Generator<bool>>(
+    gen: G,
+    entries: impl IntoIterator<Item = (K, V, usize)>,
+) -> SkipList<K, V, G> {
+    let mut list = SkipList::new(gen);
+    for (key, value, level) in entries {
+        assert!(level > 0, "level must be at least 1");
+        if let Some(last) = list.tails[0].next.take() {
+            assert!(last.key() < &key, "entries must be sorted by key");
+        }
+        let node = Node::new(key, value, level);
+        list.allocated(node);
+        list.push_back(node);
+    }
+    list
+}
+
+// 各linkのspanと各levelのtailsが，level 0での実際の位置と一致するか確かめる．
+// keyの順序は見ないので，SkipVecのようにkeyで並べないlistにも使える．
+pub fn assert_structure<K: Ord, V, G: Generator<bool>>(list: &SkipList<K, V, G>) {
+    assert_eq!(list.check_structure(), Ok(()));
+    let mut positions = vec![MaybeNode::null()];
+    let mut node = list.nodes[0].next;
+    while let Some(n) = node.take() {
+        positions.push(node);
+        node = n.nexts()[0].next;
+    }
+    let pos = |node: MaybeNode<K, V>| positions.iter().position(|e| *e == node).unwrap();
+
+    let mut towers = vec![(0, list.nodes.as_slice())];
+    towers.extend(
+        positions[1..]
+            .iter()
+            .map(|e| e.take().unwrap().nexts())
+            .enumerate()
+            .map(|(i, tower)| (i + 1, tower)),
+    );
+    let count = positions.len() - 1;
+    assert_eq!(list.count, count);
+    assert_eq!(list.tails.len(), list.nodes.len());
+    for (from, tower) in towers {
+        for (level, link) in tower.iter().enumerate() {
+            if link.next.is_null() {
+                assert_eq!(link.span, 0);
+                // 各levelの最後のnodeはtailsと一致する．
+                assert!(list.tails[level].next == positions[from]);
+                assert_eq!(list.tails[level].span, count - from);
+            } else {
+                assert_eq!(link.span, pos(link.next) - from);
+            }
+        }
+    }
+}
+
+// 上のlevelから1行ずつ，そのlevelに届いているnodeのkeyを並べる．形が同じなら同じ文字列になる．
+pub fn render<K: Ord + Debug, V, G: Generator<bool>>(list: &SkipList<K, V, G>) -> String {
+    let mut out = String::new();
+    for level in (0..list.height()).rev() {
+        write!(out, "{level}:").unwrap();
+        for (key, _) in list.level_iter(level) {
+            write!(out, " {key:?}").unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{assert_structure, from_levels, render, Scripted};
+    use crate::{Generator, SkipList};
+
+    #[mockalloc::test]
+    fn from_levels_and_render() {
+        let list = from_levels(
+            || false,
+            [(1, 'a', 1), (2, 'b', 3), (3, 'c', 1), (5, 'd', 2)],
+        );
+        assert_structure(&list);
+        assert_eq!(list.check_invariants(), Ok(()));
+        assert_eq!(render(&list), "2: 2\n1: 2 5\n0: 1 2 3 5\n");
+
+        let empty = SkipList::<i32, (), _>::new(|| false);
+        assert_eq!(render(&empty), "0:\n");
+    }
+
+    #[mockalloc::test]
+    fn scripted() {
+        let mut gen = Scripted::new([3, 1, 2]);
+        let bools: Vec<_> = (0..7).map(|_| gen.gen()).collect();
+        assert_eq!(bools, [true, true, false, false, true, false, false]);
+
+        // 上限に届かない高さだけを与えれば，そのとおりに積まれる．
+        let mut list = SkipList::new(Scripted::new([1, 1, 2, 1, 2, 1]));
+        for key in 0..8 {
+            list.insert(key, ()).unwrap();
+        }
+        assert_eq!(render(&list), "1: 4 6\n0: 0 1 2 3 4 5 6 7\n");
+    }
+}
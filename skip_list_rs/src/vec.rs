@@ -0,0 +1,212 @@
+use std::cmp::Ordering;
+
+use crate::{
+    iter::{IntoIter, Iter, IterMut},
+    Generator, SkipList,
+};
+
+// keyを持たないSkipListとして，位置(span)だけで辿る．
+pub struct SkipVec<T, G: Generator<bool>> {
+    list: SkipList<(), T, G>,
+}
+
+impl<T, G: Generator<bool>> SkipVec<T, G> {
+    pub fn new(gen: G) -> Self {
+        Self {
+            list: SkipList::new(gen),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let node = self.list.node_at(index.checked_add(1)?)?;
+        Some(node.value())
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let node = self.list.node_at(index.checked_add(1)?)?;
+        Some(node.value_mut())
+    }
+
+    pub fn insert_at(&mut self, index: usize, value: T) {
+        let count = self.count();
+        assert!(
+            index <= count,
+            "insertion index (is {index}) should be <= count (is {count})"
+        );
+
+        // 位置がindex + 1未満のnodeの後ろに挿入する．
+        let result = self.list.insert_by(
+            |_, pos, _| {
+                if pos <= index {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            },
+            (),
+            value,
+        );
+        debug_assert!(result.is_ok());
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.insert_at(self.count(), value);
+    }
+
+    pub fn remove_at(&mut self, index: usize) -> Option<T> {
+        let target = index.checked_add(1)?;
+        let node = self.list.remove_by(|_, pos| pos.cmp(&target)).ok()?;
+        let (_, value) = node.dispose();
+        Some(value)
+    }
+
+    pub fn iter(&self) -> VecIter<'_, T> {
+        VecIter {
+            iter: self.list.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> VecIterMut<'_, T> {
+        VecIterMut {
+            iter: self.list.iter_mut(),
+        }
+    }
+}
+
+impl<T, G: Generator<bool>> IntoIterator for SkipVec<T, G> {
+    type Item = T;
+
+    type IntoIter = VecIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        VecIntoIter {
+            iter: self.list.into_iter(),
+        }
+    }
+}
+
+pub struct VecIter<'a, T: 'a> {
+    iter: Iter<'a, (), T>,
+}
+
+impl<'a, T: 'a> Iterator for VecIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(_, value)| value)
+    }
+}
+
+/// ```compile_fail
+/// let mut vec = skip_list_rs::SkipVec::<&'static str, _>::new(|| false);
+/// vec.push("static");
+/// let s = String::from("short");
+/// *vec.iter_mut().next().unwrap() = s.as_str();
+/// drop(s);
+/// println!("{}", vec.get(0).unwrap());
+/// ```
+pub struct VecIterMut<'a, T: 'a> {
+    iter: IterMut<'a, (), T>,
+}
+
+impl<'a, T: 'a> Iterator for VecIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(_, value)| value)
+    }
+}
+
+pub struct VecIntoIter<T> {
+    iter: IntoIter<(), T>,
+}
+
+impl<T> Iterator for VecIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::{check_spans, Gen};
+    use crate::SkipVec;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[mockalloc::test]
+    fn positional() {
+        let mut vec = SkipVec::new(Gen::standard(SmallRng::from_entropy()));
+
+        vec.push('b');
+        vec.push('d');
+        vec.insert_at(0, 'a');
+        vec.insert_at(2, 'c');
+        vec.insert_at(4, 'e');
+
+        assert!(vec.iter().copied().eq("abcde".chars()));
+        assert_eq!(vec.get(0), Some(&'a'));
+        assert_eq!(vec.get(4), Some(&'e'));
+        assert_eq!(vec.get(5), None);
+
+        *vec.get_mut(1).unwrap() = 'B';
+        assert_eq!(vec.remove_at(2), Some('c'));
+        assert_eq!(vec.remove_at(4), None);
+        assert_eq!(vec.get(usize::MAX), None);
+        assert_eq!(vec.get_mut(usize::MAX), None);
+        assert_eq!(vec.remove_at(usize::MAX), None);
+        assert!(vec.into_iter().eq("aBde".chars()));
+    }
+
+    #[mockalloc::test]
+    fn random_against_vec() {
+        let mut rng = SmallRng::from_entropy();
+        let mut vec = SkipVec::new(Gen::standard(SmallRng::from_entropy()));
+        let mut model = Vec::new();
+
+        for op in 0..500 {
+            if model.is_empty() || rng.gen_bool(0.6) {
+                let index = rng.gen_range(0..=model.len());
+                vec.insert_at(index, op);
+                model.insert(index, op);
+            } else {
+                let index = rng.gen_range(0..model.len());
+                assert_eq!(vec.remove_at(index), Some(model.remove(index)));
+            }
+            check_spans(&vec.list);
+        }
+
+        assert_eq!(vec.count(), model.len());
+        assert!(vec.iter().eq(model.iter()));
+        for (index, value) in model.iter().enumerate() {
+            assert_eq!(vec.get(index), Some(value));
+        }
+    }
+
+    #[mockalloc::test]
+    fn zero_sized_values() {
+        let mut vec = SkipVec::new(Gen::standard(SmallRng::from_entropy()));
+        for _ in 0..100 {
+            vec.push(());
+        }
+        check_spans(&vec.list);
+        assert_eq!(vec.get(99), Some(&()));
+        assert_eq!(vec.remove_at(50), Some(()));
+        assert_eq!(vec.count(), 99);
+        assert_eq!(vec.into_iter().count(), 99);
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_bounds() {
+        let mut vec = SkipVec::new(Gen::standard(SmallRng::from_entropy()));
+        vec.push(0);
+        vec.insert_at(2, 1);
+    }
+}
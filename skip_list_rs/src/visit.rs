@@ -0,0 +1,59 @@
+use crate::{Generator, SkipList};
+
+// heightはそのnodeのtowerの高さ．
+pub trait Visitor<K, V> {
+    fn visit(&mut self, key: &K, value: &V, height: usize);
+}
+
+impl<K, V, F: FnMut(&K, &V, usize)> Visitor<K, V> for F {
+    fn visit(&mut self, key: &K, value: &V, height: usize) {
+        self(key, value, height)
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // keyの順に，すべてのnodeをvisitorに渡す．
+    pub fn visit(&self, mut visitor: impl Visitor<K, V>) {
+        let mut node = self.nodes[0].next;
+        while let Some(n) = node.take() {
+            visitor.visit(n.key(), n.value(), n.level());
+            node = n.nexts()[0].next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{SkipList, Visitor};
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    // towerの高さごとの数を数える．
+    struct Histogram(Vec<usize>);
+
+    impl<K, V> Visitor<K, V> for &mut Histogram {
+        fn visit(&mut self, _: &K, _: &V, height: usize) {
+            if self.0.len() < height {
+                self.0.resize(height, 0);
+            }
+            self.0[height - 1] += 1;
+        }
+    }
+
+    #[mockalloc::test]
+    fn visit() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..100 {
+            list.insert(key, key * 2).unwrap();
+        }
+
+        let mut entries = Vec::new();
+        list.visit(|k: &i32, v: &i32, _| entries.push((*k, *v)));
+        assert!(entries.into_iter().eq((0..100).map(|k| (k, k * 2))));
+
+        let mut histogram = Histogram(Vec::new());
+        list.visit(&mut histogram);
+        assert_eq!(histogram.0, list.stats().towers[..histogram.0.len()]);
+    }
+}
@@ -0,0 +1,222 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    snapshot::{invalid, read_chunk, write_chunk, write_entry},
+    Codec, Generator, SkipList,
+};
+
+// 記録は種類の1 byteに続けて，keyとvalue(Putのみ)を長さつきで並べる．長さはsnapshotと同じくu64．
+const PUT: u8 = 1;
+const DELETE: u8 = 2;
+
+// 変更をlogに書いてからlistに反映する．書き込みに失敗したときはlistを変えずにErrを返す．
+// 途中まで書かれた記録が残りうるので，そのlogには以後書き足さず，checkpointで取り替える．
+pub struct WalSkipList<K: Ord, V, G: Generator<bool>, W: Write, C: Codec<K> + Codec<V>> {
+    list: SkipList<K, V, G>,
+    log: W,
+    codec: C,
+    buf: Vec<u8>,
+}
+
+impl<K: Ord, V, G: Generator<bool>, W: Write, C: Codec<K> + Codec<V>> WalSkipList<K, V, G, W, C> {
+    // listはlogに書かれていない状態から始めてよい．その場合は先にcheckpointでsnapshotを取る．
+    pub fn new(list: SkipList<K, V, G>, log: W, codec: C) -> Self {
+        Self {
+            list,
+            log,
+            codec,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn list(&self) -> &SkipList<K, V, G> {
+        &self.list
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.count()
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        self.list.search(key)
+    }
+
+    // keyがあればvalueを置き換え，前のvalueを返す．
+    pub fn put(&mut self, key: K, value: V) -> io::Result<Option<V>> {
+        self.log.write_all(&[PUT])?;
+        let entry = (&key, &value);
+        write_entry(
+            &mut self.log,
+            &mut self.buf,
+            entry,
+            &self.codec,
+            &self.codec,
+        )?;
+        self.log.flush()?;
+
+        Ok(put(&mut self.list, key, value))
+    }
+
+    // keyがなければ何も書かない．
+    pub fn delete(&mut self, key: &K) -> io::Result<Option<(K, V)>> {
+        if !self.list.contains_key(key) {
+            return Ok(None);
+        }
+        self.log.write_all(&[DELETE])?;
+        self.buf.clear();
+        self.codec.encode(key, &mut self.buf);
+        write_chunk(&mut self.log, &self.buf)?;
+        self.log.flush()?;
+
+        Ok(self.list.remove(key).ok())
+    }
+
+    // 今の中身をsnapshotに書き，以後の記録を空のlogへ向ける．前のlogを返すので，呼び出し側で捨てる．
+    // snapshotを書けなかったときはlogを取り替えない．
+    pub fn checkpoint(&mut self, snapshot: impl Write, log: W) -> io::Result<W> {
+        self.list
+            .write_snapshot(snapshot, &self.codec, &self.codec)?;
+        Ok(std::mem::replace(&mut self.log, log))
+    }
+
+    pub fn into_inner(self) -> (SkipList<K, V, G>, W) {
+        (self.list, self.log)
+    }
+}
+
+fn put<K: Ord, V, G: Generator<bool>>(list: &mut SkipList<K, V, G>, key: K, value: V) -> Option<V> {
+    if list.contains_key(&key) {
+        list.update(&key, |v| std::mem::replace(v, value))
+    } else {
+        list.insert(key, value).ok();
+        None
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    // logの記録を順に反映し，その数を返す．書き込みの途中で落ちたときの，末尾の書きかけの記録は読み捨てる．
+    pub fn replay_log(
+        &mut self,
+        mut log: impl Read,
+        codec: &(impl Codec<K> + Codec<V>),
+    ) -> io::Result<usize> {
+        let mut applied = 0;
+        let mut buf = Vec::new();
+        loop {
+            let mut tag = [0];
+            if log.read(&mut tag)? == 0 {
+                return Ok(applied);
+            }
+            let record = (|| {
+                read_chunk(&mut log, &mut buf)?;
+                let key = codec.decode(&buf)?;
+                match tag[0] {
+                    PUT => {
+                        read_chunk(&mut log, &mut buf)?;
+                        Ok((key, Some(codec.decode(&buf)?)))
+                    }
+                    DELETE => Ok((key, None)),
+                    _ => Err(invalid("unknown log record")),
+                }
+            })();
+            match record {
+                Ok((key, Some(value))) => {
+                    put(self, key, value);
+                }
+                Ok((key, None)) => {
+                    self.remove(&key).ok();
+                }
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(applied),
+                Err(e) => return Err(e),
+            }
+            applied += 1;
+        }
+    }
+
+    // snapshotがあればそれを読み込み，続けてlogを反映する．
+    pub fn recover(
+        gen: G,
+        snapshot: Option<impl Read>,
+        log: impl Read,
+        codec: &(impl Codec<K> + Codec<V>),
+    ) -> io::Result<Self> {
+        let mut list = match snapshot {
+            Some(snapshot) => Self::read_snapshot(gen, snapshot, codec, codec)?,
+            None => Self::new(gen),
+        };
+        list.replay_log(log, codec)?;
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{Plain, SkipList, WalSkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::ErrorKind;
+
+    #[mockalloc::test]
+    fn recover() {
+        let mut rng = SmallRng::from_entropy();
+        let mut wal = WalSkipList::new(
+            SkipList::new(Gen::standard(SmallRng::from_entropy())),
+            Vec::new(),
+            Plain,
+        );
+        let mut snapshot = None;
+        let mut model = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+
+        for round in 0..1000 {
+            let key: u16 = rng.gen_range(0..100);
+            if rng.gen_bool(0.7) {
+                let value: u32 = rng.gen();
+                let expected = model.search(&key).copied();
+                model.remove(&key).ok();
+                model.insert(key, value).unwrap();
+                assert_eq!(wal.put(key, value).unwrap(), expected);
+            } else {
+                let expected = model.remove(&key).ok();
+                assert_eq!(wal.delete(&key).unwrap(), expected);
+            }
+            if round == 500 {
+                let mut bytes = Vec::new();
+                let old = wal.checkpoint(&mut bytes, Vec::new()).unwrap();
+                assert!(!old.is_empty());
+                snapshot = Some(bytes);
+            }
+        }
+
+        let (list, log) = wal.into_inner();
+        let snapshot = snapshot.unwrap();
+        let recovered = SkipList::recover(
+            Gen::standard(SmallRng::from_entropy()),
+            Some(snapshot.as_slice()),
+            log.as_slice(),
+            &Plain,
+        )
+        .unwrap();
+        assert_eq!(recovered.check_invariants(), Ok(()));
+        assert!(recovered.iter().eq(list.iter()));
+        assert!(recovered.iter().eq(model.iter()));
+
+        // 書きかけの記録は読み捨てる．
+        let torn = &log[..log.len() - 1];
+        let mut partial = SkipList::<u16, u32, _>::new(Gen::standard(SmallRng::from_entropy()));
+        let applied = partial.replay_log(torn, &Plain).unwrap();
+        let mut whole = SkipList::<u16, u32, _>::new(Gen::standard(SmallRng::from_entropy()));
+        assert_eq!(
+            whole.replay_log(log.as_slice(), &Plain).unwrap(),
+            applied + 1
+        );
+    }
+
+    #[mockalloc::test]
+    fn unknown_record() {
+        let mut list = SkipList::<u8, u8, _>::new(|| false);
+        let log = [9u8, 1, 0, 0, 0, 0, 0, 0, 0, 7];
+        let kind = list.replay_log(log.as_slice(), &Plain).unwrap_err().kind();
+        assert_eq!(kind, ErrorKind::InvalidData);
+    }
+}
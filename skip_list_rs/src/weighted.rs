@@ -0,0 +1,195 @@
+use crate::{
+    bounded::Evictor, BoundedSkipList, EvictionPolicy, EvictionReason, Generator, InsertError,
+    RemoveError,
+};
+
+pub trait Weigher<K, V> {
+    fn weigh(&self, key: &K, value: &V) -> usize;
+}
+
+impl<K, V, F: Fn(&K, &V) -> usize> Weigher<K, V> for F {
+    fn weigh(&self, key: &K, value: &V) -> usize {
+        self(key, value)
+    }
+}
+
+// entryの数ではなく，weigherで量った重さの和をmax_weight以下に保つ．valueは書き換えられないので，
+// 挿入したときの重さを取り除くときにも使える．溢れた分はpolicyの順に，収まるまで何個でも追い出す．
+pub struct WeightedSkipList<K: Ord, V, W: Weigher<K, V>, G: Generator<bool>> {
+    entries: BoundedSkipList<K, V, G>,
+    weigher: W,
+    weight: usize,
+    max_weight: usize,
+    evictor: Option<Box<Evictor<K, V>>>,
+}
+
+impl<K: Ord, V, W: Weigher<K, V>, G: Generator<bool>> WeightedSkipList<K, V, W, G> {
+    pub fn with_max_weight(
+        max_weight: usize,
+        weigher: W,
+        policy: EvictionPolicy,
+        make_gen: impl FnMut() -> G,
+    ) -> Self {
+        Self {
+            entries: BoundedSkipList::with_max_len(usize::MAX, policy, make_gen),
+            weigher,
+            weight: 0,
+            max_weight,
+            evictor: None,
+        }
+    }
+
+    // 以後，追い出したentryはinsertから返さずにfへ渡す．
+    pub fn on_evict(&mut self, f: impl FnMut(K, V, EvictionReason) + Send + Sync + 'static) {
+        self.evictor = Some(Box::new(f));
+    }
+
+    pub fn count(&self) -> usize {
+        self.entries.count()
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn max_weight(&self) -> usize {
+        self.max_weight
+    }
+
+    pub fn policy(&self) -> EvictionPolicy {
+        self.entries.policy()
+    }
+
+    // 追い出したentryを追い出した順に返す．1つでmax_weightを超えるentryは，最後には自身も追い出される．
+    pub fn insert(&mut self, key: K, value: V) -> Result<Vec<(K, V)>, InsertError<K, V>> {
+        let weight = self.weigher.weigh(&key, &value);
+        if self.entries.insert(key, value)?.is_some() {
+            unreachable!("the inner list is unbounded");
+        }
+        self.weight += weight;
+
+        let mut evicted = Vec::new();
+        while self.weight > self.max_weight {
+            let Some((key, value)) = self.entries.evict() else {
+                unreachable!("the weight of an empty list is zero");
+            };
+            self.weight -= self.weigher.weigh(&key, &value);
+            match &mut self.evictor {
+                Some(evictor) => evictor(key, value, EvictionReason::Capacity),
+                None => evicted.push((key, value)),
+            }
+        }
+        Ok(evicted)
+    }
+
+    pub fn search(&self, key: &K) -> Option<&V> {
+        self.entries.search(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<(K, V), RemoveError> {
+        let (key, value) = self.entries.remove(key)?;
+        self.weight -= self.weigher.weigh(&key, &value);
+        Ok((key, value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::{EvictionPolicy, EvictionReason, InsertError, WeightedSkipList};
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    #[mockalloc::test]
+    fn evict_by_weight() {
+        let mut list = WeightedSkipList::with_max_weight(
+            10,
+            |_: &u32, value: &String| value.len(),
+            EvictionPolicy::Oldest,
+            || Gen::standard(SmallRng::from_entropy()),
+        );
+
+        assert_eq!(list.insert(5, "aaaa".to_string()), Ok(vec![]));
+        assert_eq!(list.insert(3, "bbb".to_string()), Ok(vec![]));
+        assert_eq!(list.insert(8, "cc".to_string()), Ok(vec![]));
+        assert_eq!(list.weight(), 9);
+        assert_eq!(
+            list.insert(8, String::new()),
+            Err(InsertError::Occupied(8, String::new()))
+        );
+        // 重いentryは古いほうから2つ追い出して収める．
+        assert_eq!(
+            list.insert(1, "dddddd".to_string()),
+            Ok(vec![(5, "aaaa".to_string()), (3, "bbb".to_string())])
+        );
+        assert_eq!(list.weight(), 8);
+        assert_eq!(list.remove(&8), Ok((8, "cc".to_string())));
+        assert_eq!(list.weight(), 6);
+        assert_eq!(list.insert(2, String::new()), Ok(vec![]));
+        assert_eq!(
+            list.insert(4, "e".repeat(11)),
+            Ok(vec![
+                (1, "dddddd".to_string()),
+                (2, String::new()),
+                (4, "e".repeat(11))
+            ])
+        );
+        assert_eq!(list.count(), 0);
+        assert_eq!(list.weight(), 0);
+    }
+
+    #[mockalloc::test]
+    fn weight_model() {
+        let mut rng = SmallRng::from_entropy();
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut list = WeightedSkipList::with_max_weight(
+            100,
+            |k: &u32, v: &u32| (k + v) as usize,
+            EvictionPolicy::Largest,
+            || Gen::standard(SmallRng::from_entropy()),
+        );
+        let sink = evicted.clone();
+        list.on_evict(move |key, value, reason| {
+            assert_eq!(reason, EvictionReason::Capacity);
+            sink.lock().unwrap().push((key, value));
+        });
+
+        let mut model = BTreeMap::new();
+        for _ in 0..1000 {
+            let key = rng.gen_range(0..40);
+            if rng.gen_ratio(1, 3) {
+                assert_eq!(list.remove(&key).ok(), model.remove_entry(&key));
+                continue;
+            }
+            let value = rng.gen_range(0..20);
+            if list.insert(key, value).is_err() {
+                continue;
+            }
+            assert!(!model.contains_key(&key));
+            model.insert(key, value);
+            for (key, value) in evicted.lock().unwrap().drain(..) {
+                assert_eq!(model.pop_last(), Some((key, value)));
+            }
+            let weight: u32 = model.iter().map(|(k, v)| k + v).sum();
+            assert_eq!(list.weight(), weight as usize);
+            assert!(list.weight() <= list.max_weight());
+        }
+        assert!(list.iter().eq(model.iter()));
+    }
+
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<WeightedSkipList<u32, String, fn(&u32, &String) -> usize, fn() -> bool>>();
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{iter::Iter, Generator, SkipList};
+
+// 1回のpollでbudget個までfを呼び，残りがあればwakerを起こしてPendingを返す．
+// executorは他のtaskを回してから再びpollするので，大きなlistを辿ってもthreadを占有しない．
+pub struct ForEachYielding<'a, K: Ord, V, F> {
+    iter: Iter<'a, K, V>,
+    budget: usize,
+    f: F,
+}
+
+impl<'a, K: Ord + 'a, V: 'a, F: FnMut(&'a K, &'a V) + Unpin> Future
+    for ForEachYielding<'a, K, V, F>
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        for (key, value) in this.iter.by_ref().take(this.budget) {
+            (this.f)(key, value);
+        }
+        if this.iter.node.is_null() {
+            return Poll::Ready(());
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl<K: Ord, V, G: Generator<bool>> SkipList<K, V, G> {
+    pub fn for_each_yielding<'a, F: FnMut(&'a K, &'a V) + Unpin>(
+        &'a self,
+        budget: usize,
+        f: F,
+    ) -> ForEachYielding<'a, K, V, F> {
+        assert!(budget > 0, "budget must be non-zero");
+        ForEachYielding {
+            iter: self.iter(),
+            budget,
+            f,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::Gen;
+    use crate::SkipList;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct Count(AtomicUsize);
+
+    impl Wake for Count {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[mockalloc::test]
+    fn for_each_yielding() {
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..1000 {
+            list.insert(key, key * 2).unwrap();
+        }
+
+        let wakes = Arc::new(Count(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut seen = Vec::new();
+        let mut future = pin!(list.for_each_yielding(300, |k, v| {
+            assert_eq!(*v, k * 2);
+            seen.push(*k);
+        }));
+        let mut polls = 1;
+        while future.as_mut().poll(&mut cx).is_pending() {
+            polls += 1;
+        }
+        assert_eq!(polls, 4);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 3);
+        assert!(seen.into_iter().eq(0..1000));
+
+        let empty = SkipList::<i32, i32, _>::new(|| false);
+        let future = pin!(empty.for_each_yielding(1, |_, _| unreachable!()));
+        assert_eq!(future.poll(&mut cx), Poll::Ready(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_budget() {
+        let list = SkipList::<i32, i32, _>::new(|| false);
+        let _future = list.for_each_yielding(0, |_, _| {});
+    }
+
+    // multi-threadのexecutorのtaskとして，別のthreadでpollできる．
+    #[test]
+    fn send() {
+        fn check<T: Send>(future: T) -> T {
+            future
+        }
+        let mut list = SkipList::new(Gen::standard(SmallRng::from_entropy()));
+        for key in 0..100 {
+            list.insert(key, key).unwrap();
+        }
+        let sum = AtomicUsize::new(0);
+        let future = check(list.for_each_yielding(30, |_, v| {
+            sum.fetch_add(*v, Ordering::Relaxed);
+        }));
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let waker = Waker::from(Arc::new(Count(AtomicUsize::new(0))));
+                let mut cx = Context::from_waker(&waker);
+                let mut future = pin!(future);
+                while future.as_mut().poll(&mut cx).is_pending() {}
+            });
+        });
+        assert_eq!(sum.into_inner(), (0..100).sum());
+    }
+}
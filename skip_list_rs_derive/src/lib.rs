@@ -0,0 +1,157 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Index, Member};
+
+// fieldの宣言順に比べるPartialEq，Eq，PartialOrd，Ordと，
+// 先頭からi個のfieldの型のtuple(1 <= i < fieldの数)に対するskip_list_rs::KeyPrefixを生成する．
+#[proc_macro_derive(SkipKey)]
+pub fn derive_skip_key(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "SkipKey can only be derived for structs",
+        ));
+    };
+    let (members, types): (Vec<Member>, Vec<_>) = match &data.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|f| (Member::Named(f.ident.clone().unwrap()), f.ty.clone()))
+            .unzip(),
+        Fields::Unnamed(fields) => fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (Member::Unnamed(Index::from(i)), f.ty.clone()))
+            .unzip(),
+        Fields::Unit => (Vec::new(), Vec::new()),
+    };
+
+    let where_clause = input.generics.make_where_clause();
+    for ty in &types {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::core::cmp::Ord));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let prefixes = (1..members.len()).map(|len| {
+        let members = &members[..len];
+        let types = &types[..len];
+        let indices = (0..len).map(Index::from);
+        quote! {
+            impl #impl_generics ::skip_list_rs::KeyPrefix<#name #ty_generics> for (#(#types,)*) #where_clause {
+                fn cmp_key(&self, key: &#name #ty_generics) -> ::core::cmp::Ordering {
+                    #(
+                        match ::core::cmp::Ord::cmp(&key.#members, &self.#indices) {
+                            ::core::cmp::Ordering::Equal => {}
+                            ord => return ord,
+                        }
+                    )*
+                    ::core::cmp::Ordering::Equal
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::core::cmp::PartialEq for #name #ty_generics #where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                ::core::cmp::Ord::cmp(self, other) == ::core::cmp::Ordering::Equal
+            }
+        }
+
+        impl #impl_generics ::core::cmp::Eq for #name #ty_generics #where_clause {}
+
+        impl #impl_generics ::core::cmp::PartialOrd for #name #ty_generics #where_clause {
+            fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                ::core::option::Option::Some(::core::cmp::Ord::cmp(self, other))
+            }
+        }
+
+        impl #impl_generics ::core::cmp::Ord for #name #ty_generics #where_clause {
+            fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                #(
+                    match ::core::cmp::Ord::cmp(&self.#members, &other.#members) {
+                        ::core::cmp::Ordering::Equal => {}
+                        ord => return ord,
+                    }
+                )*
+                ::core::cmp::Ordering::Equal
+            }
+        }
+
+        #(#prefixes)*
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use skip_list_rs::{SkipKey, SkipList};
+
+    #[derive(SkipKey, Debug, Clone, Copy)]
+    struct Key {
+        tenant: u32,
+        timestamp: u64,
+        id: u8,
+    }
+
+    #[derive(SkipKey, Debug)]
+    struct Pair<'a, T>(&'a str, T);
+
+    #[derive(SkipKey)]
+    struct Unit;
+
+    #[test]
+    fn ordering() {
+        let key = |tenant, timestamp, id| Key {
+            tenant,
+            timestamp,
+            id,
+        };
+        assert!(key(1, 9, 9) < key(2, 0, 0));
+        assert!(key(1, 2, 9) < key(1, 3, 0));
+        assert!(key(1, 2, 3) == key(1, 2, 3));
+        assert!(Pair("a", 2) < Pair("b", 1));
+        assert!(Unit == Unit);
+    }
+
+    #[test]
+    fn prefix_scan() {
+        let mut list = SkipList::new(|| false);
+        for tenant in 0..4 {
+            for timestamp in 0..5 {
+                for id in [u8::MIN, 7, u8::MAX] {
+                    let key = Key {
+                        tenant,
+                        timestamp,
+                        id,
+                    };
+                    list.insert(key, ()).unwrap();
+                }
+            }
+        }
+
+        let tenant: Vec<_> = list.range_prefix((2,)).map(|(k, _)| *k).collect();
+        assert_eq!(tenant.len(), 15);
+        assert!(tenant.iter().all(|k| k.tenant == 2));
+
+        let hour: Vec<_> = list.range_prefix((3, 4)).map(|(k, _)| k.id).collect();
+        assert_eq!(hour, [u8::MIN, 7, u8::MAX]);
+        assert_eq!(list.range_prefix((9,)).count(), 0);
+
+        let mut pairs = SkipList::new(|| false);
+        pairs.insert(Pair("a", 1), ()).unwrap();
+        pairs.insert(Pair("b", 2), ()).unwrap();
+        pairs.insert(Pair("b", 3), ()).unwrap();
+        assert_eq!(pairs.range_prefix(("b",)).count(), 2);
+    }
+}